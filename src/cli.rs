@@ -0,0 +1,717 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+use serde_json::json;
+use crate::modules::compliance;
+use crate::modules::dsc::Configuration;
+use crate::modules::firefox::{self, Policies};
+use crate::modules::hooks::{self, HookCatalog};
+use crate::modules::journal::{Journal, RevertScope};
+use crate::modules::lockfile::{self, LockFile};
+use crate::modules::package_list::PackageList;
+use crate::modules::package_manager::{self, Backend};
+use crate::modules::profile::{self, Profile};
+use crate::modules::results::{ApplyReport, ApplyResult};
+use crate::modules::tweaks::{self, Catalog, Host};
+use crate::modules::{packages, uwp};
+use crate::utils::command::{self, CommandRunner};
+use crate::utils::filesystem;
+use crate::utils::progress;
+use crate::utils::registry::{self, RegistryBackend};
+
+/// Всё выполнено (или машина соответствует профилю)
+pub const EXIT_OK: i32 = 0;
+/// Команда выполнена с ошибками или найдены расхождения
+pub const EXIT_FAILURE: i32 = 1;
+/// Неверные аргументы, неизвестный профиль или твик
+pub const EXIT_USAGE: i32 = 2;
+
+pub const USAGE: &str = "\
+Использование: win-tool [команда] [--json]
+
+Без команды запускается интерактивный интерфейс.
+
+Команды:
+  list [tweaks|packages|uwp|profiles]   списки твиков, пакетов, UWP-приложений, профилей
+  status                                текущее состояние всех твиков
+  check <профиль>                       проверить соответствие профилю
+  apply <профиль> [--dry-run]           применить профиль (--dry-run — только показать план)
+  dsc <профиль> [<файл>]                документ winget configure (DSC YAML) по профилю;
+                                        без файла — в stdout
+  revert [--all | --session <id> | --tweak <id> | --after <№>]
+                                        отменить изменения (по умолчанию — последний сеанс)
+  revert --list                         неотменённые изменения из журнала
+  install <id>[@версия]... [--manager <m>]
+                                        установить пакеты (@1.2 — точная версия,
+                                        @>=1.2 — не ниже указанной); m — winget, scoop, choco
+  sync [<лок-файл>]                     привести пакеты к версиям из лок-файла
+  export [<файл>] [--include-versions] [--manager <m>]
+                                        сохранить установленные пакеты в формате winget export
+                                        (для scoop и choco — их собственный экспорт)
+  import [<файл>] [--ignore-versions]   установить недостающие пакеты из файла winget export
+                                        (по умолчанию — Документы\\winget-packages.json)
+  remove-uwp <имя>...                   удалить UWP-приложения
+  firefox-policies [<файл>] [--dry-run] записать policies.json во все установки Firefox
+                                        (по умолчанию — шаблон из data/config_templates/firefox);
+                                        --dry-run — только показать изменения
+  help                                  эта справка
+
+--json поддерживается командами list, status, check, apply, install, sync, import,
+remove-uwp и revert --list. Ход выполнения выводится в stderr, результат — в stdout.
+
+Коды возврата: 0 — успех, 1 — ошибки выполнения или расхождения с профилем,
+2 — неверные аргументы или профиль не найден.";
+
+/// Что выводить командой `list`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListWhat {
+    Tweaks,
+    Packages,
+    Uwp,
+    Profiles,
+}
+
+/// Команда командной строки
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    List(ListWhat),
+    Status,
+    Check { profile: String },
+    Apply { profile: String, dry_run: bool },
+    /// Документ `winget configure` по профилю; без пути — в stdout
+    Dsc { profile: String, path: Option<PathBuf> },
+    Revert { scope: Option<RevertScope> },
+    RevertList,
+    Install { specs: Vec<String>, manager: Backend },
+    /// Синхронизация с лок-файлом; без пути — с лок-файлом по умолчанию
+    Sync { lock: Option<PathBuf> },
+    /// Экспорт установленных пакетов; без пути — в файл по умолчанию
+    Export { path: Option<PathBuf>, include_versions: bool, manager: Backend },
+    Import { path: Option<PathBuf>, ignore_versions: bool },
+    RemoveUwp(Vec<String>),
+    /// Политики Firefox из шаблона; без пути — встроенный шаблон
+    FirefoxPolicies { template: Option<PathBuf>, dry_run: bool },
+    Help,
+}
+
+/// Разобранные аргументы
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub command: Command,
+    pub json: bool,
+}
+
+/// Разбирает аргументы (без имени программы).
+/// `Ok(None)` — команды нет, нужно запустить интерактивный интерфейс.
+pub fn parse(args: &[String]) -> Result<Option<Invocation>, String> {
+    let json = args.iter().any(|a| a == "--json");
+    let mut args: Vec<&str> = args.iter().map(String::as_str).filter(|a| *a != "--json").collect();
+    if args.is_empty() {
+        return Ok(None);
+    }
+    let name = args.remove(0);
+    // Значение --manager не должно попасть в позиционные аргументы
+    let manager = match args.iter().position(|a| *a == "--manager") {
+        Some(i) => {
+            let value = args.get(i + 1).ok_or("после --manager нужно значение")?;
+            let backend = Backend::parse(value).ok_or_else(|| format!("неизвестный менеджер пакетов: {}", value))?;
+            args.drain(i..=i + 1);
+            backend
+        }
+        None => Backend::default(),
+    };
+    let flag = |flag: &str| args.contains(&flag);
+    let positional: Vec<String> = args.iter().filter(|a| !a.starts_with("--")).map(|a| a.to_string()).collect();
+    let single = |what: &str| -> Result<String, String> {
+        match positional.as_slice() {
+            [value] => Ok(value.clone()),
+            _ => Err(format!("команде {} нужен один аргумент: {}", name, what)),
+        }
+    };
+    let option = |flag: &str| -> Result<Option<String>, String> {
+        match args.iter().position(|a| *a == flag) {
+            Some(i) => args
+                .get(i + 1)
+                .map(|v| Some(v.to_string()))
+                .ok_or_else(|| format!("после {} нужно значение", flag)),
+            None => Ok(None),
+        }
+    };
+
+    let path = || -> Result<Option<PathBuf>, String> {
+        match positional.as_slice() {
+            [] => Ok(None),
+            [path] => Ok(Some(PathBuf::from(path))),
+            _ => Err(format!("команде {} нужен не больше одного аргумента: файл", name)),
+        }
+    };
+
+    let command = match name {
+        "list" => Command::List(match positional.first().map(String::as_str) {
+            None | Some("tweaks") => ListWhat::Tweaks,
+            Some("packages") => ListWhat::Packages,
+            Some("uwp") => ListWhat::Uwp,
+            Some("profiles") => ListWhat::Profiles,
+            Some(other) => return Err(format!("неизвестный список: {}", other)),
+        }),
+        "status" => Command::Status,
+        "check" => Command::Check { profile: single("профиль")? },
+        "apply" => Command::Apply {
+            profile: single("профиль")?,
+            dry_run: flag("--dry-run"),
+        },
+        "dsc" => match positional.as_slice() {
+            [profile] => Command::Dsc { profile: profile.clone(), path: None },
+            [profile, path] => Command::Dsc { profile: profile.clone(), path: Some(PathBuf::from(path)) },
+            _ => return Err("команде dsc нужны профиль и, необязательно, файл".to_string()),
+        },
+        "revert" if flag("--list") => Command::RevertList,
+        "revert" => {
+            let scope = if flag("--all") {
+                Some(RevertScope::All)
+            } else if let Some(id) = option("--session")? {
+                Some(RevertScope::Session(id))
+            } else if let Some(id) = option("--tweak")? {
+                Some(RevertScope::Tweak(id))
+            } else if let Some(seq) = option("--after")? {
+                Some(RevertScope::After(seq.parse().map_err(|_| format!("неверный номер записи: {}", seq))?))
+            } else {
+                None
+            };
+            Command::Revert { scope }
+        }
+        "install" | "remove-uwp" if positional.is_empty() => {
+            return Err(format!("команде {} нужен хотя бы один аргумент", name))
+        }
+        "install" => Command::Install { specs: positional, manager },
+        "sync" => Command::Sync { lock: path()? },
+        "export" => Command::Export {
+            path: path()?,
+            include_versions: flag("--include-versions"),
+            manager,
+        },
+        "import" => Command::Import {
+            path: path()?,
+            ignore_versions: flag("--ignore-versions"),
+        },
+        "remove-uwp" => Command::RemoveUwp(positional),
+        "firefox-policies" => Command::FirefoxPolicies {
+            template: path()?,
+            dry_run: flag("--dry-run"),
+        },
+        "help" | "--help" | "-h" => Command::Help,
+        other => return Err(format!("неизвестная команда: {}", other)),
+    };
+    Ok(Some(Invocation { command, json }))
+}
+
+/// Реестр, команды, каталог и журнал, с которыми работают CLI и TUI
+pub struct Context {
+    pub registry: Box<dyn RegistryBackend>,
+    pub runner: Box<dyn CommandRunner>,
+    pub catalog: Catalog,
+    /// Действия после установки пакетов
+    pub hooks: HookCatalog,
+    pub journal: Option<Journal>,
+    pub profile_dirs: Vec<PathBuf>,
+    /// Лок-файл установленных версий; `None` — не вести
+    pub lock_file: Option<PathBuf>,
+}
+
+impl Context {
+    /// Окружение текущей системы
+    pub fn system() -> Self {
+        Self {
+            registry: registry::system_registry(),
+            runner: command::system_runner(filesystem::get_tool_data_dir().map(|dir| dir.join(command::LOG_FILE))),
+            catalog: Catalog::load_default().unwrap_or_else(|_| Catalog::builtin()),
+            hooks: HookCatalog::load_default().unwrap_or_else(|_| HookCatalog::builtin()),
+            journal: Journal::open_default().ok(),
+            profile_dirs: profile::profile_dirs(),
+            lock_file: LockFile::default_path(),
+        }
+    }
+
+    /// Host, записывающий изменения в журнал
+    pub fn host(&self) -> Host<'_> {
+        let host = Host::new(self.registry.as_ref(), self.runner.as_ref());
+        match &self.journal {
+            Some(journal) => host.with_journal(journal, &self.catalog),
+            None => host,
+        }
+    }
+
+    /// Host без журнала, для отмены изменений
+    pub fn host_without_journal(&self) -> Host<'_> {
+        Host::new(self.registry.as_ref(), self.runner.as_ref())
+    }
+
+    /// Записывает установленные версии пакетов в лок-файл.
+    /// Ошибка записи не отменяет установку, поэтому только выводится
+    pub fn record_lock(&self, ids: &[String]) {
+        if let Some(path) = &self.lock_file {
+            if let Err(e) = lockfile::record_packages(path, self.runner.as_ref(), ids) {
+                progress::emit(&format!("⚠️  Лок-файл {} не обновлён: {}", path.display(), e));
+            }
+        }
+    }
+
+    fn find_profile(&self, name: &str) -> io::Result<Profile> {
+        let profile = profile::find_profile(&self.profile_dirs, name)?;
+        profile.validate(&self.catalog)?;
+        Ok(profile)
+    }
+}
+
+fn write_json(out: &mut dyn Write, value: &serde_json::Value) -> io::Result<()> {
+    writeln!(out, "{}", serde_json::to_string_pretty(value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?)
+}
+
+/// Код возврата по ошибке: ненайденное — ошибка использования
+fn error_code(e: &io::Error) -> i32 {
+    match e.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput => EXIT_USAGE,
+        _ => EXIT_FAILURE,
+    }
+}
+
+fn list(ctx: &Context, what: ListWhat, json: bool, out: &mut dyn Write) -> io::Result<()> {
+    match what {
+        ListWhat::Tweaks if json => write_json(
+            out,
+            &ctx.catalog
+                .tweaks
+                .iter()
+                .map(|t| {
+                    json!({
+                        "id": t.id,
+                        "category": t.category,
+                        "title": t.title.text(),
+                        "requires_admin": t.requires_admin,
+                        "restart": t.restart,
+                    })
+                })
+                .collect(),
+        ),
+        ListWhat::Tweaks => {
+            for t in &ctx.catalog.tweaks {
+                writeln!(out, "{:<28} {}{}", t.id, t.title.text(), if t.requires_admin { " (админ)" } else { "" })?;
+            }
+            Ok(())
+        }
+        ListWhat::Packages if json => write_json(
+            out,
+            &packages::WINGET_PACKAGES
+                .iter()
+                .map(|(id, version, description)| json!({ "id": id, "version": version, "description": description }))
+                .collect(),
+        ),
+        ListWhat::Packages => {
+            for (id, _, description) in packages::WINGET_PACKAGES {
+                writeln!(out, "{:<36} {}", id, description)?;
+            }
+            Ok(())
+        }
+        ListWhat::Uwp if json => write_json(
+            out,
+            &uwp::BLOATWARE_APPS
+                .iter()
+                .map(|(name, description)| json!({ "name": name, "description": description }))
+                .collect(),
+        ),
+        ListWhat::Uwp => {
+            for (name, description) in uwp::BLOATWARE_APPS {
+                writeln!(out, "{:<40} {}", name, description)?;
+            }
+            Ok(())
+        }
+        ListWhat::Profiles => {
+            let list = profile::list_profiles(&ctx.profile_dirs);
+            for error in &list.errors {
+                progress::emit(&format!("⚠️  {}", error));
+            }
+            let profiles = list.profiles;
+            if json {
+                return write_json(
+                    out,
+                    &profiles
+                        .iter()
+                        .map(|(path, p)| json!({ "name": p.name, "description": p.description, "path": path }))
+                        .collect(),
+                );
+            }
+            for (path, p) in profiles {
+                writeln!(out, "{:<24} {} ({})", p.name, p.description, path.display())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn status(ctx: &Context, json: bool, out: &mut dyn Write) -> io::Result<()> {
+    let states = tweaks::detect_all(ctx.registry.as_ref(), &ctx.catalog);
+    if json {
+        return write_json(
+            out,
+            &ctx.catalog
+                .tweaks
+                .iter()
+                .map(|t| json!({ "id": t.id, "title": t.title.text(), "state": states[&t.id] }))
+                .collect(),
+        );
+    }
+    for t in &ctx.catalog.tweaks {
+        writeln!(out, "{:<14} {:<28} {}", states[&t.id].label(), t.id, t.title.text())?;
+    }
+    Ok(())
+}
+
+fn revert(ctx: &Context, scope: &Option<RevertScope>, out: &mut dyn Write) -> io::Result<()> {
+    let journal = ctx
+        .journal
+        .as_ref()
+        .ok_or_else(|| io::Error::other("журнал изменений недоступен"))?;
+    let scope = match scope {
+        Some(scope) => scope.clone(),
+        None => match journal.last_session()? {
+            Some(session) => RevertScope::Session(session),
+            None => {
+                writeln!(out, "Журнал пуст, отменять нечего.")?;
+                return Ok(());
+            }
+        },
+    };
+    let count = journal.revert(&ctx.host_without_journal(), &ctx.catalog, &scope)?;
+    writeln!(out, "↩️  Отменено изменений: {}", count)
+}
+
+fn revert_list(ctx: &Context, json: bool, out: &mut dyn Write) -> io::Result<()> {
+    let entries = match &ctx.journal {
+        Some(journal) => journal.entries()?,
+        None => Vec::new(),
+    };
+    if json {
+        return write_json(out, &serde_json::to_value(&entries).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?);
+    }
+    for entry in entries {
+        writeln!(out, "{}  (сеанс {})", entry, entry.session)?;
+    }
+    Ok(())
+}
+
+/// Выполняет действие для каждого элемента, не останавливаясь на ошибках
+fn for_each(items: &[String], message: &str, action: impl Fn(&str) -> io::Result<()>) -> ApplyReport {
+    items
+        .iter()
+        .map(|item| ApplyResult::from_result(item, message, action(item)))
+        .collect()
+}
+
+/// Выводит результаты применения; код возврата 1, если что-то не выполнено
+fn write_report(report: &ApplyReport, json: bool, out: &mut dyn Write) -> io::Result<i32> {
+    if json {
+        writeln!(out, "{}", report.to_json()?)?;
+    } else {
+        write!(out, "{}", report)?;
+    }
+    Ok(report.exit_code())
+}
+
+/// План применения профиля: твики, пакеты с действиями после установки, UWP и шаблоны
+fn dry_run(ctx: &Context, profile: &Profile, json: bool, out: &mut dyn Write) -> io::Result<()> {
+    let plan = profile.plan(ctx.registry.as_ref(), &ctx.catalog)?;
+    let hooks: Vec<(String, String)> = profile
+        .package_ids()
+        .into_iter()
+        .flat_map(|id| ctx.hooks.actions_for(&id).iter().map(move |action| (id.clone(), action.to_string())))
+        .collect();
+    if json {
+        let value = json!({
+            "profile": profile.name,
+            "steps": plan.steps,
+            "winget_packages": profile.winget_packages,
+            "hooks": hooks.iter().map(|(package, action)| json!({ "package": package, "action": action })).collect::<Vec<_>>(),
+            "uwp_remove": profile.uwp_remove,
+            "config_templates": profile.config_templates,
+        });
+        let text = serde_json::to_string_pretty(&value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        return writeln!(out, "{}", text);
+    }
+    write!(out, "{}", plan)?;
+    for id in &profile.winget_packages {
+        writeln!(out, "[winget] установить {}", id)?;
+    }
+    for (package, action) in &hooks {
+        writeln!(out, "[после установки {}] {}", package, action)?;
+    }
+    for name in &profile.uwp_remove {
+        writeln!(out, "[uwp] удалить {}", name)?;
+    }
+    for name in &profile.config_templates {
+        writeln!(out, "[шаблон] {}", name)?;
+    }
+    Ok(())
+}
+
+fn run_command(ctx: &Context, inv: &Invocation, out: &mut dyn Write) -> io::Result<i32> {
+    match &inv.command {
+        Command::List(what) => list(ctx, *what, inv.json, out)?,
+        Command::Status => status(ctx, inv.json, out)?,
+        Command::Check { profile } => {
+            let report = compliance::check_profile(&ctx.host(), &ctx.catalog, &ctx.find_profile(profile)?);
+            if inv.json {
+                writeln!(out, "{}", report.to_json()?)?;
+            } else {
+                write!(out, "{}", report)?;
+            }
+            return Ok(report.exit_code());
+        }
+        Command::Apply { profile, dry_run: true } => dry_run(ctx, &ctx.find_profile(profile)?, inv.json, out)?,
+        Command::Apply { profile, dry_run: false } => {
+            let profile = ctx.find_profile(profile)?;
+            let report = profile.apply(&ctx.host(), &ctx.catalog, &ctx.hooks);
+            ctx.record_lock(&profile.package_ids());
+            return write_report(&report, inv.json, out);
+        }
+        Command::Dsc { profile, path } => {
+            let config = Configuration::from_profile(&ctx.find_profile(profile)?, &ctx.catalog)?;
+            for skipped in config.skipped() {
+                progress::emit(&format!("⚠️  Не переносится: {}", skipped));
+            }
+            match path {
+                Some(path) => {
+                    config.save(path)?;
+                    writeln!(out, "Документ сохранён: {}", path.display())?;
+                }
+                None => write!(out, "{}", config)?,
+            }
+        }
+        Command::Revert { scope } => revert(ctx, scope, out)?,
+        Command::RevertList => revert_list(ctx, inv.json, out)?,
+        Command::Install { specs, manager } => {
+            let pm = package_manager::manager(*manager, ctx.runner.as_ref());
+            let mut report: ApplyReport = specs
+                .iter()
+                .map(|spec| {
+                    let (id, pin) = packages::parse_package_spec(spec);
+                    ApplyResult::from_outcome(&id, "установка", pm.install(&id, &pin))
+                })
+                .collect();
+            let installed = report.applied_ids();
+            if *manager == Backend::Winget {
+                ctx.record_lock(&installed);
+            }
+            report.extend(hooks::run_after_install(&ctx.host(), &ctx.hooks, &installed));
+            return write_report(&report, inv.json, out);
+        }
+        Command::Sync { lock } => {
+            let path = lock
+                .clone()
+                .or_else(|| ctx.lock_file.clone())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "лок-файл не указан"))?;
+            let lock = LockFile::load(&path)?;
+            if lock.packages.is_empty() {
+                progress::emit(&format!("Лок-файл {} пуст или не найден", path.display()));
+            }
+            let report = packages::sync_packages(ctx.runner.as_ref(), &lock.pins())?;
+            return write_report(&report, inv.json, out);
+        }
+        Command::Export { path, include_versions, manager: Backend::Winget } => {
+            let path = PackageList::resolve_path(path.as_deref())?;
+            PackageList::from_installed(ctx.runner.as_ref(), *include_versions)?.save(&path)?;
+            writeln!(out, "Список пакетов сохранён: {}", path.display())?;
+        }
+        Command::Export { path, manager, .. } => {
+            let path = path
+                .clone()
+                .or_else(|| filesystem::get_documents_dir().map(|dir| dir.join(manager.export_file())))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "не найдена папка пользователя, укажите файл"))?;
+            package_manager::manager(*manager, ctx.runner.as_ref()).export(&path)?;
+            writeln!(out, "Список пакетов сохранён: {}", path.display())?;
+        }
+        Command::Import { path, ignore_versions } => {
+            let list = PackageList::load(&PackageList::resolve_path(path.as_deref())?)?;
+            let report = packages::install_missing(ctx.runner.as_ref(), &list.wanted(*ignore_versions))?;
+            ctx.record_lock(&report.applied_ids());
+            return write_report(&report, inv.json, out);
+        }
+        Command::RemoveUwp(names) => {
+            let report = for_each(names, "удаление", |name| uwp::uninstall_uwp(ctx.runner.as_ref(), name));
+            return write_report(&report, inv.json, out);
+        }
+        Command::FirefoxPolicies { template, dry_run } => {
+            let template = match template {
+                Some(path) => path.clone(),
+                None => hooks::template_dir("firefox")?.join(firefox::POLICIES_TEMPLATE),
+            };
+            let policies = Policies::load(&template)?;
+            let unknown = policies.unknown();
+            if !unknown.is_empty() {
+                writeln!(out, "⚠️  Неизвестные политики, Firefox их пропустит: {}", unknown.join(", "))?;
+            }
+            let dirs = firefox::install_dirs();
+            if dirs.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::NotFound, "Firefox не установлен"));
+            }
+            for dir in dirs {
+                let changes = if *dry_run {
+                    policies.diff(&firefox::installed_policies(&dir)?)
+                } else {
+                    firefox::deploy_policies(&policies, &dir)?
+                };
+                writeln!(out, "{}:", firefox::policies_path(&dir).display())?;
+                if changes.is_empty() {
+                    writeln!(out, "  без изменений")?;
+                }
+                for change in &changes {
+                    writeln!(out, "  {}", change)?;
+                }
+            }
+        }
+        Command::Help => writeln!(out, "{}", USAGE)?,
+    }
+    Ok(EXIT_OK)
+}
+
+/// Выполняет команду и возвращает код возврата
+pub fn execute(ctx: &Context, inv: &Invocation, out: &mut dyn Write) -> i32 {
+    match run_command(ctx, inv, out) {
+        Ok(code) => code,
+        Err(e) => {
+            // В фоновой задаче TUI ошибка попадает в её журнал, а не на экран
+            match progress::current() {
+                Some(progress) => progress.line(&format!("❌ {}", e)),
+                None => eprintln!("❌ {}", e),
+            }
+            error_code(&e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+    use crate::utils::registry::{MemoryRegistry, RegRoot, RegValue};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn context(runner: ScriptedRunner) -> Context {
+        Context {
+            registry: Box::new(MemoryRegistry::new()),
+            runner: Box::new(runner),
+            catalog: Catalog::builtin(),
+            hooks: HookCatalog::default(),
+            journal: None,
+            profile_dirs: vec![PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("data").join("profiles")],
+            lock_file: None,
+        }
+    }
+
+    fn run(ctx: &Context, line: &str) -> (i32, String) {
+        let inv = parse(&args(line)).unwrap().unwrap();
+        let mut out = Vec::new();
+        let code = execute(ctx, &inv, &mut out);
+        (code, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&[]).unwrap(), None);
+        assert_eq!(
+            parse(&args("apply standard --dry-run --json")).unwrap().unwrap(),
+            Invocation {
+                command: Command::Apply { profile: "standard".into(), dry_run: true },
+                json: true
+            }
+        );
+        assert_eq!(
+            parse(&args("revert --after 5")).unwrap().unwrap().command,
+            Command::Revert { scope: Some(RevertScope::After(5)) }
+        );
+        assert_eq!(parse(&args("sync")).unwrap().unwrap().command, Command::Sync { lock: None });
+        assert!(parse(&args("sync a.json b.json")).is_err());
+        assert_eq!(
+            parse(&args("export team.json --include-versions")).unwrap().unwrap().command,
+            Command::Export { path: Some(PathBuf::from("team.json")), include_versions: true, manager: Backend::Winget }
+        );
+        assert_eq!(
+            parse(&args("install --manager scoop extras/vscode main/git@2.44.0")).unwrap().unwrap().command,
+            Command::Install {
+                specs: vec!["extras/vscode".into(), "main/git@2.44.0".into()],
+                manager: Backend::Scoop
+            }
+        );
+        assert_eq!(
+            parse(&args("firefox-policies --dry-run")).unwrap().unwrap().command,
+            Command::FirefoxPolicies { template: None, dry_run: true }
+        );
+        assert!(parse(&args("install git --manager apt")).is_err());
+        assert!(parse(&args("install")).is_err());
+        assert!(parse(&args("revert --after x")).is_err());
+        assert!(parse(&args("frobnicate")).is_err());
+    }
+
+    #[test]
+    fn test_status_json() {
+        let ctx = context(ScriptedRunner::new());
+        ctx.registry
+            .write_value(
+                RegRoot::CurrentUser,
+                "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced",
+                "HideFileExt",
+                &RegValue::Dword(0),
+            )
+            .unwrap();
+        let (code, out) = run(&ctx, "status --json");
+        assert_eq!(code, EXIT_OK);
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        let item = json.as_array().unwrap().iter().find(|i| i["id"] == "show_file_extensions").unwrap();
+        assert_eq!(item["state"], "on");
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let runner = ScriptedRunner::new()
+            .expect(
+                "winget",
+                &["install", "--id", "Bad.Id", "--silent", "--accept-source-agreements", "--accept-package-agreements"],
+                CommandOutput::with_code(-1978335212, "No package found"),
+            )
+            .expect(
+                "winget",
+                &["install", "--id", "7zip.7zip", "--silent", "--accept-source-agreements", "--accept-package-agreements"],
+                CommandOutput::ok(""),
+            );
+        let ctx = context(runner);
+        assert_eq!(run(&ctx, "install Bad.Id 7zip.7zip").0, EXIT_FAILURE);
+        assert_eq!(run(&ctx, "apply no-such-profile").0, EXIT_USAGE);
+
+        let (code, out) = run(&ctx, "dsc standard");
+        assert_eq!(code, EXIT_OK);
+        assert!(out.contains("resource: Microsoft.WinGet.DSC/WinGetPackage"));
+
+        let (code, out) = run(&ctx, "apply standard --dry-run");
+        assert_eq!(code, EXIT_OK);
+        assert!(out.contains("[winget] установить 7zip.7zip"));
+    }
+
+    #[test]
+    fn test_dry_run_json() {
+        let ctx = Context { hooks: HookCatalog::builtin(), ..context(ScriptedRunner::new()) };
+        let (code, out) = run(&ctx, "apply standard --dry-run --json");
+        assert_eq!(code, EXIT_OK);
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["profile"], "Стандарт");
+        let steps = json["steps"].as_array().unwrap();
+        assert!(steps.iter().any(|s| s["tweak_id"] == "show_file_extensions"));
+        assert_eq!(json["winget_packages"][1], "7zip.7zip");
+        assert_eq!(json["hooks"][0], json!({ "package": "Mozilla.Firefox", "action": "шаблон firefox" }));
+        assert_eq!(json["uwp_remove"].as_array().unwrap().len(), 5);
+        assert_eq!(json["config_templates"], json!([]));
+
+        let (_, out) = run(&ctx, "apply standard --dry-run");
+        assert!(out.contains("[после установки Mozilla.Firefox] шаблон firefox"));
+    }
+}
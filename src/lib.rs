@@ -0,0 +1,4 @@
+pub mod cli;
+pub mod modules;
+pub mod tui;
+pub mod utils;
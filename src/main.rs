@@ -1,6 +1,10 @@
+use std::collections::HashMap;
 use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, Event, KeyCode, KeyEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -13,18 +17,34 @@ use ratatui::{
     Terminal,
 };
 
-mod modules;
-mod tui;
-mod utils;
-
-use modules::explorer;
-use utils::admin;
+use win_tool::cli::{self, Context, Invocation};
+use win_tool::modules::compliance::{self, ComplianceReport};
+use win_tool::modules::dsc::{self, Configuration};
+use win_tool::modules::explorer;
+use win_tool::modules::hooks;
+use win_tool::modules::installers::{self, Installer};
+use win_tool::modules::journal;
+use win_tool::modules::package_list::PackageList;
+use win_tool::modules::package_manager::{self, Backend, PackageEntry};
+use win_tool::modules::packages::{self, Package};
+use win_tool::modules::plan::{self, Plan};
+use win_tool::modules::profile::{self, Profile};
+use win_tool::modules::results::ApplyReport;
+use win_tool::modules::tweaks::{self, Category, Selection};
+use win_tool::modules::uwp::{self, UwpApp};
+use win_tool::modules::winget::WingetEntry;
+use win_tool::progress;
+use win_tool::utils::{admin, filesystem};
+use win_tool::utils::progress::BackgroundTask;
+use win_tool::tui::checkbox::{CheckboxItem, CheckboxList};
+use win_tool::tui::menu;
 
 #[derive(Debug, Clone, PartialEq)]
 enum AppState {
     MainMenu,
     PackagesMenu,
     WingetPackages,
+    WingetUpgrades,
     UwpApps,
     OtherInstallers,
     ExplorerSettings,
@@ -32,34 +52,625 @@ enum AppState {
     PersonalizationMenu,
     PersonalizationBasic,
     PersonalizationContext,
+    Profiles,
+    ComplianceReport,
+}
+
+impl AppState {
+    /// Раздел каталога твиков, который показывает экран
+    fn tweak_category(&self) -> Option<Category> {
+        match self {
+            AppState::ExplorerSettings => Some(Category::Explorer),
+            AppState::SystemSettings => Some(Category::System),
+            AppState::PersonalizationBasic => Some(Category::PersonalizationBasic),
+            AppState::PersonalizationContext => Some(Category::PersonalizationContext),
+            _ => None,
+        }
+    }
+
+    fn has_checkboxes(&self) -> bool {
+        self.tweak_category().is_some()
+            || matches!(
+                self,
+                AppState::WingetPackages | AppState::WingetUpgrades | AppState::UwpApps | AppState::OtherInstallers
+            )
+    }
+}
+
+/// Экран с чекбоксами; `keys` — id твиков, пакетов или имена файлов по строкам списка
+struct CheckboxScreen {
+    list: CheckboxList,
+    keys: Vec<String>,
+}
+
+impl CheckboxScreen {
+    fn new(rows: Vec<(String, CheckboxItem)>) -> Self {
+        let (keys, items) = rows.into_iter().unzip();
+        Self {
+            list: CheckboxList::new(items),
+            keys,
+        }
+    }
+
+    /// Отмеченные и снятые пункты
+    fn selection(&self) -> Selection {
+        self.keys
+            .iter()
+            .zip(&self.list.items)
+            .map(|(key, item)| (key.clone(), item.checked))
+            .collect()
+    }
+}
+
+/// Выводит результаты в журнал задачи; итог задачи — сводка, неуспех — если что-то не выполнено
+fn report_outcome(report: ApplyReport) -> io::Result<String> {
+    for result in &report.results {
+        progress!("{}", result);
+    }
+    if report.has_failures() {
+        return Err(io::Error::other(report.summary()));
+    }
+    Ok(report.summary())
+}
+
+fn checkbox_item(name: &str, description: &str, version: &str, checked: bool) -> CheckboxItem {
+    let mut item = CheckboxItem::new(name.to_string(), description.to_string(), version.to_string());
+    item.checked = checked;
+    item
+}
+
+/// Загрузка данных экрана; выполняется фоновой задачей
+enum Load {
+    Checkboxes,
+    Compliance,
+    Import,
+    /// Поиск в каталоге текущего менеджера по запросу
+    Search(String),
+}
+
+impl Load {
+    fn message(&self) -> &'static str {
+        match self {
+            Load::Checkboxes => "Получение текущего состояния...",
+            Load::Compliance => "Проверка соответствия профилю...",
+            Load::Import => "Импорт списка пакетов...",
+            Load::Search(_) => "Поиск пакетов...",
+        }
+    }
+}
+
+/// Что вводится в строке ввода
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Prompt {
+    /// Запрос к каталогу текущего менеджера
+    Search,
+    /// Имя сохраняемого профиля
+    ProfileName,
+}
+
+/// Результат фоновой загрузки, который применяется к экрану в потоке интерфейса
+enum Loaded {
+    Installed(Vec<PackageEntry>),
+    Upgrades(Vec<WingetEntry>),
+    Uwp(Vec<UwpApp>),
+    Installers(Vec<Installer>),
+    Compliance(ComplianceReport),
+    Import(PackageList),
+    /// Запрос и найденные по нему пакеты
+    Found(String, Vec<PackageEntry>),
 }
 
 struct App {
     state: AppState,
     selected: usize,
     is_admin: bool,
+    ctx: Arc<Context>,
+    runtime: tokio::runtime::Runtime,
+    task: Option<BackgroundTask>,
+    /// Задача ничего не меняет в системе: после неё экран не перечитывается
+    keep_screen: bool,
+    /// Открытая задача загружает данные экрана; результат забирает `finish_loading`
+    loading: bool,
+    profiles: Vec<(PathBuf, Profile)>,
+    /// Файлы профилей, которые не удалось прочитать; показываются под списком
+    profile_errors: Vec<String>,
+    report: Option<ComplianceReport>,
+    scroll: u16,
+    checkboxes: Option<CheckboxScreen>,
+    packages: Vec<Package>,
+    /// Менеджер пакетов экрана «Winget-пакеты»
+    backend: Backend,
+    /// Установленные на машине менеджеры; определяются при первом переключении
+    backends: Vec<Backend>,
+    /// Установленные пакеты текущего менеджера — для отметок в поиске и импорте
+    installed: Vec<PackageEntry>,
+    /// Строка ввода и то, что в неё вводится, пока она открыта
+    input: Option<(Prompt, String)>,
+    /// Результаты поиска за сеанс: менеджер и запрос → найденные пакеты
+    search_cache: HashMap<(Backend, String), Vec<PackageEntry>>,
+    upgrades: Vec<WingetEntry>,
+    uwp_apps: Vec<UwpApp>,
+    /// Отметки, оставленные на экранах пакетов и UWP; попадают в сохраняемый профиль
+    package_selection: Selection,
+    uwp_selection: Selection,
+    installers: Vec<Installer>,
+    plan: Option<Plan>,
 }
 
 impl App {
-    fn new() -> Self {
-        Self {
+    fn new() -> io::Result<Self> {
+        Ok(Self {
             state: AppState::MainMenu,
             selected: 0,
             is_admin: admin::is_admin(),
-        }
+            ctx: Arc::new(Context::system()),
+            runtime: tokio::runtime::Runtime::new()?,
+            task: None,
+            keep_screen: false,
+            loading: false,
+            profiles: Vec::new(),
+            profile_errors: Vec::new(),
+            report: None,
+            scroll: 0,
+            checkboxes: None,
+            packages: Vec::new(),
+            backend: Backend::default(),
+            backends: Vec::new(),
+            installed: Vec::new(),
+            input: None,
+            search_cache: HashMap::new(),
+            upgrades: Vec::new(),
+            uwp_apps: Vec::new(),
+            package_selection: Selection::new(),
+            uwp_selection: Selection::new(),
+            installers: Vec::new(),
+            plan: None,
+        })
+    }
+
+    /// Запускает длительную операцию в фоне и показывает её ход
+    fn start_task<F>(&mut self, title: &str, work: F)
+    where
+        F: FnOnce() -> io::Result<String> + Send + 'static,
+    {
+        self.plan = None;
+        self.scroll = 0;
+        self.keep_screen = false;
+        self.task = Some(BackgroundTask::spawn(self.runtime.handle(), title, work));
     }
 
-    fn get_menu_items(&self) -> Vec<&str> {
+    /// Закрывает завершённую задачу и перечитывает состояние экрана
+    fn close_task(&mut self) {
+        self.task = None;
+        self.scroll = 0;
+        if std::mem::take(&mut self.keep_screen) {
+            return;
+        }
         match self.state {
+            AppState::Profiles => self.load_profiles(),
+            _ if self.state.has_checkboxes() => self.start_load(Load::Checkboxes),
+            _ => {}
+        }
+    }
+
+    /// Перечитывает профили вместе с ошибками чтения файлов
+    fn load_profiles(&mut self) {
+        let list = profile::list_profiles(&self.ctx.profile_dirs);
+        self.profiles = list.profiles;
+        self.profile_errors = list.errors;
+    }
+
+    /// Переходит на экран с чекбоксами и загружает его состояние
+    fn open_checkboxes(&mut self, state: AppState) {
+        self.state = state;
+        self.selected = 0;
+        self.start_load(Load::Checkboxes);
+    }
+
+    /// Запускает загрузку в фоне: интерфейс показывает её ход и остаётся отзывчивым.
+    /// Твики читаются из реестра сразу, повторный поиск берётся из кэша.
+    fn start_load(&mut self, load: Load) {
+        let ctx = Arc::clone(&self.ctx);
+        let backend = self.backend;
+        let work: Box<dyn FnOnce() -> io::Result<Loaded> + Send> = match &load {
+            Load::Checkboxes if self.state.tweak_category().is_some() => {
+                self.load_tweaks();
+                return;
+            }
+            Load::Checkboxes => match self.state {
+                AppState::WingetPackages => Box::new(move || {
+                    Ok(Loaded::Installed(package_manager::manager(backend, ctx.runner.as_ref()).list_installed()?))
+                }),
+                AppState::WingetUpgrades => Box::new(move || Ok(Loaded::Upgrades(packages::get_upgrades(ctx.runner.as_ref())?))),
+                AppState::UwpApps => Box::new(move || Ok(Loaded::Uwp(uwp::get_uwp_apps(ctx.runner.as_ref())?))),
+                AppState::OtherInstallers => Box::new(|| {
+                    let installers = installers::installers_dir().and_then(|dir| installers::list_installers(&dir).ok());
+                    Ok(Loaded::Installers(installers.unwrap_or_default()))
+                }),
+                _ => {
+                    self.checkboxes = None;
+                    return;
+                }
+            },
+            Load::Compliance => {
+                let Some((_, profile)) = self.profiles.get(self.selected) else { return };
+                let profile = profile.clone();
+                Box::new(move || Ok(Loaded::Compliance(compliance::check_profile(&ctx.host(), &ctx.catalog, &profile))))
+            }
+            Load::Import => Box::new(|| Ok(Loaded::Import(PackageList::load(&PackageList::resolve_path(None)?)?))),
+            Load::Search(query) => {
+                if let Some(found) = self.search_cache.get(&(backend, query.to_lowercase())).cloned() {
+                    self.show_found(query, &found);
+                    return;
+                }
+                let query = query.clone();
+                Box::new(move || {
+                    let found = package_manager::manager(backend, ctx.runner.as_ref()).search(&query)?;
+                    Ok(Loaded::Found(query, found))
+                })
+            }
+        };
+        self.plan = None;
+        self.scroll = 0;
+        // Ошибка загрузки остаётся в окне задачи; перечитывать после неё нечего,
+        // а строки прошлого экрана не должны остаться под новым
+        if matches!(load, Load::Checkboxes) {
+            self.checkboxes = None;
+        }
+        self.keep_screen = true;
+        self.loading = true;
+        self.task = Some(BackgroundTask::spawn_with_result(self.runtime.handle(), load.message(), work));
+    }
+
+    /// Применяет результат завершившейся загрузки и закрывает её окно.
+    /// При ошибке или отмене окно остаётся открытым с сообщением.
+    fn finish_loading(&mut self) {
+        let Some(task) = self.task.as_mut().filter(|t| self.loading && !t.is_running()) else { return };
+        self.loading = false;
+        let Some(loaded) = task.take_result::<Loaded>() else { return };
+        self.task = None;
+        self.keep_screen = false;
+        match loaded {
+            Loaded::Compliance(report) => {
+                self.report = Some(report);
+                self.scroll = 0;
+                self.state = AppState::ComplianceReport;
+            }
+            Loaded::Import(list) => self.import_packages(&list),
+            Loaded::Found(query, found) => {
+                self.search_cache.insert((self.backend, query.to_lowercase()), found.clone());
+                self.show_found(&query, &found);
+            }
+            loaded => self.load_checkboxes(loaded),
+        }
+    }
+
+    /// Заполняет чекбоксы твиков; отмечено то, что уже включено
+    fn load_tweaks(&mut self) {
+        let Some(category) = self.state.tweak_category() else { return };
+        let rows = self
+            .ctx
+            .catalog
+            .by_category(category)
+            .map(|t| {
+                let state = tweaks::detect_state(self.ctx.registry.as_ref(), t);
+                let item = checkbox_item(t.title.text(), t.description.text(), state.short_label(), state == tweaks::TweakState::On);
+                (t.id.clone(), item)
+            })
+            .collect();
+        self.checkboxes = Some(CheckboxScreen::new(rows));
+    }
+
+    /// Заполняет чекбоксы экрана загруженными данными; отмечено то, что уже установлено
+    fn load_checkboxes(&mut self, loaded: Loaded) {
+        let rows = match loaded {
+            Loaded::Installed(installed) => {
+                // Найденные и импортированные пакеты остаются в списке до конца сеанса
+                let curated = packages::curated_packages();
+                let extra: Vec<Package> = self
+                    .packages
+                    .drain(..)
+                    .filter(|p| !curated.iter().any(|c| c.id == p.id))
+                    .map(|p| Package { installed: false, ..p })
+                    .collect();
+                self.installed = installed;
+                self.packages = packages::mark_installed(curated.into_iter().chain(extra).collect(), self.backend, &self.installed);
+                self.packages.iter().map(|p| self.package_row(p, p.installed)).collect()
+            }
+            Loaded::Upgrades(upgrades) => {
+                // Ничего не отмечено: обновлять вслепую нельзя
+                self.upgrades = upgrades;
+                self.upgrades
+                    .iter()
+                    .map(|e| {
+                        let version = format!("{} → {}", e.version, e.available.as_deref().unwrap_or("?"));
+                        let description = format!("{} ({})", e.id, e.source.as_deref().unwrap_or("—"));
+                        (e.id.clone(), checkbox_item(&e.name, &description, &version, false))
+                    })
+                    .collect()
+            }
+            Loaded::Uwp(apps) => {
+                self.uwp_apps = apps;
+                self.uwp_apps
+                    .iter()
+                    .map(|a| {
+                        let description = uwp::BLOATWARE_APPS
+                            .iter()
+                            .find(|(name, _)| *name == a.name)
+                            .map_or("", |(_, d)| d);
+                        (a.name.clone(), checkbox_item(&a.name, description, "", a.installed))
+                    })
+                    .collect()
+            }
+            Loaded::Installers(installers) => {
+                self.installers = installers;
+                self.installers
+                    .iter()
+                    .map(|i| (i.filename.clone(), checkbox_item(&i.filename, &i.description, "", false)))
+                    .collect()
+            }
+            Loaded::Compliance(_) | Loaded::Import(_) | Loaded::Found(..) => return,
+        };
+        self.checkboxes = Some(CheckboxScreen::new(rows));
+    }
+
+    /// План изменений для отмеченных твиков: строки экрана взяты из каталога, план тоже
+    fn plan_checkboxes(&self) -> io::Result<Option<Plan>> {
+        let Some(screen) = self.checkboxes.as_ref().filter(|_| self.state.tweak_category().is_some()) else {
+            return Ok(None);
+        };
+        plan::plan_selection(self.ctx.registry.as_ref(), &self.ctx.catalog, &screen.selection()).map(Some)
+    }
+
+    /// Выполняет показанный пользователю план — ровно те шаги, что были в окне
+    fn execute_plan(&mut self) {
+        let Some(plan) = self.plan.take() else { return };
+        let ctx = Arc::clone(&self.ctx);
+        self.start_task("Применение изменений", move || report_outcome(plan.execute(&ctx.host())));
+    }
+
+    /// Применяет отмеченное на экране пакетов, UWP или установщиков в фоновой задаче
+    fn apply_checkboxes(&mut self) {
+        let Some(screen) = &self.checkboxes else { return };
+        let selection = screen.selection();
+        let ctx = Arc::clone(&self.ctx);
+        let state = self.state.clone();
+        let packages = self.packages.clone();
+        let backend = self.backend;
+        let upgrades = self.upgrades.clone();
+        let uwp_apps = self.uwp_apps.clone();
+        let installers = self.installers.clone();
+        self.start_task("Применение изменений", move || {
+            let host = ctx.host();
+            let report = match state {
+                AppState::WingetPackages => {
+                    let manager = package_manager::manager(backend, host.runner);
+                    let mut report = packages::apply_package_selection(manager.as_ref(), &packages, &selection);
+                    let applied = report.applied_ids();
+                    // Лок-файл хранит версии winget
+                    if backend == Backend::Winget {
+                        ctx.record_lock(&applied);
+                    }
+                    let installed = packages::newly_installed(&report, &selection);
+                    report.extend(hooks::run_after_install(&host, &ctx.hooks, &installed));
+                    report
+                }
+                AppState::WingetUpgrades => {
+                    let report = packages::apply_upgrades(host.runner, &upgrades, &selection);
+                    ctx.record_lock(&report.applied_ids());
+                    report
+                }
+                AppState::UwpApps => uwp::apply_uwp_selection(host.runner, &uwp_apps, &selection),
+                AppState::OtherInstallers => installers::apply_installers(host.runner, &installers, &selection),
+                _ => ApplyReport::default(),
+            };
+            report_outcome(report)
+        });
+    }
+
+    /// Сохраняет установленные пакеты: для winget — в формате `winget export`,
+    /// для остальных менеджеров — их собственным экспортом
+    fn export_packages(&mut self) {
+        let ctx = Arc::clone(&self.ctx);
+        let backend = self.backend;
+        self.start_task("Экспорт пакетов", move || {
+            let runner = ctx.runner.as_ref();
+            let path = match backend {
+                Backend::Winget => {
+                    let path = PackageList::resolve_path(None)?;
+                    PackageList::from_installed(runner, false)?.save(&path)?;
+                    path
+                }
+                _ => {
+                    let path = filesystem::get_documents_dir()
+                        .map(|dir| dir.join(backend.export_file()))
+                        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "не найдена папка пользователя"))?;
+                    package_manager::manager(backend, runner).export(&path)?;
+                    path
+                }
+            };
+            Ok(format!("Список пакетов сохранён: {}", path.display()))
+        });
+        self.keep_screen = true;
+    }
+
+    /// Отмечает пакеты из файла `winget export`
+    fn import_packages(&mut self, list: &PackageList) {
+        let wanted = list.wanted(false);
+        packages::merge_imported(&mut self.packages, &wanted, self.backend, &self.installed);
+        self.rebuild_package_rows(|p| wanted.iter().any(|(id, _)| id.eq_ignore_ascii_case(&p.id)));
+    }
+
+    /// Добавляет найденные пакеты в список; курсор — на первый найденный
+    fn show_found(&mut self, query: &str, found: &[PackageEntry]) {
+        if found.is_empty() {
+            let message = format!("Ничего не найдено по запросу \"{}\"", query);
+            self.start_task(&format!("Поиск {}", self.backend), move || Ok(message));
+            self.keep_screen = true;
+            return;
+        }
+
+        packages::merge_found(&mut self.packages, self.backend, found, &self.installed);
+        let first = self.packages.iter().position(|p| p.id.eq_ignore_ascii_case(&found[0].id));
+        self.rebuild_package_rows(|_| false);
+        if let (Some(screen), Some(index)) = (&mut self.checkboxes, first) {
+            screen.list.selected = index;
+        }
+    }
+
+    /// Пересобирает строки экрана пакетов; отметки, сделанные вручную, сохраняются
+    fn rebuild_package_rows(&mut self, check: impl Fn(&Package) -> bool) {
+        let previous = self.checkboxes.as_ref().map(CheckboxScreen::selection).unwrap_or_default();
+        let rows = self
+            .packages
+            .iter()
+            .map(|p| self.package_row(p, check(p) || previous.get(&p.id).copied().unwrap_or(p.installed)))
+            .collect();
+        self.checkboxes = Some(CheckboxScreen::new(rows));
+    }
+
+    /// Строка экрана пакетов; пакет, которого нет в текущем менеджере, помечается
+    fn package_row(&self, package: &Package, checked: bool) -> (String, CheckboxItem) {
+        let version = match package.id_for(self.backend) {
+            Some(_) => package.version.clone(),
+            None => format!("нет в {}", self.backend),
+        };
+        (package.id.clone(), checkbox_item(&package.id, &package.description, &version, checked))
+    }
+
+    /// Переключает экран пакетов на следующий установленный менеджер
+    fn switch_backend(&mut self) {
+        if self.backends.is_empty() {
+            self.backends = package_manager::available(self.ctx.runner.as_ref());
+        }
+        let next = self
+            .backends
+            .iter()
+            .position(|b| *b == self.backend)
+            .map_or(0, |i| (i + 1) % self.backends.len());
+        if let Some(backend) = self.backends.get(next).copied().filter(|b| *b != self.backend) {
+            self.backend = backend;
+            self.start_load(Load::Checkboxes);
+        }
+    }
+
+    /// Сохраняет выбор текущего экрана как документ `winget configure`
+    fn export_dsc(&mut self) {
+        let Some(screen) = &self.checkboxes else { return };
+        let selection = screen.selection();
+        let mut config = Configuration::new();
+        let built = match self.state {
+            AppState::WingetPackages => {
+                let checked = |p: &&Package| selection.get(&p.id).copied().unwrap_or(false);
+                // В DSC попадают только пакеты, у которых есть Id winget
+                let winget_id = |p: &Package| p.id_for(Backend::Winget).map(str::to_string);
+                let install: Vec<_> = self
+                    .packages
+                    .iter()
+                    .filter(checked)
+                    .filter_map(|p| Some((winget_id(p)?, p.pin.clone())))
+                    .collect();
+                let remove: Vec<_> = self.packages.iter().filter(|p| p.installed && !checked(p)).filter_map(winget_id).collect();
+                config.add_packages(&install, &remove);
+                Ok(())
+            }
+            _ => config.add_tweaks(&self.ctx.catalog, &selection),
+        };
+        self.start_task("Документ winget configure", move || {
+            built?;
+            let path = filesystem::get_documents_dir()
+                .map(|dir| dir.join(dsc::DEFAULT_FILE))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "не найдена папка пользователя"))?;
+            for skipped in config.skipped() {
+                progress!("⚠️  Не переносится: {}", skipped);
+            }
+            config.save(&path)?;
+            Ok(format!("Документ сохранён: {}", path.display()))
+        });
+        self.keep_screen = true;
+    }
+
+    /// Применяет профиль из списка в фоновой задаче
+    fn apply_profile(&mut self, index: usize) {
+        let Some((_, profile)) = self.profiles.get(index) else { return };
+        let profile = profile.clone();
+        let ctx = Arc::clone(&self.ctx);
+        self.start_task(&format!("Профиль \"{}\"", profile.name), move || {
+            profile.validate(&ctx.catalog)?;
+            let report = profile.apply(&ctx.host(), &ctx.catalog, &ctx.hooks);
+            ctx.record_lock(&profile.package_ids());
+            report_outcome(report)
+        });
+    }
+
+    /// Открывает ввод имени профиля; по умолчанию — время сохранения
+    fn prompt_profile_name(&mut self) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        self.input = Some((Prompt::ProfileName, format!("Сохранено {}", journal::format_timestamp(now))));
+    }
+
+    /// Сохраняет текущие настройки машины и отметки экранов пакетов и UWP как профиль пользователя
+    fn save_current_profile(&mut self, name: String) {
+        let ctx = Arc::clone(&self.ctx);
+        let packages = self.packages.clone();
+        let package_selection = self.package_selection.clone();
+        let uwp_apps = self.uwp_apps.clone();
+        let uwp_selection = self.uwp_selection.clone();
+        self.start_task("Сохранение профиля", move || {
+            let dir = profile::user_profiles_dir()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "директория данных не найдена"))?;
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+            let path = dir.join(format!("saved-{}.json", now));
+            Profile::from_machine(&name, ctx.registry.as_ref(), &ctx.catalog)
+                .with_selection(&packages, &package_selection, &uwp_apps, &uwp_selection)
+                .save(&path)?;
+            Ok(format!("💾 Профиль сохранён: {}", path.display()))
+        });
+    }
+
+    /// Отменяет изменения последнего сеанса из журнала
+    fn revert_last_session(&mut self) {
+        let ctx = Arc::clone(&self.ctx);
+        self.start_task("Отмена последнего сеанса", move || {
+            let revert = Invocation {
+                command: cli::Command::Revert { scope: None },
+                json: false,
+            };
+            let mut out = Vec::new();
+            let code = cli::execute(&ctx, &revert, &mut out);
+            for line in String::from_utf8_lossy(&out).lines() {
+                progress!("{}", line);
+            }
+            if code != cli::EXIT_OK {
+                return Err(io::Error::other(format!("код возврата {}", code)));
+            }
+            Ok("Изменения отменены".to_string())
+        });
+    }
+
+    /// Перезапускает проводник в фоновой задаче
+    fn restart_explorer(&mut self) {
+        let ctx = Arc::clone(&self.ctx);
+        self.start_task("Перезагрузка проводника", move || {
+            explorer::restart_explorer(ctx.runner.as_ref())?;
+            Ok("Проводник перезапущен".to_string())
+        });
+    }
+
+    fn get_menu_items(&self) -> Vec<String> {
+        let items = match self.state {
             AppState::MainMenu => vec![
                 "📦 Управление пакетами",
                 "📁 Проводник и рабочий стол",
                 "⚙️  Система",
                 "🎨 Персонализация",
+                "📋 Профили",
                 "🔄 Перезагрузить проводник",
+                "↩️  Отменить последний сеанс изменений",
             ],
             AppState::PackagesMenu => vec![
                 "🌐 Winget-пакеты",
+                "⬆️  Обновления winget",
                 "📱 UWP-приложения",
                 "💾 Другое (.exe / DISM)",
             ],
@@ -67,8 +678,18 @@ impl App {
                 "✨ Основное",
                 "🖱️  Контекстное меню",
             ],
+            AppState::Profiles => {
+                return self
+                    .profiles
+                    .iter()
+                    .map(|(_, p)| if p.description.is_empty() { p.name.clone() } else { format!("{} — {}", p.name, p.description) })
+                    .chain(["💾 Сохранить текущие настройки как профиль".to_string()])
+                    .chain(self.profile_errors.iter().map(|e| format!("⚠️  {}", e)))
+                    .collect();
+            }
             _ => vec![],
-        }
+        };
+        items.into_iter().map(String::from).collect()
     }
 
     fn handle_enter(&mut self) -> Option<Action> {
@@ -79,57 +700,87 @@ impl App {
                     self.selected = 0;
                 }
                 1 => {
-                    self.state = AppState::ExplorerSettings;
-                    self.selected = 0;
+                    self.open_checkboxes(AppState::ExplorerSettings);
                 }
                 2 => {
-                    self.state = AppState::SystemSettings;
-                    self.selected = 0;
+                    self.open_checkboxes(AppState::SystemSettings);
                 }
                 3 => {
                     self.state = AppState::PersonalizationMenu;
                     self.selected = 0;
                 }
-                4 => return Some(Action::RestartExplorer),
+                4 => {
+                    self.load_profiles();
+                    self.state = AppState::Profiles;
+                    self.selected = 0;
+                }
+                5 => return Some(Action::RestartExplorer),
+                6 => return Some(Action::RevertLastSession),
                 _ => {}
             },
             AppState::PackagesMenu => match self.selected {
                 0 => {
-                    self.state = AppState::WingetPackages;
-                    self.selected = 0;
+                    self.open_checkboxes(AppState::WingetPackages);
                 }
                 1 => {
-                    self.state = AppState::UwpApps;
-                    self.selected = 0;
+                    self.open_checkboxes(AppState::WingetUpgrades);
                 }
                 2 => {
-                    self.state = AppState::OtherInstallers;
-                    self.selected = 0;
+                    self.open_checkboxes(AppState::UwpApps);
+                }
+                3 => {
+                    self.open_checkboxes(AppState::OtherInstallers);
                 }
                 _ => {}
             },
             AppState::PersonalizationMenu => match self.selected {
                 0 => {
-                    self.state = AppState::PersonalizationBasic;
-                    self.selected = 0;
+                    self.open_checkboxes(AppState::PersonalizationBasic);
                 }
                 1 => {
-                    self.state = AppState::PersonalizationContext;
-                    self.selected = 0;
+                    self.open_checkboxes(AppState::PersonalizationContext);
                 }
                 _ => {}
             },
+            AppState::Profiles => {
+                if self.selected < self.profiles.len() {
+                    return Some(Action::ApplyProfile(self.selected));
+                }
+                if self.selected == self.profiles.len() {
+                    return Some(Action::SaveProfile);
+                }
+            }
+            // Для твиков сначала показываем план, остальное применяем сразу
+            _ if self.state.tweak_category().is_some() => return Some(Action::ReviewPlan),
+            _ if self.state.has_checkboxes() => return Some(Action::ApplyCheckboxes),
             _ => {}
         }
         None
     }
 
     fn handle_escape(&mut self) {
+        // Отметки запоминаются до конца сеанса: из них собирается сохраняемый профиль
+        if let Some(screen) = &self.checkboxes {
+            match self.state {
+                AppState::WingetPackages => self.package_selection.extend(screen.selection()),
+                AppState::UwpApps => self.uwp_selection = screen.selection(),
+                _ => {}
+            }
+        }
+        if self.state == AppState::ComplianceReport {
+            self.state = AppState::Profiles;
+            self.report = None;
+            return;
+        }
         self.state = match self.state {
-            AppState::PackagesMenu | AppState::ExplorerSettings | AppState::SystemSettings | AppState::PersonalizationMenu => {
+            AppState::PackagesMenu
+            | AppState::ExplorerSettings
+            | AppState::SystemSettings
+            | AppState::PersonalizationMenu
+            | AppState::Profiles => {
                 AppState::MainMenu
             }
-            AppState::WingetPackages | AppState::UwpApps | AppState::OtherInstallers => {
+            AppState::WingetPackages | AppState::WingetUpgrades | AppState::UwpApps | AppState::OtherInstallers => {
                 AppState::PackagesMenu
             }
             AppState::PersonalizationBasic | AppState::PersonalizationContext => {
@@ -138,9 +789,14 @@ impl App {
             _ => self.state.clone(),
         };
         self.selected = 0;
+        self.checkboxes = None;
     }
 
     fn move_selection(&mut self, delta: isize) {
+        if let Some(screen) = &mut self.checkboxes {
+            screen.list.move_selection(delta);
+            return;
+        }
         let items = self.get_menu_items();
         if items.is_empty() {
             return;
@@ -153,9 +809,25 @@ impl App {
 
 enum Action {
     RestartExplorer,
+    RevertLastSession,
+    ApplyProfile(usize),
+    SaveProfile,
+    ReviewPlan,
+    ApplyCheckboxes,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // С командой работаем без интерактивного интерфейса
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match cli::parse(&args) {
+        Ok(Some(invocation)) => std::process::exit(cli::execute(&Context::system(), &invocation, &mut io::stdout())),
+        Ok(None) => {}
+        Err(message) => {
+            eprintln!("❌ {}\n\n{}", message, cli::USAGE);
+            std::process::exit(cli::EXIT_USAGE);
+        }
+    }
+
     // Инициализация терминала
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -163,7 +835,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new();
+    let mut app = App::new()?;
     let mut running = true;
 
     while running {
@@ -200,17 +872,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             f.render_widget(title_widget, chunks[0]);
 
             // Основное меню
+            let packages_title = format!("Пакеты {}", app.backend);
             let current_title = match app.state {
                 AppState::MainMenu => "Главное меню",
                 AppState::PackagesMenu => "Управление пакетами",
                 AppState::PersonalizationMenu => "Персонализация",
+                AppState::WingetPackages if app.backend != Backend::Winget => &packages_title,
                 AppState::WingetPackages => "Winget-пакеты",
+                AppState::WingetUpgrades => "Обновления winget",
                 AppState::UwpApps => "UWP-приложения",
                 AppState::OtherInstallers => "Другое (.exe / DISM)",
                 AppState::ExplorerSettings => "Проводник и рабочий стол",
                 AppState::SystemSettings => "Система",
                 AppState::PersonalizationBasic => "Персонализация - Основное",
                 AppState::PersonalizationContext => "Персонализация - Контекстное меню",
+                AppState::Profiles | AppState::ComplianceReport => "Профили",
             };
 
             let menu_items: Vec<ListItem> = app
@@ -223,7 +899,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     } else {
                         Style::default()
                     };
-                    ListItem::new(*item).style(style)
+                    ListItem::new(item.clone()).style(style)
                 })
                 .collect();
 
@@ -232,32 +908,145 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .highlight_symbol("➤ ")
                 .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
 
-            let mut list_state = ListState::default();
-            list_state.select(Some(app.selected));
-            f.render_stateful_widget(menu, chunks[1], &mut list_state);
+            if let Some(screen) = &app.checkboxes {
+                let (list, mut list_state) = screen.list.render(current_title);
+                f.render_stateful_widget(list, chunks[1], &mut list_state);
+            } else {
+                let mut list_state = ListState::default();
+                list_state.select(Some(app.selected));
+                f.render_stateful_widget(menu, chunks[1], &mut list_state);
+            }
 
             // Подсказки управления
-            let hints = Paragraph::new("↑↓ - Навигация | Enter - Выбрать | Esc - Назад | Q - Выход")
+            let hints = if app.state == AppState::Profiles {
+                "↑↓ - Навигация | Enter - Применить | C - Проверить | Esc - Назад | Q - Выход"
+            } else if app.state == AppState::WingetPackages {
+                "↑↓ - Навигация | Space - Отметить | Enter - Применить | / - Поиск | M - Менеджер | E - Экспорт | I - Импорт | D - DSC | Esc - Назад | Q - Выход"
+            } else if app.state.tweak_category().is_some() {
+                "↑↓ - Навигация | Space - Отметить | Enter - Применить | D - DSC | Esc - Назад | Q - Выход"
+            } else if app.state.has_checkboxes() {
+                "↑↓ - Навигация | Space - Отметить | Enter - Применить | Esc - Назад | Q - Выход"
+            } else {
+                "↑↓ - Навигация | Enter - Выбрать | Esc - Назад | Q - Выход"
+            };
+            let hints = Paragraph::new(hints)
                 .style(Style::default().fg(Color::DarkGray))
                 .block(Block::default().borders(Borders::ALL));
             f.render_widget(hints, chunks[2]);
+            if let Some((prompt, value)) = &app.input {
+                let title = match prompt {
+                    Prompt::Search => format!("Поиск {} (Enter - искать, Esc - отмена)", app.backend),
+                    Prompt::ProfileName => "Имя профиля (Enter - сохранить, Esc - отмена)".to_string(),
+                };
+                menu::render_input(f, chunks[2], &title, value);
+            }
+
+            if let (AppState::ComplianceReport, Some(report)) = (&app.state, &app.report) {
+                menu::render_compliance_report(f, size, report, app.scroll);
+            }
+            if let Some(plan) = &app.plan {
+                menu::render_plan_review(f, size, plan, app.scroll);
+            }
+            if let Some(task) = &app.task {
+                menu::render_progress(f, size, task, app.scroll);
+            }
         })?;
 
-        // Обработка событий
+        // Ждём клавишу недолго, чтобы подхватывать вывод фоновой задачи
+        if let Some(task) = &mut app.task {
+            task.poll();
+        }
+        app.finish_loading();
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+
+        // Обработка событий; Windows сообщает и нажатие, и отпускание клавиши
         if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            // Окно задачи перехватывает клавиши, пока открыто
+            if let Some(task) = &app.task {
+                match key.code {
+                    KeyCode::Up => app.scroll = app.scroll.saturating_add(1),
+                    KeyCode::Down => app.scroll = app.scroll.saturating_sub(1),
+                    KeyCode::Esc | KeyCode::Char('c') | KeyCode::Char('C') if task.is_running() => task.cancel(),
+                    KeyCode::Esc | KeyCode::Enter if !task.is_running() => app.close_task(),
+                    _ => {}
+                }
+                continue;
+            }
+            // Окно с планом перехватывает клавиши, пока открыто
+            if app.plan.is_some() {
+                match key.code {
+                    KeyCode::Up => app.scroll = app.scroll.saturating_sub(1),
+                    KeyCode::Down => app.scroll = app.scroll.saturating_add(1),
+                    KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => app.execute_plan(),
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => app.plan = None,
+                    _ => {}
+                }
+                continue;
+            }
+            // Строка ввода перехватывает клавиши, пока открыта
+            if let Some((prompt, value)) = &mut app.input {
+                match key.code {
+                    KeyCode::Char(c) => value.push(c),
+                    KeyCode::Backspace => {
+                        value.pop();
+                    }
+                    KeyCode::Enter => {
+                        let (prompt, value) = (*prompt, value.trim().to_string());
+                        app.input = None;
+                        match prompt {
+                            _ if value.is_empty() => {}
+                            Prompt::Search => app.start_load(Load::Search(value)),
+                            Prompt::ProfileName => app.save_current_profile(value),
+                        }
+                    }
+                    KeyCode::Esc => app.input = None,
+                    _ => {}
+                }
+                continue;
+            }
             match key.code {
                 KeyCode::Char('q') | KeyCode::Char('Q') => {
                     running = false;
                 }
+                KeyCode::Up if app.state == AppState::ComplianceReport => app.scroll = app.scroll.saturating_sub(1),
+                KeyCode::Down if app.state == AppState::ComplianceReport => app.scroll = app.scroll.saturating_add(1),
+                KeyCode::Char('c') | KeyCode::Char('C') if app.state == AppState::Profiles => app.start_load(Load::Compliance),
+                KeyCode::Char('/') if app.state == AppState::WingetPackages => app.input = Some((Prompt::Search, String::new())),
+                KeyCode::Char('m') | KeyCode::Char('M') if app.state == AppState::WingetPackages => app.switch_backend(),
+                KeyCode::Char('e') | KeyCode::Char('E') if app.state == AppState::WingetPackages => app.export_packages(),
+                KeyCode::Char('i') | KeyCode::Char('I') if app.state == AppState::WingetPackages => app.start_load(Load::Import),
+                KeyCode::Char('d') | KeyCode::Char('D')
+                    if app.state == AppState::WingetPackages || app.state.tweak_category().is_some() =>
+                {
+                    app.export_dsc()
+                }
+                KeyCode::Char(' ') => {
+                    if let Some(screen) = &mut app.checkboxes {
+                        screen.list.toggle_selected();
+                    }
+                }
                 KeyCode::Up => app.move_selection(-1),
                 KeyCode::Down => app.move_selection(1),
                 KeyCode::Enter => {
                     if let Some(action) = app.handle_enter() {
                         match action {
-                            Action::RestartExplorer => {
-                                // Здесь будет логика перезапуска проводника
-                                explorer::restart_explorer()?;
-                            }
+                            Action::RestartExplorer => app.restart_explorer(),
+                            Action::RevertLastSession => app.revert_last_session(),
+                            Action::ApplyProfile(index) => app.apply_profile(index),
+                            Action::SaveProfile => app.prompt_profile_name(),
+                            Action::ReviewPlan => match app.plan_checkboxes() {
+                                Ok(plan) => {
+                                    app.scroll = 0;
+                                    app.plan = plan;
+                                }
+                                Err(e) => app.start_task("План изменений", move || Err(e)),
+                            },
+                            Action::ApplyCheckboxes => app.apply_checkboxes(),
                         }
                     }
                 }
@@ -0,0 +1,139 @@
+use std::io;
+use std::path::Path;
+use crate::modules::package_manager::{self, Backend, PackageEntry, PackageManager};
+use crate::modules::packages::{self, VersionPin};
+use crate::modules::results::{ApplyStatus, Outcome};
+use crate::modules::winget::ExitCode;
+use crate::progress;
+use crate::utils::command::{CommandOutput, CommandRunner, CommandSpec};
+
+/// Коды установщика «перезагрузка нужна» (3010) и «перезагрузка начата» (1641)
+const REBOOT_CODES: [i32; 2] = [3010, 1641];
+
+/// Разбирает вывод с `--limit-output`: строки `id|версия[|...]`
+fn entries(output: &str) -> Vec<PackageEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.trim().split('|');
+            let id = fields.next().filter(|id| !id.is_empty())?;
+            let version = fields.next()?;
+            Some(PackageEntry {
+                id: id.to_string(),
+                name: id.to_string(),
+                version: version.to_string(),
+                available: None,
+                source: None,
+            })
+        })
+        .collect()
+}
+
+/// Chocolatey 2.x: `choco list` показывает только локальные пакеты.
+/// Установка требует прав администратора.
+pub struct Chocolatey<'a> {
+    runner: &'a dyn CommandRunner,
+}
+
+impl<'a> Chocolatey<'a> {
+    pub fn new(runner: &'a dyn CommandRunner) -> Self {
+        Self { runner }
+    }
+
+    fn run(&self, args: &[&str]) -> io::Result<(CommandSpec, CommandOutput)> {
+        let spec = package_manager::spec("choco", args);
+        let output = self.runner.run(&spec)?;
+        Ok((spec, output))
+    }
+
+    /// Итог изменения: коды перезагрузки — не ошибка
+    fn change(&self, args: &[&str], markers: &[(&str, ApplyStatus, &str)]) -> io::Result<Outcome> {
+        let (spec, output) = self.run(args)?;
+        if output.code.is_some_and(|code| REBOOT_CODES.contains(&code)) {
+            return Ok(Outcome::new(ApplyStatus::NeedsReboot, ExitCode::RebootRequired.message()));
+        }
+        package_manager::outcome_from_output(&spec, output, markers)
+    }
+}
+
+impl PackageManager for Chocolatey<'_> {
+    fn backend(&self) -> Backend {
+        Backend::Chocolatey
+    }
+
+    fn list_installed(&self) -> io::Result<Vec<PackageEntry>> {
+        let (spec, output) = self.run(&["list", "--limit-output"])?;
+        Ok(entries(&output.check(&spec)?.stdout))
+    }
+
+    fn search(&self, query: &str) -> io::Result<Vec<PackageEntry>> {
+        let (spec, output) = self.run(&["search", query, "--limit-output"])?;
+        Ok(entries(&output.check(&spec)?.stdout))
+    }
+
+    fn install(&self, id: &str, pin: &VersionPin) -> io::Result<Outcome> {
+        progress!("🔄 Установка пакета {} через choco ({})...", id, pin);
+        let mut args = vec!["install", id, "-y", "--no-progress"];
+        if let VersionPin::Exact(version) = pin {
+            args.extend(["--version", version]);
+        }
+        let markers = [("already installed", ApplyStatus::Skipped, ExitCode::AlreadyInstalled.message())];
+        let outcome = self.change(&args, &markers)?;
+        packages::report_outcome(id, &outcome, "успешно установлен");
+        Ok(outcome)
+    }
+
+    fn uninstall(&self, id: &str) -> io::Result<Outcome> {
+        progress!("🔄 Удаление пакета {} через choco...", id);
+        let markers = [("is not installed", ApplyStatus::Skipped, "пакет не установлен")];
+        let outcome = self.change(&["uninstall", id, "-y"], &markers)?;
+        packages::report_outcome(id, &outcome, "успешно удалён");
+        Ok(outcome)
+    }
+
+    fn upgrade(&self, id: &str) -> io::Result<Outcome> {
+        progress!("🔄 Обновление пакета {} через choco...", id);
+        let markers = [("is the latest version available", ApplyStatus::Skipped, ExitCode::UpdateNotApplicable.message())];
+        let outcome = self.change(&["upgrade", id, "-y", "--no-progress"], &markers)?;
+        packages::report_outcome(id, &outcome, "обновлён");
+        Ok(outcome)
+    }
+
+    /// `choco export` пишет packages.config с версиями
+    fn export(&self, path: &Path) -> io::Result<()> {
+        let path = path.to_string_lossy();
+        let (spec, output) = self.run(&["export", "--output-file-path", &path, "--include-version-numbers"])?;
+        output.check(&spec).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::ScriptedRunner;
+
+    #[test]
+    fn test_choco_outcomes() {
+        let runner = ScriptedRunner::new()
+            .expect("choco", &["list", "--limit-output"], CommandOutput::ok("7zip|23.1.0\r\nchocolatey|2.2.2\r\ngit|2.44.0\r\n"))
+            .expect(
+                "choco",
+                &["install", "git", "-y", "--no-progress", "--version", "2.44.0"],
+                CommandOutput::ok("git v2.44.0 already installed.\n Use --force to reinstall"),
+            )
+            .expect("choco", &["install", "vcredist140", "-y", "--no-progress"], CommandOutput::with_code(3010, ""))
+            .expect("choco", &["upgrade", "nosuchpkg", "-y", "--no-progress"], CommandOutput::with_code(1, "nosuchpkg not installed. The package was not found"));
+        let choco = Chocolatey::new(&runner);
+
+        let installed = choco.list_installed().unwrap();
+        assert_eq!(installed.len(), 3);
+        assert_eq!(installed[0].id, "7zip");
+        assert_eq!(installed[2].version, "2.44.0");
+
+        let pin = VersionPin::Exact("2.44.0".to_string());
+        assert_eq!(choco.install("git", &pin).unwrap().status, ApplyStatus::Skipped);
+        assert_eq!(choco.install("vcredist140", &VersionPin::Latest).unwrap().status, ApplyStatus::NeedsReboot);
+        assert!(choco.upgrade("nosuchpkg").is_err());
+        assert!(runner.is_exhausted());
+    }
+}
@@ -0,0 +1,319 @@
+use std::fmt;
+use std::io;
+use serde::{Deserialize, Serialize};
+use crate::modules::profile::Profile;
+use crate::modules::tweaks::{self, Catalog, Host, Operation, StartType};
+use crate::modules::packages::{self, VersionPin};
+use crate::modules::uwp;
+use crate::utils::registry::{RegRoot, RegValue, RegistryBackend};
+
+/// Результат проверки одного пункта
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComplianceStatus {
+    /// Состояние совпадает с профилем
+    Complies,
+    /// Состояние отличается от профиля
+    Drifted,
+    /// Проверить нельзя
+    Unknown,
+}
+
+impl ComplianceStatus {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            ComplianceStatus::Complies => "✅",
+            ComplianceStatus::Drifted => "❌",
+            ComplianceStatus::Unknown => "❔",
+        }
+    }
+}
+
+/// Что проверялось
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Registry,
+    Service,
+    Command,
+    Tweak,
+    Package,
+    Uwp,
+}
+
+/// Пункт отчёта
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComplianceItem {
+    pub kind: ItemKind,
+    /// Твик, пакет или приложение, к которому относится пункт
+    pub id: String,
+    /// Что именно проверялось: ключ реестра, служба и т. п.
+    pub target: String,
+    pub status: ComplianceStatus,
+    pub expected: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<String>,
+}
+
+impl fmt::Display for ComplianceItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [{}] {}: ожидается {}", self.status.icon(), self.id, self.target, self.expected)?;
+        if let Some(actual) = &self.actual {
+            write!(f, ", сейчас {}", actual)?;
+        }
+        Ok(())
+    }
+}
+
+/// Отчёт о соответствии машины профилю
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComplianceReport {
+    pub profile: String,
+    pub items: Vec<ComplianceItem>,
+}
+
+impl ComplianceReport {
+    pub fn count(&self, status: ComplianceStatus) -> usize {
+        self.items.iter().filter(|i| i.status == status).count()
+    }
+
+    pub fn has_drift(&self) -> bool {
+        self.count(ComplianceStatus::Drifted) > 0
+    }
+
+    /// Код возврата для скриптов: 0 — всё совпадает, 1 — есть расхождения
+    pub fn exit_code(&self) -> i32 {
+        i32::from(self.has_drift())
+    }
+
+    /// Краткая сводка: "соответствует 10, расхождений 2, не проверено 1"
+    pub fn summary(&self) -> String {
+        format!(
+            "соответствует {}, расхождений {}, не проверено {}",
+            self.count(ComplianceStatus::Complies),
+            self.count(ComplianceStatus::Drifted),
+            self.count(ComplianceStatus::Unknown)
+        )
+    }
+
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl fmt::Display for ComplianceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Профиль \"{}\": {}", self.profile, self.summary())?;
+        for item in &self.items {
+            writeln!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+fn show_value(value: Option<RegValue>) -> String {
+    value.map_or("(нет)".to_string(), |v| v.to_string())
+}
+
+fn status(matches: Option<bool>) -> ComplianceStatus {
+    match matches {
+        Some(true) => ComplianceStatus::Complies,
+        Some(false) => ComplianceStatus::Drifted,
+        None => ComplianceStatus::Unknown,
+    }
+}
+
+/// Проверяет одну операцию твика
+fn check_operation(reg: &dyn RegistryBackend, id: &str, op: &Operation) -> ComplianceItem {
+    let matches = tweaks::operation_matches(reg, op);
+    let (kind, target, expected, actual) = match op {
+        Operation::SetValue { root, path, name, value, .. } => (
+            ItemKind::Registry,
+            format!("{}\\{}\\{}", root, path, name),
+            value.to_string(),
+            Some(show_value(reg.read_value(*root, path, name).ok())),
+        ),
+        Operation::DeleteValue { root, path, name } => (
+            ItemKind::Registry,
+            format!("{}\\{}\\{}", root, path, name),
+            "(нет)".to_string(),
+            Some(show_value(reg.read_value(*root, path, name).ok())),
+        ),
+        Operation::DeleteKey { root, path } => (
+            ItemKind::Registry,
+            format!("{}\\{}", root, path),
+            "ключа нет".to_string(),
+            Some(if reg.key_exists(*root, path) { "ключ есть" } else { "ключа нет" }.to_string()),
+        ),
+        Operation::Service { name, start, .. } => {
+            let current = match reg.read_value(RegRoot::LocalMachine, &tweaks::service_key(name), "Start") {
+                Ok(RegValue::Dword(v)) => StartType::from_registry_value(v).map(|s| s.sc_name().to_string()),
+                _ => None,
+            };
+            (
+                ItemKind::Service,
+                format!("служба {}", name),
+                start.sc_name().to_string(),
+                Some(current.unwrap_or_else(|| "(нет)".to_string())),
+            )
+        }
+        Operation::Run { program, args } => (
+            ItemKind::Command,
+            format!("{} {}", program, args.join(" ")),
+            "результат команды".to_string(),
+            None,
+        ),
+    };
+    ComplianceItem {
+        kind,
+        id: id.to_string(),
+        target,
+        status: status(matches),
+        expected,
+        actual,
+    }
+}
+
+/// Сравнивает состояние машины с профилем
+pub fn check_profile(host: &Host, catalog: &Catalog, profile: &Profile) -> ComplianceReport {
+    let mut items = Vec::new();
+
+    for (id, enable) in &profile.tweaks {
+        match catalog.get(id) {
+            Some(tweak) => items.extend(
+                tweak
+                    .detect_operations(*enable)
+                    .iter()
+                    .map(|op| check_operation(host.registry, id, op)),
+            ),
+            None => items.push(ComplianceItem {
+                kind: ItemKind::Tweak,
+                id: id.clone(),
+                target: "твик".to_string(),
+                status: ComplianceStatus::Unknown,
+                expected: if *enable { "включено" } else { "выключено" }.to_string(),
+                actual: Some("нет в каталоге".to_string()),
+            }),
+        }
+    }
+
+    for spec in &profile.winget_packages {
+        let (id, pin) = packages::parse_package_spec(spec);
+        let installed = packages::installed_version(host.runner, &id);
+        items.push(ComplianceItem {
+            kind: ItemKind::Package,
+            id,
+            target: "winget".to_string(),
+            status: status(installed.as_ref().ok().map(|v| v.as_deref().is_some_and(|v| pin.is_satisfied_by(v)))),
+            expected: match pin {
+                VersionPin::Latest => "установлен".to_string(),
+                pin => format!("установлен {}", pin),
+            },
+            actual: Some(match installed {
+                Ok(Some(version)) => format!("установлен {}", version),
+                Ok(None) => "не установлен".to_string(),
+                Err(e) => e.to_string(),
+            }),
+        });
+    }
+
+    if !profile.uwp_remove.is_empty() {
+        let installed = uwp::get_installed_uwp(host.runner);
+        for name in &profile.uwp_remove {
+            let (status, actual) = match &installed {
+                Ok(apps) if apps.iter().any(|(n, _)| n.eq_ignore_ascii_case(name)) => {
+                    (ComplianceStatus::Drifted, "установлено".to_string())
+                }
+                Ok(_) => (ComplianceStatus::Complies, "удалено".to_string()),
+                Err(e) => (ComplianceStatus::Unknown, e.to_string()),
+            };
+            items.push(ComplianceItem {
+                kind: ItemKind::Uwp,
+                id: name.clone(),
+                target: "UWP".to_string(),
+                status,
+                expected: "удалено".to_string(),
+                actual: Some(actual),
+            });
+        }
+    }
+
+    ComplianceReport {
+        profile: profile.name.clone(),
+        items,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+    use crate::utils::registry::MemoryRegistry;
+
+    const ADVANCED: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced";
+
+    fn profile() -> Profile {
+        let mut profile = Profile::new("Стандарт");
+        profile.tweaks.insert("show_file_extensions".to_string(), true);
+        profile.tweaks.insert("show_hidden_files".to_string(), true);
+        profile.tweaks.insert("disable_telemetry".to_string(), true);
+        profile.winget_packages.push("7zip.7zip".to_string());
+        profile.uwp_remove.push("Microsoft.BingNews".to_string());
+        profile
+    }
+
+    #[test]
+    fn test_check_reports_drift() {
+        let reg = MemoryRegistry::new();
+        reg.write_value(RegRoot::CurrentUser, ADVANCED, "HideFileExt", &RegValue::Dword(0)).unwrap();
+        reg.write_value(RegRoot::CurrentUser, ADVANCED, "Hidden", &RegValue::Dword(2)).unwrap();
+        reg.write_value(RegRoot::LocalMachine, &tweaks::service_key("DiagTrack"), "Start", &RegValue::Dword(4)).unwrap();
+        let runner = ScriptedRunner::new()
+            .expect(
+                "winget",
+                &["list", "--id", "7zip.7zip", "--exact"],
+                CommandOutput::ok("Имя    ИД         Версия  Источник\n------------------------------------\n7-Zip  7zip.7zip  23.01   winget"),
+            )
+            .expect(
+                "powershell",
+                &["-Command", "Get-AppxPackage | Select-Object Name, PackageFullName | ConvertTo-Json"],
+                CommandOutput::ok(r#"{ "Name": "Microsoft.BingNews", "PackageFullName": "Microsoft.BingNews_1_x64__8wekyb3d8bbwe" }"#),
+            );
+
+        let report = check_profile(&Host::new(&reg, &runner), &Catalog::builtin(), &profile());
+        let find = |target: &str| report.items.iter().find(|i| i.target.ends_with(target)).unwrap();
+
+        assert_eq!(find("HideFileExt").status, ComplianceStatus::Complies);
+        assert_eq!(find("\\Hidden").status, ComplianceStatus::Drifted);
+        assert_eq!(find("\\Hidden").actual.as_deref(), Some("2 (DWORD)"));
+        assert_eq!(find("служба DiagTrack").status, ComplianceStatus::Complies);
+        // Службы dmwappushservice и ключа политики нет — проверить нельзя
+        assert_eq!(find("служба dmwappushservice").status, ComplianceStatus::Unknown);
+        assert_eq!(find("winget").status, ComplianceStatus::Complies);
+        assert_eq!(find("UWP").status, ComplianceStatus::Drifted);
+        assert!(report.has_drift());
+        assert_eq!(report.exit_code(), 1);
+    }
+
+    #[test]
+    fn test_unavailable_tools_are_unknown() {
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new()
+            .expect_error("winget", &["list", "--id", "7zip.7zip", "--exact"], io::ErrorKind::NotFound)
+            .expect_error(
+                "powershell",
+                &["-Command", "Get-AppxPackage | Select-Object Name, PackageFullName | ConvertTo-Json"],
+                io::ErrorKind::NotFound,
+            );
+        let mut profile = profile();
+        profile.tweaks.clear();
+
+        let report = check_profile(&Host::new(&reg, &runner), &Catalog::builtin(), &profile);
+        assert_eq!(report.count(ComplianceStatus::Unknown), 2);
+        assert_eq!(report.exit_code(), 0);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["items"][0]["status"], "unknown");
+        assert_eq!(json["items"][1]["kind"], "uwp");
+    }
+}
@@ -0,0 +1,367 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::modules::packages::{self, VersionPin};
+use crate::modules::profile::Profile;
+use crate::modules::tweaks::{Catalog, Operation, Selection, StartType, Tweak};
+use crate::utils::registry::{RegRoot, RegValue};
+
+/// Имя файла по умолчанию — его ищет `winget configure`
+pub const DEFAULT_FILE: &str = "configuration.dsc.yaml";
+
+const SCHEMA: &str = "https://aka.ms/configuration-dsc-schema/0.2";
+const PACKAGE_RESOURCE: &str = "Microsoft.WinGet.DSC/WinGetPackage";
+const REGISTRY_RESOURCE: &str = "PSDscResources/Registry";
+const SERVICE_RESOURCE: &str = "PSDscResources/Service";
+
+/// Значение параметра ресурса
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Setting {
+    Text(String),
+    Bool(bool),
+    List(Vec<String>),
+}
+
+impl From<&str> for Setting {
+    fn from(value: &str) -> Self {
+        Setting::Text(value.to_string())
+    }
+}
+
+/// Ресурс документа конфигурации
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Resource {
+    resource: &'static str,
+    id: String,
+    description: String,
+    elevated: bool,
+    settings: Vec<(&'static str, Setting)>,
+}
+
+/// Документ `winget configure`: пакеты — ресурсы `WinGetPackage`,
+/// твики — ресурсы `Registry` и `Service` из PSDscResources.
+/// Строится без обращения к системе: описывает выбор, а не текущее состояние.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Configuration {
+    resources: Vec<Resource>,
+    /// Операции, у которых нет DSC-ресурса: "твик: причина"
+    skipped: Vec<String>,
+}
+
+/// Строка YAML в одинарных кавычках; с управляющими символами — в двойных (как JSON)
+fn quote(value: &str) -> String {
+    if value.chars().any(char::is_control) {
+        return serde_json::Value::from(value).to_string();
+    }
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Тип и данные значения в терминах ресурса Registry
+fn registry_data(value: &RegValue) -> (&'static str, Setting) {
+    match value {
+        RegValue::String(s) => ("String", Setting::Text(s.clone())),
+        RegValue::ExpandString(s) => ("ExpandString", Setting::Text(s.clone())),
+        RegValue::MultiString(v) => ("MultiString", Setting::List(v.clone())),
+        RegValue::Dword(v) => ("DWord", Setting::Text(v.to_string())),
+        RegValue::Qword(v) => ("QWord", Setting::Text(v.to_string())),
+        RegValue::Binary(bytes) => ("Binary", Setting::Text(bytes.iter().map(|b| format!("{:02x}", b)).collect())),
+    }
+}
+
+fn registry_key(root: RegRoot, path: &str) -> Setting {
+    Setting::Text(format!("{}\\{}", root.long_name(), path))
+}
+
+fn startup_type(start: StartType) -> &'static str {
+    match start {
+        StartType::Auto => "Automatic",
+        StartType::Demand => "Manual",
+        StartType::Disabled => "Disabled",
+    }
+}
+
+impl Configuration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Документ по профилю: его твики и winget-пакеты
+    pub fn from_profile(profile: &Profile, catalog: &Catalog) -> io::Result<Self> {
+        let mut config = Self::new();
+        config.add_tweaks(catalog, &profile.tweaks)?;
+        let packages: Vec<(String, VersionPin)> = profile
+            .winget_packages
+            .iter()
+            .map(|spec| packages::parse_package_spec(spec))
+            .collect();
+        config.add_packages(&packages, &[]);
+        Ok(config)
+    }
+
+    /// Добавляет твики выбора в порядке каталога
+    pub fn add_tweaks(&mut self, catalog: &Catalog, selection: &Selection) -> io::Result<()> {
+        if let Some(id) = selection.keys().find(|id| catalog.get(id).is_none()) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("твик \"{}\" не найден в каталоге", id)));
+        }
+        for tweak in &catalog.tweaks {
+            if let Some(enable) = selection.get(&tweak.id) {
+                self.add_tweak(tweak, *enable);
+            }
+        }
+        Ok(())
+    }
+
+    fn add_tweak(&mut self, tweak: &Tweak, enable: bool) {
+        let state = if enable { "включить" } else { "выключить" };
+        let description = format!("{}: {}", tweak.title.text(), state);
+        for (n, op) in tweak.operations(enable).iter().enumerate() {
+            let (resource, elevated, settings) = match op {
+                Operation::SetValue { only_if_key_exists: true, path, .. } => {
+                    self.skipped.push(format!("{}: запись только в существующий ключ {}", tweak.id, path));
+                    continue;
+                }
+                Operation::SetValue { root, path, name, value, .. } => {
+                    let (kind, data) = registry_data(value);
+                    let settings = vec![
+                        ("Key", registry_key(*root, path)),
+                        ("ValueName", name.as_str().into()),
+                        ("ValueType", kind.into()),
+                        ("ValueData", data),
+                        ("Ensure", "Present".into()),
+                        ("Force", Setting::Bool(true)),
+                    ];
+                    (REGISTRY_RESOURCE, *root != RegRoot::CurrentUser, settings)
+                }
+                Operation::DeleteValue { root, path, name } => {
+                    let settings = vec![
+                        ("Key", registry_key(*root, path)),
+                        ("ValueName", name.as_str().into()),
+                        ("Ensure", "Absent".into()),
+                    ];
+                    (REGISTRY_RESOURCE, *root != RegRoot::CurrentUser, settings)
+                }
+                Operation::DeleteKey { root, path } => {
+                    // Пустое ValueName с Ensure: Absent удаляет ключ целиком
+                    let settings = vec![
+                        ("Key", registry_key(*root, path)),
+                        ("ValueName", "".into()),
+                        ("Ensure", "Absent".into()),
+                    ];
+                    (REGISTRY_RESOURCE, *root != RegRoot::CurrentUser, settings)
+                }
+                Operation::Service { name, start, stop } => {
+                    let mut settings = vec![("Name", name.as_str().into()), ("StartupType", startup_type(*start).into())];
+                    if *stop {
+                        settings.push(("State", "Stopped".into()));
+                    }
+                    (SERVICE_RESOURCE, true, settings)
+                }
+                Operation::Run { program, args } => {
+                    self.skipped.push(format!("{}: запуск {} {}", tweak.id, program, args.join(" ")));
+                    continue;
+                }
+            };
+            self.resources.push(Resource {
+                resource,
+                id: format!("{}-{}", tweak.id, n + 1),
+                description: description.clone(),
+                elevated: elevated || tweak.requires_admin,
+                settings,
+            });
+        }
+    }
+
+    /// Добавляет установку пакетов `install` и удаление пакетов `remove`.
+    /// Точная версия передаётся ресурсу, минимальная — нет: ставится последняя.
+    pub fn add_packages(&mut self, install: &[(String, VersionPin)], remove: &[String]) {
+        for (id, pin) in install {
+            let mut settings = vec![("id", id.as_str().into()), ("source", "winget".into())];
+            if let VersionPin::Exact(version) = pin {
+                settings.push(("version", version.as_str().into()));
+            }
+            self.push_package(id, format!("Установка {}", id), settings);
+        }
+        for id in remove {
+            let settings = vec![("id", id.as_str().into()), ("source", "winget".into()), ("Ensure", "Absent".into())];
+            self.push_package(id, format!("Удаление {}", id), settings);
+        }
+    }
+
+    fn push_package(&mut self, id: &str, description: String, settings: Vec<(&'static str, Setting)>) {
+        self.resources.push(Resource {
+            resource: PACKAGE_RESOURCE,
+            id: id.to_string(),
+            description,
+            elevated: false,
+            settings,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    /// Операции, которые не попали в документ
+    pub fn skipped(&self) -> &[String] {
+        &self.skipped
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_string())
+    }
+}
+
+/// YAML документа
+impl fmt::Display for Configuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# yaml-language-server: $schema={}", SCHEMA)?;
+        writeln!(f, "# Создано win-tool. Применение: winget configure -f {}", DEFAULT_FILE)?;
+        for skipped in &self.skipped {
+            writeln!(f, "# Не переносится: {}", skipped)?;
+        }
+        writeln!(f, "properties:")?;
+        writeln!(f, "  configurationVersion: 0.2.0")?;
+        if self.resources.is_empty() {
+            return writeln!(f, "  resources: []");
+        }
+        writeln!(f, "  resources:")?;
+        for resource in &self.resources {
+            writeln!(f, "    - resource: {}", resource.resource)?;
+            writeln!(f, "      id: {}", quote(&resource.id))?;
+            writeln!(f, "      directives:")?;
+            writeln!(f, "        description: {}", quote(&resource.description))?;
+            if resource.elevated {
+                writeln!(f, "        securityContext: elevated")?;
+            }
+            writeln!(f, "      settings:")?;
+            for (name, value) in &resource.settings {
+                match value {
+                    Setting::Text(text) => writeln!(f, "        {}: {}", name, quote(text))?,
+                    Setting::Bool(flag) => writeln!(f, "        {}: {}", name, flag)?,
+                    Setting::List(items) => {
+                        writeln!(f, "        {}:", name)?;
+                        for item in items {
+                            writeln!(f, "          - {}", quote(item))?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG: &str = r#"{
+        "tweaks": [
+            {
+                "id": "show_file_extensions",
+                "category": "explorer",
+                "title": { "ru": "Показывать расширения файлов" },
+                "on": [
+                    { "op": "set_value", "root": "HKCU", "path": "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced", "name": "HideFileExt", "value": { "type": "dword", "data": 0 } }
+                ],
+                "off": []
+            },
+            {
+                "id": "disable_telemetry",
+                "category": "system",
+                "title": { "ru": "Отключить телеметрию" },
+                "requires_admin": true,
+                "on": [
+                    { "op": "service", "name": "DiagTrack", "start": "disabled", "stop": true },
+                    { "op": "run", "program": "powercfg", "args": ["/hibernate", "off"] }
+                ],
+                "off": [
+                    { "op": "delete_key", "root": "HKLM", "path": "SOFTWARE\\Policies\\Microsoft\\Windows\\DataCollection" }
+                ]
+            }
+        ]
+    }"#;
+
+    const EXPECTED: &str = "\
+# yaml-language-server: $schema=https://aka.ms/configuration-dsc-schema/0.2
+# Создано win-tool. Применение: winget configure -f configuration.dsc.yaml
+# Не переносится: disable_telemetry: запуск powercfg /hibernate off
+properties:
+  configurationVersion: 0.2.0
+  resources:
+    - resource: PSDscResources/Registry
+      id: 'show_file_extensions-1'
+      directives:
+        description: 'Показывать расширения файлов: включить'
+      settings:
+        Key: 'HKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced'
+        ValueName: 'HideFileExt'
+        ValueType: 'DWord'
+        ValueData: '0'
+        Ensure: 'Present'
+        Force: true
+    - resource: PSDscResources/Service
+      id: 'disable_telemetry-1'
+      directives:
+        description: 'Отключить телеметрию: включить'
+        securityContext: elevated
+      settings:
+        Name: 'DiagTrack'
+        StartupType: 'Disabled'
+        State: 'Stopped'
+    - resource: Microsoft.WinGet.DSC/WinGetPackage
+      id: 'Git.Git'
+      directives:
+        description: 'Установка Git.Git'
+      settings:
+        id: 'Git.Git'
+        source: 'winget'
+        version: '2.44.0'
+    - resource: Microsoft.WinGet.DSC/WinGetPackage
+      id: 'Notepad++.Notepad++'
+      directives:
+        description: 'Удаление Notepad++.Notepad++'
+      settings:
+        id: 'Notepad++.Notepad++'
+        source: 'winget'
+        Ensure: 'Absent'
+";
+
+    #[test]
+    fn test_generate_yaml() {
+        let catalog = Catalog::from_json(CATALOG).unwrap();
+        let selection: Selection = [("disable_telemetry".to_string(), true), ("show_file_extensions".to_string(), true)]
+            .into_iter()
+            .collect();
+        let mut config = Configuration::new();
+        config.add_tweaks(&catalog, &selection).unwrap();
+        config.add_packages(
+            &[("Git.Git".to_string(), VersionPin::Exact("2.44.0".to_string()))],
+            &["Notepad++.Notepad++".to_string()],
+        );
+        assert_eq!(config.to_string(), EXPECTED);
+        assert_eq!(config.skipped().len(), 1);
+
+        let unknown: Selection = [("no_such_tweak".to_string(), true)].into_iter().collect();
+        assert_eq!(config.add_tweaks(&catalog, &unknown).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_quote_and_delete_key() {
+        assert_eq!(quote("it's"), "'it''s'");
+        assert_eq!(quote("a\nb"), "\"a\\nb\"");
+
+        let catalog = Catalog::from_json(CATALOG).unwrap();
+        let selection: Selection = [("disable_telemetry".to_string(), false)].into_iter().collect();
+        let mut config = Configuration::new();
+        config.add_tweaks(&catalog, &selection).unwrap();
+        let yaml = config.to_string();
+        assert!(yaml.contains("Key: 'HKEY_LOCAL_MACHINE\\SOFTWARE\\Policies\\Microsoft\\Windows\\DataCollection'"));
+        assert!(yaml.contains("ValueName: ''\n        Ensure: 'Absent'"));
+        assert!(yaml.contains("securityContext: elevated"));
+    }
+}
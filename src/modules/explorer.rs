@@ -1,9 +1,13 @@
 use std::io;
-use std::process::Command;
-use winreg::enums::*;
-use winreg::RegKey;
+use crate::modules::plan::{self, Plan};
+use crate::modules::results::ApplyReport;
+use crate::modules::tweaks::{self, Catalog, Category, Host, Selection};
+use crate::progress;
+use crate::utils::command::{CommandRunner, CommandSpec};
+use crate::utils::registry::RegistryBackend;
 
 /// Настройки проводника
+#[derive(Debug, Default)]
 pub struct ExplorerSettings {
     pub show_hidden_files: bool,
     pub show_file_extensions: bool,
@@ -11,128 +15,115 @@ pub struct ExplorerSettings {
     pub remove_shortcut_suffix: bool,
 }
 
-impl Default for ExplorerSettings {
-    fn default() -> Self {
+impl ExplorerSettings {
+    /// Значения в виде выбора твиков каталога
+    pub fn to_selection(&self) -> Selection {
+        [
+            ("show_hidden_files", self.show_hidden_files),
+            ("show_file_extensions", self.show_file_extensions),
+            ("open_this_pc", self.open_this_pc),
+            ("remove_shortcut_suffix", self.remove_shortcut_suffix),
+        ]
+        .into_iter()
+        .map(|(id, enable)| (id.to_string(), enable))
+        .collect()
+    }
+
+    /// Настройки из выбора твиков; отсутствующие считаются выключенными
+    pub fn from_selection(selection: &Selection) -> Self {
+        let get = |id: &str| selection.get(id).copied().unwrap_or(false);
         Self {
-            show_hidden_files: false,
-            show_file_extensions: false,
-            open_this_pc: false,
-            remove_shortcut_suffix: false,
+            show_hidden_files: get("show_hidden_files"),
+            show_file_extensions: get("show_file_extensions"),
+            open_this_pc: get("open_this_pc"),
+            remove_shortcut_suffix: get("remove_shortcut_suffix"),
         }
     }
 }
 
 /// Получает текущие настройки проводника
-pub fn get_explorer_settings() -> io::Result<ExplorerSettings> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    
-    let mut settings = ExplorerSettings::default();
-    
-    // Проверяем настройки из реестра
-    if let Ok(explorer_key) = hkcu.open_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced") {
-        settings.show_hidden_files = explorer_key.get_value::<u32, _>("Hidden").unwrap_or(0) == 1;
-        settings.show_file_extensions = explorer_key.get_value::<u32, _>("HideFileExt").unwrap_or(1) == 0;
-    }
-    
-    Ok(settings)
-}
-
-/// Показывает скрытые файлы и папки
-pub fn show_hidden_files(enable: bool) -> io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced")?;
-    
-    key.set_value("Hidden", &(if enable { 1u32 } else { 2u32 }))?;
-    
-    println!("{} Скрытые файлы и папки {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "показываются" } else { "скрыты" }
-    );
-    
-    Ok(())
-}
-
-/// Показывает расширения файлов
-pub fn show_file_extensions(enable: bool) -> io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced")?;
-    
-    key.set_value("HideFileExt", &(if enable { 0u32 } else { 1u32 }))?;
-    
-    println!("{} Расширения файлов {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "показываются" } else { "скрыты" }
-    );
-    
-    Ok(())
-}
-
-/// Открывает "Этот ПК" вместо "Главная" в проводнике
-pub fn open_this_pc(enable: bool) -> io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced")?;
-    
-    // LaunchTo: 1 = Этот ПК, 2 = Быстрый доступ
-    key.set_value("LaunchTo", &(if enable { 1u32 } else { 2u32 }))?;
-    
-    println!("{} Проводник открывается в \"{}\"", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "Этот ПК" } else { "Быстрый доступ" }
-    );
-    
-    Ok(())
-}
-
-/// Убирает окончание "-Ярлык" у новых ярлыков
-pub fn remove_shortcut_suffix(enable: bool) -> io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Explorer")?;
-    
-    if enable {
-        key.set_value("link", &"")?; // Пустая строка
-    } else {
-        let _ = key.delete_value("link"); // Удаляем ключ для значения по умолчанию
-    }
-    
-    println!("{} Окончание \"-Ярлык\" {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "убрано" } else { "используется" }
-    );
-    
-    Ok(())
+pub fn get_explorer_settings(reg: &dyn RegistryBackend, catalog: &Catalog) -> ExplorerSettings {
+    ExplorerSettings::from_selection(&tweaks::current_selection(reg, catalog, Category::Explorer))
 }
 
 /// Перезагружает проводник Windows
-pub fn restart_explorer() -> io::Result<()> {
-    println!("🔄 Перезагрузка проводника...");
+pub fn restart_explorer(runner: &dyn CommandRunner) -> io::Result<()> {
+    progress!("🔄 Перезагрузка проводника...");
     
     // Закрываем процесс explorer.exe
-    Command::new("taskkill")
-        .args(&["/F", "/IM", "explorer.exe"])
-        .output()?;
+    runner.run(&CommandSpec::new("taskkill").args(["/F", "/IM", "explorer.exe"]))?;
     
     // Ждем немного
     std::thread::sleep(std::time::Duration::from_millis(500));
     
     // Запускаем проводник снова
-    Command::new("explorer.exe")
-        .spawn()?;
+    runner.spawn(&CommandSpec::new("explorer.exe"))?;
     
-    println!("✅ Проводник успешно перезапущен.");
+    progress!("✅ Проводник успешно перезапущен.");
     
     Ok(())
 }
 
+/// Планирует изменения для настроек проводника, ничего не меняя
+pub fn plan_explorer_settings(reg: &dyn RegistryBackend, catalog: &Catalog, settings: &ExplorerSettings) -> io::Result<Plan> {
+    plan::plan_selection(reg, catalog, &settings.to_selection())
+}
+
 /// Применяет все настройки проводника
-pub fn apply_explorer_settings(settings: &ExplorerSettings) -> io::Result<()> {
-    println!("⚙️  Применение настроек проводника...");
-    
-    show_hidden_files(settings.show_hidden_files)?;
-    show_file_extensions(settings.show_file_extensions)?;
-    open_this_pc(settings.open_this_pc)?;
-    remove_shortcut_suffix(settings.remove_shortcut_suffix)?;
-    
-    println!("✅ Настройки проводника применены. Для применения изменений перезагрузите проводник.");
-    
-    Ok(())
+pub fn apply_explorer_settings(host: &Host, catalog: &Catalog, settings: &ExplorerSettings) -> io::Result<ApplyReport> {
+    progress!("⚙️  Применение настроек проводника...");
+    Ok(plan_explorer_settings(host.registry, catalog, settings)?.execute(host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::ScriptedRunner;
+    use crate::utils::registry::{MemoryRegistry, RegRoot, RegValue};
+
+    const ADVANCED_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced";
+    const EXPLORER_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer";
+
+    #[test]
+    fn test_explorer_settings_roundtrip() {
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new();
+        let settings = ExplorerSettings {
+            show_hidden_files: true,
+            show_file_extensions: true,
+            open_this_pc: true,
+            remove_shortcut_suffix: true,
+        };
+        let catalog = Catalog::builtin();
+        apply_explorer_settings(&Host::new(&reg, &runner), &catalog, &settings).unwrap();
+
+        assert_eq!(reg.read_value(RegRoot::CurrentUser, ADVANCED_KEY, "LaunchTo").unwrap(), RegValue::Dword(1));
+        assert!(reg.read_value(RegRoot::CurrentUser, EXPLORER_KEY, "link").is_ok());
+
+        let current = get_explorer_settings(&reg, &catalog);
+        assert!(current.show_hidden_files);
+        assert!(current.show_file_extensions);
+        assert!(current.open_this_pc);
+        assert!(current.remove_shortcut_suffix);
+    }
+
+    #[test]
+    fn test_restore_shortcut_suffix() {
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new();
+        let host = Host::new(&reg, &runner);
+        let catalog = Catalog::builtin();
+
+        tweaks::apply_by_id(&host, &catalog, "remove_shortcut_suffix", true).unwrap();
+        tweaks::apply_by_id(&host, &catalog, "remove_shortcut_suffix", false).unwrap();
+        assert!(reg.read_value(RegRoot::CurrentUser, EXPLORER_KEY, "link").is_err());
+    }
+
+    #[test]
+    fn test_selection_roundtrip() {
+        let settings = ExplorerSettings { open_this_pc: true, ..Default::default() };
+        let restored = ExplorerSettings::from_selection(&settings.to_selection());
+        assert!(restored.open_this_pc);
+        assert!(!restored.show_hidden_files);
+    }
 }
@@ -0,0 +1,524 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde_json::{Map, Value};
+use crate::modules::results::{ApplyStatus, Outcome};
+use crate::progress;
+
+/// Шаблон настроек в data/config_templates/firefox/
+pub const PREFS_TEMPLATE: &str = "pref.js";
+/// Файл настроек пользователя в папке профиля; Firefox читает его при каждом запуске
+pub const USER_JS: &str = "user.js";
+/// Копия user.js до первого изменения
+pub const USER_JS_BACKUP: &str = "user.js.bak";
+/// Шаблон корпоративных политик в data/config_templates/firefox/
+pub const POLICIES_TEMPLATE: &str = "policies.json";
+
+/// Политики из схемы policy-templates (ключи объекта `policies`).
+/// Опечатку в имени Firefox молча игнорирует, поэтому неизвестные имена выводятся
+/// предупреждением; список может отставать от новых версий Firefox, так что это не ошибка.
+pub const KNOWN_POLICIES: &[&str] = &[
+    "3rdparty", "AllowedDomainsForApps", "AllowFileSelectionDialogs", "AppAutoUpdate", "AppUpdatePin",
+    "AppUpdateURL", "Authentication", "AutofillAddressEnabled", "AutofillCreditCardEnabled",
+    "AutoLaunchProtocolsFromOrigins", "BackgroundAppUpdate", "BlockAboutAddons", "BlockAboutConfig",
+    "BlockAboutProfiles", "BlockAboutSupport", "Bookmarks", "CaptivePortal", "Certificates", "Containers",
+    "ContentAnalysis", "Cookies", "DefaultDownloadDirectory", "DisableAccounts", "DisableAppUpdate",
+    "DisableBuiltinPDFViewer", "DisableDefaultBrowserAgent", "DisableDeveloperTools", "DisableEncryptedClientHello",
+    "DisableFeedbackCommands", "DisableFirefoxAccounts", "DisableFirefoxScreenshots", "DisableFirefoxStudies",
+    "DisableForgetButton", "DisableFormHistory", "DisableMasterPasswordCreation", "DisablePasswordReveal",
+    "DisablePocket", "DisablePrivateBrowsing", "DisableProfileImport", "DisableProfileRefresh", "DisableSafeMode",
+    "DisableSecurityBypass", "DisableSetDesktopBackground", "DisableSystemAddonUpdate", "DisableTelemetry",
+    "DisableThirdPartyModuleBlocking", "DisplayBookmarksToolbar", "DisplayMenuBar", "DNSOverHTTPS",
+    "DontCheckDefaultBrowser", "DownloadDirectory", "EnableTrackingProtection", "EncryptedMediaExtensions",
+    "ExemptDomainFileTypePairsFromFileTypeDownloadWarnings", "ExtensionSettings", "ExtensionUpdate", "Extensions",
+    "FirefoxHome", "FirefoxSuggest", "GoToIntranetSiteForSingleWordEntryInAddressBar", "Handlers",
+    "HardwareAcceleration", "Homepage", "HttpAllowlist", "HttpsOnlyMode", "InstallAddonsPermission",
+    "LegacyProfiles", "LegacySameSiteCookieBehaviorEnabled", "LegacySameSiteCookieBehaviorEnabledForDomainList",
+    "LocalFileLinks", "ManagedBookmarks", "ManualAppUpdateOnly", "MicrosoftEntraSSO", "NetworkPrediction",
+    "NewTabPage", "NoDefaultBookmarks", "OfferToSaveLogins", "OfferToSaveLoginsDefault", "OverrideFirstRunPage",
+    "OverridePostUpdatePage", "PasswordManagerEnabled", "PasswordManagerExceptions", "PDFjs", "Permissions",
+    "PictureInPicture", "PopupBlocking", "PostQuantumKeyAgreementEnabled", "Preferences", "PrimaryPassword",
+    "PrintingEnabled", "PrivateBrowsingModeAvailability", "PromptForDownloadLocation", "Proxy", "RequestedLocales",
+    "SanitizeOnShutdown", "SearchBar", "SearchEngines", "SearchSuggestEnabled", "SecurityDevices",
+    "ShowHomeButton", "SSLVersionMax", "SSLVersionMin", "StartDownloadsInTempDirectory", "SupportMenu",
+    "TranslateEnabled", "UserMessaging", "UseSystemPrintDialog", "WebsiteFilter", "WindowsSSO",
+];
+
+/// Секция INI: имя и пары ключ — значение в порядке файла
+pub type IniSection = (String, Vec<(String, String)>);
+
+/// Разбирает INI-файл Firefox (profiles.ini, installs.ini).
+/// Строки вне секций и комментарии (`;`, `#`) пропускаются.
+pub fn parse_ini(text: &str) -> Vec<IniSection> {
+    let mut sections: Vec<IniSection> = Vec::new();
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim().to_string(), Vec::new()));
+        } else if let (Some((key, value)), Some((_, entries))) = (line.split_once('='), sections.last_mut()) {
+            entries.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    sections
+}
+
+fn ini_value<'a>(entries: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    entries.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v.as_str())
+}
+
+/// Путь профиля из INI: относительный — от папки Firefox, с `/` в качестве разделителя
+fn profile_path(firefox_dir: &Path, path: &str, relative: bool) -> PathBuf {
+    if relative {
+        path.split(['/', '\\']).filter(|part| !part.is_empty()).fold(firefox_dir.to_path_buf(), |dir, part| dir.join(part))
+    } else {
+        PathBuf::from(path)
+    }
+}
+
+/// Профиль из profiles.ini
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirefoxProfile {
+    pub name: String,
+    pub path: PathBuf,
+    /// `Default=1` в секции профиля — профиль по умолчанию у старых версий Firefox
+    pub is_default: bool,
+}
+
+/// Папка Firefox в профиле пользователя: `%APPDATA%\Mozilla\Firefox`
+pub fn firefox_dir() -> io::Result<PathBuf> {
+    std::env::var_os("APPDATA")
+        .map(|appdata| PathBuf::from(appdata).join("Mozilla").join("Firefox"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "переменная APPDATA не задана"))
+}
+
+/// Профили из `<папка Firefox>/profiles.ini`
+pub fn list_profiles(firefox_dir: &Path) -> io::Result<Vec<FirefoxProfile>> {
+    let text = fs::read_to_string(firefox_dir.join("profiles.ini"))?;
+    Ok(parse_ini(&text)
+        .into_iter()
+        .filter(|(name, _)| name.starts_with("Profile"))
+        .filter_map(|(_, entries)| {
+            let path = ini_value(&entries, "Path")?;
+            Some(FirefoxProfile {
+                name: ini_value(&entries, "Name").unwrap_or_default().to_string(),
+                path: profile_path(firefox_dir, path, ini_value(&entries, "IsRelative") != Some("0")),
+                is_default: ini_value(&entries, "Default") == Some("1"),
+            })
+        })
+        .collect())
+}
+
+/// Профили по умолчанию для каждой установки Firefox.
+/// Firefox 67+ хранит их в installs.ini и в секциях `[Install…]` profiles.ini;
+/// у старых версий — профиль с `Default=1`, а если профиль один — он сам.
+pub fn default_profiles(firefox_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let profiles = list_profiles(firefox_dir)?;
+    let installs_ini = fs::read_to_string(firefox_dir.join("installs.ini")).unwrap_or_default();
+    let profiles_ini = fs::read_to_string(firefox_dir.join("profiles.ini"))?;
+    let installs = parse_ini(&installs_ini).into_iter().chain(
+        parse_ini(&profiles_ini).into_iter().filter(|(name, _)| name.starts_with("Install")),
+    );
+
+    let mut defaults: Vec<PathBuf> = Vec::new();
+    for (_, entries) in installs {
+        let Some(path) = ini_value(&entries, "Default") else { continue };
+        let path = profile_path(firefox_dir, path, !Path::new(path).is_absolute());
+        if !defaults.contains(&path) {
+            defaults.push(path);
+        }
+    }
+    if defaults.is_empty() {
+        defaults = match profiles.iter().find(|p| p.is_default) {
+            Some(profile) => vec![profile.path.clone()],
+            None if profiles.len() == 1 => vec![profiles[0].path.clone()],
+            None => Vec::new(),
+        };
+    }
+    Ok(defaults)
+}
+
+/// Имя настройки из строки `user_pref("имя", значение);`
+fn pref_name(line: &str) -> Option<&str> {
+    let rest = line.trim().strip_prefix("user_pref(")?.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let rest = &rest[1..];
+    rest.find(quote).map(|end| &rest[..end])
+}
+
+/// Итог слияния user.js
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeSummary {
+    /// Настройки шаблона, которых не было
+    pub added: usize,
+    /// Настройки, значение которых заменено шаблоном
+    pub replaced: usize,
+    /// Свои настройки пользователя, которых нет в шаблоне
+    pub kept: usize,
+}
+
+impl MergeSummary {
+    pub fn is_unchanged(&self) -> bool {
+        self.added == 0 && self.replaced == 0
+    }
+}
+
+impl fmt::Display for MergeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "добавлено {}, заменено {}, сохранено своих {}", self.added, self.replaced, self.kept)
+    }
+}
+
+/// Сливает настройки шаблона с существующим user.js: строки с теми же именами
+/// заменяются на месте, новые дописываются в конец, остальное не трогается
+pub fn merge_user_js(existing: &str, template: &str) -> (String, MergeSummary) {
+    let prefs: HashMap<&str, &str> = template.lines().filter_map(|line| Some((pref_name(line)?, line.trim()))).collect();
+    let mut summary = MergeSummary::default();
+    let mut present = Vec::new();
+    let mut lines: Vec<String> = Vec::new();
+    for line in existing.lines() {
+        match pref_name(line) {
+            Some(name) if prefs.contains_key(name) => {
+                let new = prefs[name];
+                if line.trim() != new {
+                    summary.replaced += 1;
+                }
+                present.push(name.to_string());
+                lines.push(new.to_string());
+            }
+            Some(_) => {
+                summary.kept += 1;
+                lines.push(line.to_string());
+            }
+            None => lines.push(line.to_string()),
+        }
+    }
+
+    let missing: Vec<&str> = template
+        .lines()
+        .filter(|line| pref_name(line).is_some_and(|name| !present.iter().any(|p| p == name)))
+        .map(str::trim)
+        .collect();
+    if !missing.is_empty() {
+        if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push("// Настройки win-tool".to_string());
+        lines.extend(missing.iter().map(|l| l.to_string()));
+        summary.added = missing.len();
+    }
+    let mut merged = lines.join("\n");
+    merged.push('\n');
+    (merged, summary)
+}
+
+/// Записывает настройки шаблона в user.js профиля. Прежний user.js сохраняется
+/// в user.js.bak один раз, чтобы копия оставалась исходной и после повторных запусков.
+pub fn deploy_user_js(profile_dir: &Path, template: &Path) -> io::Result<MergeSummary> {
+    let template = fs::read_to_string(template)?;
+    let user_js = profile_dir.join(USER_JS);
+    let existing = match fs::read_to_string(&user_js) {
+        Ok(text) => Some(text),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    let (merged, summary) = merge_user_js(existing.as_deref().unwrap_or_default(), &template);
+    if summary.is_unchanged() {
+        return Ok(summary);
+    }
+    let backup = profile_dir.join(USER_JS_BACKUP);
+    if existing.is_some() && !backup.exists() {
+        fs::copy(&user_js, &backup)?;
+    }
+    fs::write(&user_js, merged)?;
+    Ok(summary)
+}
+
+/// Разворачивает шаблон настроек во все профили по умолчанию
+pub fn deploy_prefs(firefox_dir: &Path, template: &Path) -> io::Result<Outcome> {
+    let profiles = match default_profiles(firefox_dir) {
+        Ok(profiles) if !profiles.is_empty() => profiles,
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => return Ok(Outcome::new(ApplyStatus::Skipped, "профиль Firefox не найден, запустите Firefox один раз")),
+    };
+
+    let mut changed = false;
+    for profile in &profiles {
+        let summary = deploy_user_js(profile, template)?;
+        progress!("  ✅ {}: {}", profile.display(), summary);
+        changed |= !summary.is_unchanged();
+    }
+    if !changed {
+        return Ok(Outcome::new(ApplyStatus::Skipped, "настройки уже в user.js"));
+    }
+    Ok(Outcome::applied())
+}
+
+/// Корпоративные политики Firefox: содержимое объекта `policies` в policies.json
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Policies(pub Map<String, Value>);
+
+/// Изменение одной политики относительно установленного policies.json
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyChange {
+    Added(String, Value),
+    Removed(String),
+    Changed(String, Value, Value),
+}
+
+impl fmt::Display for PolicyChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyChange::Added(name, value) => write!(f, "+ {} = {}", name, value),
+            PolicyChange::Removed(name) => write!(f, "- {}", name),
+            PolicyChange::Changed(name, old, new) => write!(f, "~ {}: {} → {}", name, old, new),
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Объект `policies` из текста policies.json, без проверки имён
+fn parse_policies(json: &str) -> io::Result<Map<String, Value>> {
+    let mut root: Value = serde_json::from_str(json.trim_start_matches('\u{feff}')).map_err(|e| invalid(format!("policies.json: {}", e)))?;
+    match root.get_mut("policies").map(Value::take) {
+        Some(Value::Object(policies)) => Ok(policies),
+        _ => Err(invalid("policies.json: нет объекта \"policies\"".to_string())),
+    }
+}
+
+impl Policies {
+    /// Разбирает шаблон `{"policies": {...}}`; имена политик проверяет [`Policies::unknown`]
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        Ok(Self(parse_policies(json)?))
+    }
+
+    /// Имена, которых нет в [`KNOWN_POLICIES`]: скорее всего опечатки
+    pub fn unknown(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).filter(|name| !KNOWN_POLICIES.contains(name)).collect()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Текст policies.json
+    pub fn to_json(&self) -> String {
+        let root = serde_json::json!({ "policies": self.0 });
+        let mut json = serde_json::to_string_pretty(&root).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+
+    /// Что изменится в установленных политиках: политики, которых нет в шаблоне, удаляются
+    pub fn diff(&self, installed: &Map<String, Value>) -> Vec<PolicyChange> {
+        let mut changes: Vec<PolicyChange> = self
+            .0
+            .iter()
+            .filter_map(|(name, value)| match installed.get(name) {
+                None => Some(PolicyChange::Added(name.clone(), value.clone())),
+                Some(old) if old != value => Some(PolicyChange::Changed(name.clone(), old.clone(), value.clone())),
+                Some(_) => None,
+            })
+            .collect();
+        changes.extend(installed.keys().filter(|name| !self.0.contains_key(*name)).map(|name| PolicyChange::Removed(name.clone())));
+        changes
+    }
+}
+
+/// Папки установки Firefox: `Mozilla Firefox` в Program Files, где есть firefox.exe
+pub fn install_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for var in ["ProgramFiles", "ProgramW6432", "ProgramFiles(x86)"] {
+        let Some(base) = std::env::var_os(var) else { continue };
+        let dir = PathBuf::from(base).join("Mozilla Firefox");
+        if dir.join("firefox.exe").is_file() && !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+/// Путь policies.json для папки установки
+pub fn policies_path(install_dir: &Path) -> PathBuf {
+    install_dir.join("distribution").join(POLICIES_TEMPLATE)
+}
+
+/// Политики из установленного policies.json; файла нет — политик нет
+pub fn installed_policies(install_dir: &Path) -> io::Result<Map<String, Value>> {
+    match fs::read_to_string(policies_path(install_dir)) {
+        Ok(json) => parse_policies(&json),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Map::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Записывает policies.json в папку установки и возвращает изменения.
+/// Прежний файл один раз сохраняется как policies.json.bak. Нужны права администратора.
+pub fn deploy_policies(policies: &Policies, install_dir: &Path) -> io::Result<Vec<PolicyChange>> {
+    let path = policies_path(install_dir);
+    let changes = policies.diff(&installed_policies(install_dir)?);
+    if changes.is_empty() {
+        return Ok(changes);
+    }
+    let backup = path.with_extension("json.bak");
+    if path.exists() && !backup.exists() {
+        fs::copy(&path, &backup)?;
+    }
+    fs::create_dir_all(install_dir.join("distribution"))?;
+    fs::write(&path, policies.to_json())?;
+    Ok(changes)
+}
+
+/// Разворачивает шаблон политик во все установки Firefox с выводом изменений
+pub fn deploy_policies_template(template: &Path) -> io::Result<Outcome> {
+    let policies = Policies::load(template)?;
+    let unknown = policies.unknown();
+    if !unknown.is_empty() {
+        progress!("  ⚠️  Неизвестные политики, Firefox их пропустит: {}", unknown.join(", "));
+    }
+    let dirs = install_dirs();
+    if dirs.is_empty() {
+        return Ok(Outcome::new(ApplyStatus::Skipped, "папка установки Firefox не найдена"));
+    }
+
+    let mut changed = false;
+    for dir in &dirs {
+        let changes = deploy_policies(&policies, dir)?;
+        progress!("  📜 {}: изменений {}", policies_path(dir).display(), changes.len());
+        for change in &changes {
+            progress!("     {}", change);
+        }
+        changed |= !changes.is_empty();
+    }
+    if !changed {
+        return Ok(Outcome::new(ApplyStatus::Skipped, "политики уже установлены"));
+    }
+    Ok(Outcome::applied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::{temp_dir, TempDir};
+
+    fn fixture(name: &str, files: &[(&str, &str)]) -> TempDir {
+        let dir = temp_dir(&format!("firefox-{}", name));
+        for (path, text) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_default_profiles() {
+        let profiles_ini = "[Install308046B0AF4A39CB]\r\nDefault=Profiles/abcd.default-release\r\nLocked=1\r\n\r\n\
+                            [Profile1]\r\nName=default\r\nIsRelative=1\r\nPath=Profiles/efgh.default\r\nDefault=1\r\n\r\n\
+                            [Profile0]\r\nName=default-release\r\nIsRelative=1\r\nPath=Profiles/abcd.default-release\r\n\r\n\
+                            [General]\r\nStartWithLastProfile=1\r\nVersion=2\r\n";
+        let installs_ini = "[308046B0AF4A39CB]\nDefault=Profiles/abcd.default-release\nLocked=1\n\n\
+                            [E7CF176E110C211B]\nDefault=Profiles/ijkl.dev-edition-default\n";
+        let dir = fixture("installs", &[("profiles.ini", profiles_ini), ("installs.ini", installs_ini)]);
+
+        let profiles = list_profiles(&dir).unwrap();
+        assert_eq!(profiles.len(), 2);
+        assert!(profiles[0].is_default);
+        assert_eq!(
+            default_profiles(&dir).unwrap(),
+            vec![dir.join("Profiles").join("abcd.default-release"), dir.join("Profiles").join("ijkl.dev-edition-default")]
+        );
+
+        // Без installs.ini и секций Install — профиль с Default=1
+        let legacy = fixture("legacy", &[("profiles.ini", "[Profile0]\nName=default\nIsRelative=1\nPath=Profiles/old.default\nDefault=1\n")]);
+        assert_eq!(default_profiles(&legacy).unwrap(), vec![legacy.join("Profiles").join("old.default")]);
+    }
+
+    #[test]
+    fn test_merge_user_js() {
+        let existing = "// мои настройки\nuser_pref(\"browser.uidensity\", 0);\nuser_pref(\"general.smoothScroll\", false);\n";
+        let template = "// Тонкие вкладки\nuser_pref(\"browser.uidensity\", 1);\nuser_pref(\"extensions.pocket.enabled\", false);\n";
+        let (merged, summary) = merge_user_js(existing, template);
+        assert_eq!(summary, MergeSummary { added: 1, replaced: 1, kept: 1 });
+        assert_eq!(
+            merged,
+            "// мои настройки\nuser_pref(\"browser.uidensity\", 1);\nuser_pref(\"general.smoothScroll\", false);\n\n\
+             // Настройки win-tool\nuser_pref(\"extensions.pocket.enabled\", false);\n"
+        );
+        assert!(merge_user_js(&merged, template).1.is_unchanged());
+    }
+
+    #[test]
+    fn test_policies_validation_and_diff() {
+        let template = r#"{ "policies": {
+            "DisableTelemetry": true,
+            "ExtensionSettings": { "uBlock0@raymondhill.net": { "installation_mode": "force_installed" } }
+        } }"#;
+        let policies = Policies::from_json(template).unwrap();
+        assert!(policies.unknown().is_empty());
+        let typo = Policies::from_json(r#"{ "policies": { "DisableTelemetri": true, "PrintingEnabled": false } }"#).unwrap();
+        assert_eq!(typo.unknown(), vec!["DisableTelemetri"]);
+        // Структура файла по-прежнему проверяется строго
+        assert!(Policies::from_json(r#"{ "DisableTelemetry": true }"#).is_err());
+        assert!(Policies::from_json(r#"{ "policies": [] }"#).is_err());
+        assert!(Policies::from_json(r#"{ "policies": "#).is_err());
+
+        let dir = fixture(
+            "policies",
+            &[("distribution/policies.json", r#"{ "policies": { "DisableTelemetry": false, "BlockAboutConfig": true } }"#)],
+        );
+        let changes = policies.diff(&installed_policies(&dir).unwrap());
+        let lines: Vec<String> = changes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "~ DisableTelemetry: false → true".to_string(),
+                r#"+ ExtensionSettings = {"uBlock0@raymondhill.net":{"installation_mode":"force_installed"}}"#.to_string(),
+                "- BlockAboutConfig".to_string(),
+            ]
+        );
+
+        assert_eq!(deploy_policies(&policies, &dir).unwrap().len(), 3);
+        assert!(deploy_policies(&policies, &dir).unwrap().is_empty());
+        assert_eq!(Policies(installed_policies(&dir).unwrap()), policies);
+        assert!(dir.join("distribution").join("policies.json.bak").is_file());
+    }
+
+    #[test]
+    fn test_builtin_policies_template() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("data/config_templates/firefox").join(POLICIES_TEMPLATE);
+        let policies = Policies::load(&path).unwrap();
+        assert_eq!(policies.0.get("DisableTelemetry"), Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_deploy_keeps_original_backup() {
+        let profile = "Profiles/abcd.default-release";
+        let dir = fixture(
+            "deploy",
+            &[
+                ("profiles.ini", "[Profile0]\nName=default-release\nIsRelative=1\nPath=Profiles/abcd.default-release\n"),
+                ("Profiles/abcd.default-release/user.js", "user_pref(\"browser.uidensity\", 0);\n"),
+                ("template/pref.js", "user_pref(\"browser.uidensity\", 1);\n"),
+            ],
+        );
+        let template = dir.join("template").join(PREFS_TEMPLATE);
+        assert_eq!(deploy_prefs(&dir, &template).unwrap().status, ApplyStatus::Applied);
+        assert_eq!(deploy_prefs(&dir, &template).unwrap().status, ApplyStatus::Skipped);
+
+        let profile = profile_path(&dir, profile, true);
+        assert_eq!(fs::read_to_string(profile.join(USER_JS)).unwrap(), "user_pref(\"browser.uidensity\", 1);\n");
+        assert_eq!(fs::read_to_string(profile.join(USER_JS_BACKUP)).unwrap(), "user_pref(\"browser.uidensity\", 0);\n");
+
+        let missing = dir.join("no-firefox");
+        assert_eq!(deploy_prefs(&missing, &template).unwrap().status, ApplyStatus::Skipped);
+    }
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::modules::journal;
+use crate::modules::results::{ApplyStatus, Outcome};
+use crate::progress;
+use crate::utils::filesystem::{FileChange, FileStatus, Variables};
+
+/// Основной файл конфигурации; остальные JSON шаблона — профили в configs/
+pub const CURRENT_CONFIG: &str = "current-config.json";
+
+/// Папка настроек Hiddify: `%APPDATA%\Hiddify\hiddify`
+pub fn config_dir() -> io::Result<PathBuf> {
+    std::env::var_os("APPDATA")
+        .map(|appdata| PathBuf::from(appdata).join("Hiddify").join("hiddify"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "переменная APPDATA не задана"))
+}
+
+/// Файл шаблона и его место относительно папки настроек
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateFile {
+    pub name: String,
+    pub destination: PathBuf,
+    pub contents: Vec<u8>,
+}
+
+/// Читает JSON-файлы шаблона, подставляет переменные и проверяет, что файлы разбираются.
+/// Пустые файлы — незаполненные заготовки, они пропускаются.
+/// Ошибка в любом файле останавливает развёртывание до копирования.
+pub fn load_templates(source: &Path, variables: &Variables) -> io::Result<Vec<TemplateFile>> {
+    let mut templates = Vec::new();
+    for entry in fs::read_dir(source)? {
+        let path = entry?.path();
+        let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let Some(name) = path.file_name().and_then(|n| n.to_str()).filter(|_| is_json && path.is_file()) else {
+            continue;
+        };
+        let mut contents = fs::read(&path)?;
+        if contents.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        if let Ok(text) = std::str::from_utf8(&contents) {
+            contents = variables.substitute_json(text).into_bytes();
+        }
+        serde_json::from_slice::<serde_json::Value>(&contents)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+        let destination = if name.eq_ignore_ascii_case(CURRENT_CONFIG) {
+            PathBuf::from(name)
+        } else {
+            Path::new("configs").join(name)
+        };
+        templates.push(TemplateFile { name: name.to_string(), destination, contents });
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+/// Итог развёртывания
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deployment {
+    pub changes: Vec<FileChange>,
+    /// Папка с прежними версиями заменённых файлов
+    pub backup: Option<PathBuf>,
+}
+
+impl Deployment {
+    fn count(&self, status: FileStatus) -> usize {
+        self.changes.iter().filter(|c| c.status == status).count()
+    }
+}
+
+/// Копирует шаблоны в папку настроек, создавая её при необходимости.
+/// Файлы, которые будут заменены, сначала сохраняются в `backup-<stamp>/`
+/// с той же структурой папок.
+pub fn deploy(templates: &[TemplateFile], config_dir: &Path, stamp: &str) -> io::Result<Deployment> {
+    let mut deployment = Deployment::default();
+    for template in templates {
+        let target = config_dir.join(&template.destination);
+        let status = match fs::read(&target) {
+            Ok(existing) if existing == template.contents => FileStatus::Unchanged,
+            Ok(_) => FileStatus::Updated,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => FileStatus::Created,
+            Err(e) => return Err(e),
+        };
+        deployment.changes.push(FileChange { path: template.destination.clone(), status });
+    }
+
+    let backup = config_dir.join(format!("backup-{}", stamp));
+    for change in &deployment.changes {
+        if change.status == FileStatus::Updated {
+            let saved = backup.join(&change.path);
+            if let Some(parent) = saved.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(config_dir.join(&change.path), saved)?;
+            deployment.backup = Some(backup.clone());
+        }
+    }
+    for (template, change) in templates.iter().zip(&deployment.changes) {
+        if change.status != FileStatus::Unchanged {
+            let target = config_dir.join(&template.destination);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(target, &template.contents)?;
+        }
+    }
+    Ok(deployment)
+}
+
+/// Разворачивает шаблон data/config_templates/hiddify с выводом изменённых файлов.
+/// Если Hiddify ещё не запускался, папка настроек создаётся: он прочитает её при первом запуске.
+pub fn deploy_template(source: &Path) -> io::Result<Outcome> {
+    let templates = load_templates(source, &Variables::from_env())?;
+    if templates.is_empty() {
+        return Ok(Outcome::new(ApplyStatus::Skipped, "шаблоны Hiddify не заполнены"));
+    }
+    let config_dir = config_dir()?;
+    let created = !config_dir.is_dir();
+    let stamp = journal::format_file_stamp(journal::now());
+    let deployment = deploy(&templates, &config_dir, &stamp)?;
+
+    progress!("📋 Настройки Hiddify: {}", config_dir.display());
+    for change in &deployment.changes {
+        progress!("  {}", change);
+    }
+    if let Some(backup) = &deployment.backup {
+        progress!("  💾 Прежние файлы сохранены в {}", backup.display());
+    }
+
+    let (added, changed) = (deployment.count(FileStatus::Created), deployment.count(FileStatus::Updated));
+    if added + changed == 0 {
+        return Ok(Outcome::new(ApplyStatus::Skipped, "настройки уже совпадают с шаблоном"));
+    }
+    let mut note = format!("добавлено {}, заменено {}", added, changed);
+    if created {
+        note.push_str(", папка настроек создана");
+    }
+    Ok(Outcome::new(ApplyStatus::Applied, &note))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::temp_dir;
+
+    #[test]
+    fn test_deploy_with_backup() {
+        let source = temp_dir("hiddify-source");
+        fs::write(source.join(CURRENT_CONFIG), r#"{ "mixed-port": 7890 }"#).unwrap();
+        fs::write(source.join("733bd95f.json"), r#"{ "name": "{{HOSTNAME}}" }"#).unwrap();
+        fs::write(source.join("empty.json"), "").unwrap();
+        fs::write(source.join("notes.txt"), "не JSON").unwrap();
+        let variables = Variables::default().with("HOSTNAME", "WS-042");
+        let templates = load_templates(&source, &variables).unwrap();
+        assert_eq!(templates.len(), 2);
+        assert_eq!(templates[0].destination, Path::new("configs").join("733bd95f.json"));
+        assert_eq!(templates[0].contents, br#"{ "name": "WS-042" }"#);
+
+        let config = temp_dir("hiddify-config");
+        fs::write(config.join(CURRENT_CONFIG), r#"{ "mixed-port": 2334 }"#).unwrap();
+        let deployment = deploy(&templates, &config, "20240131-120000").unwrap();
+        let lines: Vec<String> = deployment.changes.iter().map(ToString::to_string).collect();
+        assert_eq!(lines, vec![format!("+ {}", Path::new("configs").join("733bd95f.json").display()), "~ current-config.json".to_string()]);
+        let backup = deployment.backup.unwrap();
+        assert_eq!(fs::read_to_string(backup.join(CURRENT_CONFIG)).unwrap(), r#"{ "mixed-port": 2334 }"#);
+        assert_eq!(fs::read_to_string(config.join(CURRENT_CONFIG)).unwrap(), r#"{ "mixed-port": 7890 }"#);
+
+        let again = deploy(&templates, &config, "20240131-120100").unwrap();
+        assert!(again.changes.iter().all(|c| c.status == FileStatus::Unchanged));
+        assert_eq!(again.backup, None);
+
+        // Битый шаблон останавливает развёртывание целиком
+        fs::write(source.join(CURRENT_CONFIG), "{ \"mixed-port\": ").unwrap();
+        assert_eq!(load_templates(&source, &variables).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
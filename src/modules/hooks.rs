@@ -0,0 +1,316 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::modules::{firefox, hiddify};
+use crate::modules::packages::ALTERNATIVE_IDS;
+use crate::modules::results::{ApplyReport, ApplyResult, ApplyStatus, Outcome};
+use crate::modules::tweaks::{self, Host, Operation};
+use crate::progress;
+use crate::utils::filesystem::{self, CopyOptions, FileStatus, MergeStrategy, Variables};
+use crate::utils::registry::{RegRoot, RegValue};
+
+/// Встроенные действия, используются если в data/ нет своего hooks.json
+const BUILTIN_HOOKS: &str = include_str!("../../data/hooks.json");
+
+/// Действие после установки пакета
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum HookAction {
+    /// Развернуть шаблон из data/config_templates/<template> в папку приложения
+    CopyTemplate {
+        template: String,
+        /// Папка назначения с подстановками `{{APPDATA}}` и др.;
+        /// без неё — встроенное место для firefox и hiddify
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        destination: Option<String>,
+        /// Стратегия по умолчанию и стратегии отдельных файлов шаблона
+        #[serde(default)]
+        strategy: MergeStrategy,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        files: BTreeMap<String, MergeStrategy>,
+    },
+    /// Записать значение реестра
+    SetValue { root: RegRoot, path: String, name: String, value: RegValue },
+    /// Запустить программу
+    Run {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+impl fmt::Display for HookAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HookAction::CopyTemplate { template, .. } => write!(f, "шаблон {}", template),
+            HookAction::SetValue { root, path, name, value } => write!(f, "{}\\{}\\{} = {}", root, path, name, value),
+            HookAction::Run { program, args } => write!(f, "{} {}", program, args.join(" ")),
+        }
+    }
+}
+
+impl HookAction {
+    /// Выполняет действие. Значения реестра пишутся в журнал под id `hook:<пакет>`;
+    /// команды — нет: обратной команды у них не бывает.
+    pub fn execute(&self, host: &Host, package: &str) -> io::Result<Outcome> {
+        let status = match self {
+            HookAction::CopyTemplate { template, destination: None, .. } => return deploy_template(template),
+            HookAction::CopyTemplate { template, destination: Some(destination), strategy, files } => {
+                let options = CopyOptions { strategy: *strategy, files: files.clone(), variables: Variables::from_env() };
+                return copy_template(template, destination, &options);
+            }
+            HookAction::SetValue { root, path, name, value } => {
+                let op = Operation::SetValue {
+                    root: *root,
+                    path: path.clone(),
+                    name: name.clone(),
+                    value: value.clone(),
+                    only_if_key_exists: false,
+                };
+                host.run_operation(&format!("hook:{}", package), true, &op)?
+            }
+            HookAction::Run { program, args } => {
+                let op = Operation::Run { program: program.clone(), args: args.clone() };
+                tweaks::execute_operation(host, &op)?
+            }
+        };
+        Ok(Outcome { status, note: None })
+    }
+}
+
+/// Действия для одного пакета каталога
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageHooks {
+    /// Id winget; действия выполняются и при установке пакета через scoop или choco
+    pub package: String,
+    pub actions: Vec<HookAction>,
+}
+
+/// Действия после установки пакетов
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HookCatalog {
+    #[serde(default)]
+    pub version: u32,
+    pub hooks: Vec<PackageHooks>,
+}
+
+impl HookCatalog {
+    /// Разбирает каталог действий из JSON и проверяет его
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let catalog: HookCatalog = serde_json::from_str(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("действия после установки: {}", e)))?;
+        catalog.validate()?;
+        Ok(catalog)
+    }
+
+    /// Каталог, встроенный в программу
+    pub fn builtin() -> Self {
+        Self::from_json(BUILTIN_HOOKS).expect("встроенные действия после установки должны быть корректными")
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Загружает data/hooks.json, если он есть, иначе встроенный каталог
+    pub fn load_default() -> io::Result<Self> {
+        match filesystem::find_data_dir().map(|dir| dir.join("hooks.json")) {
+            Some(path) if path.is_file() => Self::load(&path),
+            _ => Ok(Self::builtin()),
+        }
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("действия после установки: {}", message));
+        let mut packages = HashSet::new();
+        for hooks in &self.hooks {
+            if !packages.insert(hooks.package.to_lowercase()) {
+                return Err(invalid(format!("пакет \"{}\" описан дважды", hooks.package)));
+            }
+            // Встроенное развёртывание firefox и hiddify стратегий не знает: без ошибки они бы молча пропали
+            for action in &hooks.actions {
+                if let HookAction::CopyTemplate { template, destination: None, strategy, files } = action {
+                    if *strategy != MergeStrategy::default() || !files.is_empty() {
+                        return Err(invalid(format!(
+                            "шаблон \"{}\" пакета \"{}\": strategy и files работают только вместе с destination",
+                            template, hooks.package
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Действия для пакета по Id winget или по его Id в другом менеджере
+    pub fn actions_for(&self, id: &str) -> &[HookAction] {
+        let winget_id = ALTERNATIVE_IDS
+            .iter()
+            .find(|(_, _, alternative)| alternative.eq_ignore_ascii_case(id))
+            .map_or(id, |(winget_id, _, _)| *winget_id);
+        self.hooks
+            .iter()
+            .find(|h| h.package.eq_ignore_ascii_case(winget_id))
+            .map_or(&[], |h| h.actions.as_slice())
+    }
+}
+
+/// Выполняет действия для только что установленных пакетов, по результату на действие.
+/// Ошибка одного действия не останавливает остальные.
+pub fn run_after_install(host: &Host, catalog: &HookCatalog, installed: &[String]) -> ApplyReport {
+    let mut report = ApplyReport::default();
+    for id in installed {
+        let actions = catalog.actions_for(id);
+        if actions.is_empty() {
+            continue;
+        }
+        progress!("⚙️  Настройка {}...", id);
+        for action in actions {
+            let message = format!("после установки: {}", action);
+            report.push(ApplyResult::from_outcome(id, &message, action.execute(host, id)));
+        }
+    }
+    report
+}
+
+/// Папка шаблона data/config_templates/<name>
+pub fn template_dir(name: &str) -> io::Result<PathBuf> {
+    filesystem::find_data_dir()
+        .map(|dir| dir.join("config_templates").join(name))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "директория data/ не найдена"))
+}
+
+/// Копирует шаблон в папку `destination` по стратегиям из `options`
+pub fn copy_template(name: &str, destination: &str, options: &CopyOptions) -> io::Result<Outcome> {
+    let destination = PathBuf::from(options.variables.substitute(destination));
+    let changes = filesystem::copy_config_files(name, &template_dir(name)?, &destination, options)?;
+    let count = |status| changes.iter().filter(|c| c.status == status).count();
+    let (created, updated) = (count(FileStatus::Created), count(FileStatus::Updated));
+    if created + updated == 0 {
+        return Ok(Outcome::new(ApplyStatus::Skipped, "файлы уже совпадают с шаблоном"));
+    }
+    Ok(Outcome::new(ApplyStatus::Applied, &format!("создано {}, обновлено {}", created, updated)))
+}
+
+/// Общий итог нескольких частей шаблона: статус первой неудавшейся части,
+/// иначе первой выполненной; пояснения — с именем части
+fn combine(parts: &[(&str, Outcome)]) -> Outcome {
+    let statuses = || parts.iter().map(|(_, outcome)| outcome.status);
+    let status = statuses()
+        .find(ApplyStatus::is_failure)
+        .or_else(|| statuses().find(|status| *status != ApplyStatus::Skipped))
+        .unwrap_or(ApplyStatus::Skipped);
+    let notes: Vec<String> = parts
+        .iter()
+        .filter_map(|(part, outcome)| outcome.note.as_ref().map(|note| format!("{}: {}", part, note)))
+        .collect();
+    Outcome { status, note: (!notes.is_empty()).then(|| notes.join("; ")) }
+}
+
+/// Разворачивает шаблон из data/config_templates/<name> туда, где его ждёт приложение
+pub fn deploy_template(name: &str) -> io::Result<Outcome> {
+    let source = template_dir(name)?;
+    match name {
+        // Настройки сливаются с user.js профилей по умолчанию, политики пишутся в папку установки.
+        // Части независимы: ошибка одной (обычно нехватка прав на политики) не скрывает итог другой
+        "firefox" => {
+            let prefs = firefox::firefox_dir()
+                .and_then(|dir| firefox::deploy_prefs(&dir, &source.join(firefox::PREFS_TEMPLATE)))
+                .unwrap_or_else(|e| Outcome::from_error(&e));
+            let policies = source.join(firefox::POLICIES_TEMPLATE);
+            if !policies.is_file() {
+                return Ok(prefs);
+            }
+            let policies = firefox::deploy_policies_template(&policies).unwrap_or_else(|e| Outcome::from_error(&e));
+            Ok(combine(&[("user.js", prefs), ("policies.json", policies)]))
+        }
+        "hiddify" => hiddify::deploy_template(&source),
+        _ => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("неизвестно, куда копировать шаблон \"{}\": укажите destination", name),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+    use crate::utils::registry::{MemoryRegistry, RegistryBackend};
+
+    #[test]
+    fn test_run_after_install() {
+        let catalog = HookCatalog::from_json(
+            r#"{ "hooks": [
+                { "package": "Git.Git", "actions": [
+                    { "op": "set_value", "root": "HKCU", "path": "Software\\GitForWindows", "name": "ShowGui", "value": { "type": "dword", "data": 0 } },
+                    { "op": "run", "program": "git", "args": ["config", "--global", "core.autocrlf", "true"] },
+                    { "op": "run", "program": "git", "args": ["lfs", "install"] }
+                ] }
+            ] }"#,
+        )
+        .unwrap();
+        // Id scoop и choco ведут к действиям пакета winget
+        assert_eq!(catalog.actions_for("main/git").len(), 3);
+        assert!(catalog.actions_for("7zip.7zip").is_empty());
+
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new()
+            .expect("git", &["config", "--global", "core.autocrlf", "true"], CommandOutput::ok(""))
+            .expect("git", &["lfs", "install"], CommandOutput::with_code(1, "git: 'lfs' is not a git command"));
+        let report = run_after_install(&Host::new(&reg, &runner), &catalog, &["git".to_string(), "7zip.7zip".to_string()]);
+
+        let statuses: Vec<ApplyStatus> = report.results.iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec![ApplyStatus::Applied, ApplyStatus::Applied, ApplyStatus::Failed]);
+        assert_eq!(report.results[1].message, "после установки: git config --global core.autocrlf true");
+        assert_eq!(reg.read_value(RegRoot::CurrentUser, "Software\\GitForWindows", "ShowGui").unwrap(), RegValue::Dword(0));
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn test_builtin_hooks() {
+        let catalog = HookCatalog::builtin();
+        assert_eq!(
+            catalog.actions_for("Mozilla.Firefox"),
+            &[HookAction::CopyTemplate {
+                template: "firefox".to_string(),
+                destination: None,
+                strategy: MergeStrategy::Overwrite,
+                files: BTreeMap::new(),
+            }]
+        );
+        let action: HookAction = serde_json::from_str(
+            r#"{ "op": "copy_template", "template": "vscode", "destination": "{{APPDATA}}\\Code\\User",
+                 "strategy": "skip_if_exists", "files": { "settings.json": "json_merge" } }"#,
+        )
+        .unwrap();
+        assert!(matches!(action, HookAction::CopyTemplate { strategy: MergeStrategy::SkipIfExists, ref files, .. } if files.len() == 1));
+        let outcome = combine(&[
+            ("user.js", Outcome::new(ApplyStatus::Skipped, "профиль не найден")),
+            ("policies.json", Outcome::applied()),
+        ]);
+        assert_eq!(outcome, Outcome::new(ApplyStatus::Applied, "user.js: профиль не найден"));
+        // Ошибка политик видна в статусе, итог user.js не теряется
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "доступ запрещён");
+        let outcome = combine(&[
+            ("user.js", Outcome::new(ApplyStatus::Applied, "изменено 3")),
+            ("policies.json", Outcome::from_error(&denied)),
+        ]);
+        assert_eq!(outcome, Outcome::new(ApplyStatus::NeedsAdmin, "user.js: изменено 3; policies.json: доступ запрещён"));
+
+        let twice = r#"{ "hooks": [ { "package": "A", "actions": [] }, { "package": "a", "actions": [] } ] }"#;
+        assert!(HookCatalog::from_json(twice).is_err());
+
+        // Стратегия без destination не применилась бы к встроенному развёртыванию
+        let strategy = r#"{ "hooks": [ { "package": "Mozilla.Firefox", "actions": [
+            { "op": "copy_template", "template": "firefox", "strategy": "skip_if_exists" } ] } ] }"#;
+        let err = HookCatalog::from_json(strategy).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("destination"));
+        let files = strategy.replace(r#""strategy": "skip_if_exists""#, r#""files": { "user.js": "append_lines" }"#);
+        assert!(HookCatalog::from_json(&files).is_err());
+    }
+}
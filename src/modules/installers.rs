@@ -0,0 +1,122 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::modules::results::{ApplyReport, ApplyResult};
+use crate::modules::tweaks::Selection;
+use crate::progress;
+use crate::utils::command::{CommandRunner, CommandSpec};
+use crate::utils::filesystem;
+
+/// Файл с параметрами тихой установки в data/installers/
+pub const INSTALL_CONFIG: &str = "install-config.json";
+
+/// Запись из install-config.json
+#[derive(Debug, Deserialize)]
+struct InstallerConfig {
+    filename: String,
+    #[serde(default)]
+    silent_args: String,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct InstallConfig {
+    installers: Vec<InstallerConfig>,
+}
+
+/// Установщик .exe из data/installers/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Installer {
+    pub filename: String,
+    pub path: PathBuf,
+    pub silent_args: Vec<String>,
+    pub description: String,
+}
+
+/// Директория data/installers/
+pub fn installers_dir() -> Option<PathBuf> {
+    filesystem::find_data_dir().map(|dir| dir.join("installers"))
+}
+
+/// Находит .exe в директории и дополняет их параметрами из install-config.json
+pub fn list_installers(dir: &Path) -> io::Result<Vec<Installer>> {
+    let config = match fs::read_to_string(dir.join(INSTALL_CONFIG)) {
+        Ok(json) => serde_json::from_str::<InstallConfig>(&json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", INSTALL_CONFIG, e)))?
+            .installers,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    let mut files = filesystem::list_exe_files(&dir.to_string_lossy())?;
+    files.sort();
+    Ok(files
+        .into_iter()
+        .map(|filename| {
+            let entry = config.iter().find(|c| c.filename.eq_ignore_ascii_case(&filename));
+            Installer {
+                path: dir.join(&filename),
+                silent_args: entry
+                    .map(|c| c.silent_args.split_whitespace().map(String::from).collect())
+                    .unwrap_or_default(),
+                description: entry.map(|c| c.description.clone()).unwrap_or_default(),
+                filename,
+            }
+        })
+        .collect())
+}
+
+/// Запускает установщик и ждёт его завершения
+pub fn run_installer(runner: &dyn CommandRunner, installer: &Installer) -> io::Result<()> {
+    progress!("🔄 Запуск установщика {}...", installer.filename);
+
+    let spec = CommandSpec::new(&installer.path.to_string_lossy()).args(&installer.silent_args);
+    runner.run(&spec)?.check(&spec)?;
+    progress!("✅ {} установлен.", installer.filename);
+
+    Ok(())
+}
+
+/// Запускает отмеченные установщики по очереди.
+/// Ошибка одного не останавливает остальные.
+pub fn apply_installers(runner: &dyn CommandRunner, installers: &[Installer], selection: &Selection) -> ApplyReport {
+    installers
+        .iter()
+        .filter(|installer| selection.get(&installer.filename) == Some(&true))
+        .map(|installer| ApplyResult::from_result(&installer.filename, "установка", run_installer(runner, installer)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+    use crate::utils::test_support::temp_dir;
+
+    #[test]
+    fn test_list_and_run_installers() {
+        let dir = temp_dir("installers");
+        fs::write(dir.join("app1.exe"), "").unwrap();
+        fs::write(dir.join("App2.EXE"), "").unwrap();
+        fs::write(dir.join("readme.txt"), "").unwrap();
+        fs::write(
+            dir.join(INSTALL_CONFIG),
+            r#"{ "installers": [{ "filename": "app1.exe", "silent_args": "/S /norestart", "description": "Первое" }] }"#,
+        )
+        .unwrap();
+
+        let installers = list_installers(&dir).unwrap();
+        assert_eq!(installers.len(), 2);
+        let app1 = installers.iter().find(|i| i.filename == "app1.exe").unwrap();
+        assert_eq!(app1.silent_args, vec!["/S", "/norestart"]);
+        assert_eq!(app1.description, "Первое");
+
+        let selection: Selection = [("app1.exe".to_string(), true)].into_iter().collect();
+        let program = app1.path.to_string_lossy().into_owned();
+        let runner = ScriptedRunner::new().expect(&program, &["/S", "/norestart"], CommandOutput::ok(""));
+        assert!(!apply_installers(&runner, &installers, &selection).has_failures());
+        assert!(runner.is_exhausted());
+    }
+}
@@ -0,0 +1,694 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::modules::tweaks::{self, Catalog, Host, Operation, StartType, Tweak, TweakState};
+use crate::progress;
+use crate::utils::command::CommandSpec;
+use crate::utils::filesystem;
+use crate::utils::registry::{self, KeySnapshot, RegRoot, RegValue, RegistryBackend};
+
+/// Имя файла журнала в директории данных
+pub const JOURNAL_FILE: &str = "journal.jsonl";
+
+/// Сделанное изменение вместе с тем, что было до него
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordedChange {
+    /// Значение реестра записано или удалено
+    RegistryValue {
+        root: RegRoot,
+        path: String,
+        name: String,
+        /// `None` — значения не было
+        previous: Option<RegValue>,
+        /// `None` — значение удалено
+        new: Option<RegValue>,
+        /// Верхний ключ, созданный записью; при отмене созданные ключи
+        /// удаляются снизу вверх, пока они пусты
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        created_key: Option<String>,
+    },
+    /// Ключ удалён; снимок нужен, чтобы его воссоздать
+    RegistryKey {
+        root: RegRoot,
+        path: String,
+        previous: KeySnapshot,
+    },
+    /// Изменён тип запуска службы
+    Service {
+        name: String,
+        /// `None` — службы нет или тип запуска неизвестен
+        previous: Option<StartType>,
+        new: StartType,
+        stopped: bool,
+        /// Служба работала до изменения; при отмене остановленная служба запускается снова
+        #[serde(default)]
+        was_running: bool,
+    },
+    /// Запущена команда; отменяется командами твика для прежнего состояния
+    Command {
+        program: String,
+        args: Vec<String>,
+        /// Был ли твик включён до команды по его проверкам (`detect`);
+        /// `None` — состояние не определено, такую команду отменить нельзя
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        previous: Option<bool>,
+    },
+}
+
+fn show_value(value: &Option<RegValue>) -> String {
+    value.as_ref().map_or("(нет)".to_string(), |v| v.to_string())
+}
+
+impl fmt::Display for RecordedChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordedChange::RegistryValue { root, path, name, previous, new, .. } => {
+                let name = if name.is_empty() { "(по умолчанию)" } else { name };
+                write!(f, "{}\\{}\\{}: {} → {}", root, path, name, show_value(previous), show_value(new))
+            }
+            RecordedChange::RegistryKey { root, path, .. } => write!(f, "удалён ключ {}\\{}", root, path),
+            RecordedChange::Service { name, previous, new, .. } => {
+                let previous = previous.map_or("(нет)", |s| s.sc_name());
+                write!(f, "служба {}: {} → {}", name, previous, new.sc_name())
+            }
+            RecordedChange::Command { program, args, .. } => {
+                write!(f, "команда: {}", CommandSpec::new(program).args(args))
+            }
+        }
+    }
+}
+
+/// Запись журнала
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Порядковый номер, растёт на протяжении всего журнала
+    pub seq: u64,
+    /// Время изменения, секунды с 1970-01-01 UTC
+    pub timestamp: u64,
+    /// Сеанс — один запуск утилиты
+    pub session: String,
+    pub tweak_id: String,
+    pub enable: bool,
+    pub change: RecordedChange,
+}
+
+impl fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{} {} [{}] {}", self.seq, format_timestamp(self.timestamp), self.tweak_id, self.change)
+    }
+}
+
+/// Строка файла журнала: изменение или отметка о его отмене
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum Record {
+    Change(JournalEntry),
+    Reverted { seq: u64, timestamp: u64 },
+}
+
+/// Что отменять
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertScope {
+    /// Все изменения одного твика
+    Tweak(String),
+    /// Все изменения одного сеанса
+    Session(String),
+    /// Всё, что сделано после записи с этим номером
+    After(u64),
+    /// Весь журнал
+    All,
+}
+
+impl RevertScope {
+    fn matches(&self, entry: &JournalEntry) -> bool {
+        match self {
+            RevertScope::Tweak(id) => &entry.tweak_id == id,
+            RevertScope::Session(session) => &entry.session == session,
+            RevertScope::After(seq) => entry.seq > *seq,
+            RevertScope::All => true,
+        }
+    }
+}
+
+/// Текущее время в секундах с 1970-01-01 UTC
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Дата и время UTC: (год, месяц, день, часы, минуты, секунды)
+fn date_time(secs: u64) -> (i64, i64, i64, u64, u64, u64) {
+    // Перевод дней с 1970-01-01 в дату григорианского календаря
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let rem = secs % 86_400;
+    (year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+/// Время в виде "2024-01-31 12:00:00 UTC"
+pub fn format_timestamp(secs: u64) -> String {
+    let (year, month, day, hour, minute, second) = date_time(secs);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, hour, minute, second)
+}
+
+/// Время для имён файлов и папок: "20240131-120000"
+pub fn format_file_stamp(secs: u64) -> String {
+    let (year, month, day, hour, minute, second) = date_time(secs);
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", year, month, day, hour, minute, second)
+}
+
+/// Время в формате ISO 8601: "2024-01-31T12:00:00Z"
+pub fn format_iso8601(secs: u64) -> String {
+    let (year, month, day, hour, minute, second) = date_time(secs);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, minute, second)
+}
+
+/// Запоминает состояние, которое изменит операция твика `tweak`.
+/// `None` — операция ничего не изменит.
+pub fn capture(host: &Host, tweak: Option<&Tweak>, op: &Operation) -> Option<RecordedChange> {
+    let reg = host.registry;
+    match op {
+        Operation::SetValue { root, path, name, value, only_if_key_exists } => {
+            if *only_if_key_exists && !reg.key_exists(*root, path) {
+                return None;
+            }
+            Some(RecordedChange::RegistryValue {
+                root: *root,
+                path: path.clone(),
+                name: name.clone(),
+                previous: reg.read_value(*root, path, name).ok(),
+                new: Some(value.clone()),
+                created_key: registry::first_missing_key(reg, *root, path),
+            })
+        }
+        Operation::DeleteValue { root, path, name } => {
+            let previous = reg.read_value(*root, path, name).ok()?;
+            Some(RecordedChange::RegistryValue {
+                root: *root,
+                path: path.clone(),
+                name: name.clone(),
+                previous: Some(previous),
+                new: None,
+                created_key: None,
+            })
+        }
+        Operation::DeleteKey { root, path } => {
+            let previous = registry::snapshot_key(reg, *root, path).ok()?;
+            Some(RecordedChange::RegistryKey {
+                root: *root,
+                path: path.clone(),
+                previous,
+            })
+        }
+        Operation::Service { name, start, stop } => {
+            let previous = match reg.read_value(RegRoot::LocalMachine, &tweaks::service_key(name), "Start") {
+                Ok(RegValue::Dword(v)) => StartType::from_registry_value(v),
+                _ => None,
+            };
+            // Не удалось узнать — служба не будет запущена при отмене
+            let was_running = *stop && tweaks::service_running(host.runner, name).unwrap_or(false);
+            Some(RecordedChange::Service {
+                name: name.clone(),
+                previous,
+                new: *start,
+                stopped: *stop,
+                was_running,
+            })
+        }
+        Operation::Run { program, args } => Some(RecordedChange::Command {
+            program: program.clone(),
+            args: args.clone(),
+            previous: tweak.and_then(|tweak| match tweaks::detect_state(reg, tweak) {
+                TweakState::On => Some(true),
+                TweakState::Off => Some(false),
+                TweakState::Custom | TweakState::NotApplicable => None,
+            }),
+        }),
+    }
+}
+
+fn ignore_not_found(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+/// Удаляет ключи от `path` вверх до `top` включительно, пока в них нет
+/// ни значений, ни подключей: туда могли писать другие твики и действия
+fn delete_empty_keys(reg: &dyn RegistryBackend, root: RegRoot, path: &str, top: &str) -> io::Result<()> {
+    let mut key = path;
+    loop {
+        if reg.key_exists(root, key) {
+            if !reg.list_values(root, key)?.is_empty() || !reg.list_subkeys(root, key)?.is_empty() {
+                return Ok(());
+            }
+            ignore_not_found(reg.delete_key(root, key))?;
+        }
+        match key.rsplit_once('\\') {
+            Some((parent, _)) if key.len() > top.len() => key = parent,
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Возвращает то, что было до изменения
+fn revert_entry(host: &Host, catalog: &Catalog, entry: &JournalEntry) -> io::Result<()> {
+    match &entry.change {
+        RecordedChange::RegistryValue { root, path, name, previous, created_key, .. } => {
+            match (created_key, previous) {
+                (Some(key), _) => {
+                    ignore_not_found(host.registry.delete_value(*root, path, name))?;
+                    delete_empty_keys(host.registry, *root, path, key)
+                }
+                (None, Some(value)) => host.registry.write_value(*root, path, name, value),
+                (None, None) => ignore_not_found(host.registry.delete_value(*root, path, name)),
+            }
+        }
+        RecordedChange::RegistryKey { root, path, previous } => {
+            registry::restore_key(host.registry, *root, path, previous)
+        }
+        RecordedChange::Service { name, previous, stopped, was_running, .. } => {
+            let previous = previous.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::Unsupported, format!("прежний тип запуска службы {} неизвестен", name))
+            })?;
+            tweaks::execute_operation(
+                host,
+                &Operation::Service {
+                    name: name.clone(),
+                    start: previous,
+                    stop: false,
+                },
+            )?;
+            if *stopped && *was_running {
+                host.runner.run(&CommandSpec::new("sc").args(["start", name]))?;
+            }
+            Ok(())
+        }
+        RecordedChange::Command { previous, .. } => {
+            // Команды твика задают одно из двух состояний, а не прежние значения,
+            // поэтому отменить можно только переход из известного состояния
+            let previous = previous.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("состояние твика \"{}\" до команды неизвестно", entry.tweak_id),
+                )
+            })?;
+            if previous == entry.enable {
+                return Ok(());
+            }
+            let tweak = catalog.get(&entry.tweak_id).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("твик \"{}\" не найден в каталоге", entry.tweak_id))
+            })?;
+            let commands: Vec<&Operation> = tweak
+                .operations(previous)
+                .iter()
+                .filter(|op| matches!(op, Operation::Run { .. }))
+                .collect();
+            if commands.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("у твика \"{}\" нет обратной команды", entry.tweak_id),
+                ));
+            }
+            for op in commands {
+                tweaks::execute_operation(host, op)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Журнал изменений, сохраняемый между запусками (JSON Lines)
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    session: String,
+    next_seq: Mutex<u64>,
+}
+
+impl Journal {
+    /// Открывает журнал и начинает новый сеанс
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let journal = Self {
+            path: path.to_path_buf(),
+            session: format!("{}-{}", now(), std::process::id()),
+            next_seq: Mutex::new(0),
+        };
+        let last = journal
+            .read_records()?
+            .iter()
+            .filter_map(|r| match r {
+                Record::Change(entry) => Some(entry.seq),
+                Record::Reverted { .. } => None,
+            })
+            .max();
+        *journal.next_seq.lock().unwrap() = last.map_or(1, |seq| seq + 1);
+        Ok(journal)
+    }
+
+    /// Журнал в директории данных утилиты
+    pub fn open_default() -> io::Result<Self> {
+        let dir = filesystem::get_tool_data_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "директория данных не найдена"))?;
+        Self::open(&dir.join(JOURNAL_FILE))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Текущий сеанс
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    fn read_records(&self) -> io::Result<Vec<Record>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("{}, строка {}: {}", self.path.display(), i + 1, e))
+                })
+            })
+            .collect()
+    }
+
+    fn append(&self, record: &Record) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let line = serde_json::to_string(record).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", line)
+    }
+
+    /// Записывает изменение в текущий сеанс
+    pub fn record(&self, tweak_id: &str, enable: bool, change: RecordedChange) -> io::Result<()> {
+        let mut next_seq = self.next_seq.lock().unwrap();
+        let entry = JournalEntry {
+            seq: *next_seq,
+            timestamp: now(),
+            session: self.session.clone(),
+            tweak_id: tweak_id.to_string(),
+            enable,
+            change,
+        };
+        self.append(&Record::Change(entry))?;
+        *next_seq += 1;
+        Ok(())
+    }
+
+    /// Изменения, которые ещё не отменены, от старых к новым
+    pub fn entries(&self) -> io::Result<Vec<JournalEntry>> {
+        let records = self.read_records()?;
+        let reverted: Vec<u64> = records
+            .iter()
+            .filter_map(|r| match r {
+                Record::Reverted { seq, .. } => Some(*seq),
+                Record::Change(_) => None,
+            })
+            .collect();
+        Ok(records
+            .into_iter()
+            .filter_map(|r| match r {
+                Record::Change(entry) if !reverted.contains(&entry.seq) => Some(entry),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Последний сеанс, в котором остались неотменённые изменения
+    pub fn last_session(&self) -> io::Result<Option<String>> {
+        Ok(self.entries()?.pop().map(|entry| entry.session))
+    }
+
+    /// Отменяет изменения от новых к старым. Ошибка одной записи не
+    /// останавливает остальные; возвращается первая ошибка.
+    /// Возвращает число отменённых изменений.
+    pub fn revert(&self, host: &Host, catalog: &Catalog, scope: &RevertScope) -> io::Result<usize> {
+        let mut reverted = 0;
+        let mut first_error = None;
+        for entry in self.entries()?.iter().rev().filter(|e| scope.matches(e)) {
+            match revert_entry(host, catalog, entry) {
+                Ok(()) => {
+                    progress!("↩️  {}", entry);
+                    self.append(&Record::Reverted { seq: entry.seq, timestamp: now() })?;
+                    reverted += 1;
+                }
+                Err(e) => {
+                    progress!("❌ {}: {}", entry, e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(reverted), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::plan;
+    use crate::modules::tweaks::Selection;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+    use crate::utils::registry::MemoryRegistry;
+    use crate::utils::test_support::temp_dir;
+
+    const ADVANCED: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced";
+    const POWER: &str = "SYSTEM\\CurrentControlSet\\Control\\Power";
+    const RUNNING: &str = "SERVICE_NAME: DiagTrack\n        TYPE               : 10  WIN32_OWN_PROCESS\n        STATE              : 4  RUNNING\n";
+
+    fn selection(items: &[(&str, bool)]) -> Selection {
+        items.iter().map(|(id, on)| (id.to_string(), *on)).collect()
+    }
+
+    fn apply(reg: &MemoryRegistry, journal: &Journal, items: &[(&str, bool)]) {
+        let runner = ScriptedRunner::new();
+        let catalog = Catalog::builtin();
+        let host = Host::new(reg, &runner).with_journal(journal, &catalog);
+        plan::plan_selection(reg, &catalog, &selection(items))
+            .unwrap()
+            .execute(&host);
+    }
+
+    #[test]
+    fn test_revert_restores_previous_values() {
+        let dir = temp_dir("journal-restore");
+        let path = dir.join("journal.jsonl");
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new();
+        let catalog = Catalog::builtin();
+        reg.write_value(RegRoot::CurrentUser, ADVANCED, "Hidden", &RegValue::Dword(2)).unwrap();
+
+        let journal = Journal::open(&path).unwrap();
+        apply(&reg, &journal, &[("show_hidden_files", true), ("open_this_pc", true)]);
+        assert_eq!(journal.entries().unwrap().len(), 2);
+
+        let count = journal.revert(&Host::new(&reg, &runner), &catalog, &RevertScope::All).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(reg.read_value(RegRoot::CurrentUser, ADVANCED, "Hidden").unwrap(), RegValue::Dword(2));
+        assert!(reg.read_value(RegRoot::CurrentUser, ADVANCED, "LaunchTo").is_err());
+        assert!(journal.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_revert_created_and_deleted_keys() {
+        let dir = temp_dir("journal-keys");
+        let path = dir.join("journal.jsonl");
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new();
+        let host = Host::new(&reg, &runner);
+        let catalog = Catalog::builtin();
+        let clsid = "Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}";
+
+        let journal = Journal::open(&path).unwrap();
+        apply(&reg, &journal, &[("enable_old_context_menu", true)]);
+        assert!(reg.key_exists(RegRoot::CurrentUser, clsid));
+        journal.revert(&host, &catalog, &RevertScope::Tweak("enable_old_context_menu".into())).unwrap();
+        assert!(!reg.key_exists(RegRoot::CurrentUser, clsid));
+
+        reg.write_value(RegRoot::CurrentUser, &format!("{}\\InprocServer32", clsid), "", &RegValue::String("x".into()))
+            .unwrap();
+        apply(&reg, &journal, &[("enable_old_context_menu", false)]);
+        assert!(!reg.key_exists(RegRoot::CurrentUser, clsid));
+        journal.revert(&host, &catalog, &RevertScope::All).unwrap();
+        assert_eq!(
+            reg.read_value(RegRoot::CurrentUser, &format!("{}\\InprocServer32", clsid), "").unwrap(),
+            RegValue::String("x".into())
+        );
+    }
+
+    #[test]
+    fn test_revert_keeps_values_of_other_tweaks() {
+        let key = "Software\\Policies\\WinTool\\Shared";
+        let set = |name: &str| Operation::SetValue {
+            root: RegRoot::CurrentUser,
+            path: key.to_string(),
+            name: name.to_string(),
+            value: RegValue::Dword(1),
+            only_if_key_exists: false,
+        };
+        let runner = ScriptedRunner::new();
+        let catalog = Catalog::builtin();
+
+        // Ключ создаёт первый твик, второй пишет в уже созданный; отмена от нового к старому
+        // и от старого к новому
+        for (name, order) in [("shared-newest", ["hook:Git.Git", "first"]), ("shared-oldest", ["first", "hook:Git.Git"])] {
+            let dir = temp_dir(&format!("journal-{}", name));
+            let path = dir.join("journal.jsonl");
+            let reg = MemoryRegistry::new();
+            reg.write_value(RegRoot::CurrentUser, "Software\\Policies", "Keep", &RegValue::Dword(0)).unwrap();
+            let journal = Journal::open(&path).unwrap();
+            let host = Host::new(&reg, &runner).with_journal(&journal, &catalog);
+            host.run_operation("first", true, &set("First")).unwrap();
+            host.run_operation("hook:Git.Git", true, &set("Second")).unwrap();
+
+            let host = Host::new(&reg, &runner);
+            journal.revert(&host, &catalog, &RevertScope::Tweak(order[0].into())).unwrap();
+            assert_eq!(reg.list_values(RegRoot::CurrentUser, key).unwrap().len(), 1);
+            journal.revert(&host, &catalog, &RevertScope::Tweak(order[1].into())).unwrap();
+            assert!(reg.list_values(RegRoot::CurrentUser, key).unwrap_or_default().is_empty());
+            assert_eq!(reg.read_value(RegRoot::CurrentUser, "Software\\Policies", "Keep").unwrap(), RegValue::Dword(0));
+        }
+
+        // Отмена в обратном порядке удаляет созданные ключи, когда они опустели
+        let dir = temp_dir("journal-shared-cleanup");
+        let path = dir.join("journal.jsonl");
+        let reg = MemoryRegistry::new();
+        reg.write_value(RegRoot::CurrentUser, "Software\\Policies", "Keep", &RegValue::Dword(0)).unwrap();
+        let journal = Journal::open(&path).unwrap();
+        let host = Host::new(&reg, &runner).with_journal(&journal, &catalog);
+        host.run_operation("first", true, &set("First")).unwrap();
+        host.run_operation("hook:Git.Git", true, &set("Second")).unwrap();
+        journal.revert(&Host::new(&reg, &runner), &catalog, &RevertScope::All).unwrap();
+        assert!(!reg.key_exists(RegRoot::CurrentUser, "Software\\Policies\\WinTool"));
+    }
+
+    #[test]
+    fn test_journal_persists_between_sessions() {
+        let dir = temp_dir("journal-sessions");
+        let path = dir.join("journal.jsonl");
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new();
+        let catalog = Catalog::builtin();
+
+        let first = Journal::open(&path).unwrap();
+        apply(&reg, &first, &[("show_hidden_files", true)]);
+        let point = first.entries().unwrap().last().unwrap().seq;
+
+        let mut second = Journal::open(&path).unwrap();
+        second.session = "second".to_string();
+        apply(&reg, &second, &[("open_this_pc", true), ("show_file_extensions", true)]);
+
+        let entries = second.entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(entries.windows(2).all(|w| w[0].seq < w[1].seq));
+        assert_eq!(second.last_session().unwrap().as_deref(), Some("second"));
+
+        let count = second.revert(&Host::new(&reg, &runner), &catalog, &RevertScope::After(point)).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(reg.read_value(RegRoot::CurrentUser, ADVANCED, "Hidden").unwrap(), RegValue::Dword(1));
+        assert!(reg.read_value(RegRoot::CurrentUser, ADVANCED, "LaunchTo").is_err());
+    }
+
+    #[test]
+    fn test_revert_services_and_commands() {
+        let dir = temp_dir("journal-services");
+        let path = dir.join("journal.jsonl");
+        let reg = MemoryRegistry::new();
+        let catalog = Catalog::builtin();
+        reg.write_value(RegRoot::LocalMachine, &tweaks::service_key("DiagTrack"), "Start", &RegValue::Dword(2)).unwrap();
+        reg.write_value(RegRoot::LocalMachine, POWER, "HibernateEnabled", &RegValue::Dword(1)).unwrap();
+
+        let journal = Journal::open(&path).unwrap();
+        let runner = ScriptedRunner::new()
+            .expect("powercfg", &["/hibernate", "off"], CommandOutput::ok(""))
+            .expect("sc", &["query", "DiagTrack"], CommandOutput::ok(RUNNING))
+            .expect("sc", &["stop", "DiagTrack"], CommandOutput::ok(""))
+            .expect("sc", &["config", "DiagTrack", "start=", "disabled"], CommandOutput::ok(""))
+            .expect("sc", &["query", "dmwappushservice"], CommandOutput::with_code(1060, ""))
+            .expect("sc", &["stop", "dmwappushservice"], CommandOutput::ok(""))
+            .expect("sc", &["config", "dmwappushservice", "start=", "disabled"], CommandOutput::ok(""));
+        let host = Host::new(&reg, &runner).with_journal(&journal, &catalog);
+        plan::plan_selection(&reg, &catalog, &selection(&[("disable_hibernation", true), ("disable_telemetry", true)]))
+            .unwrap()
+            .execute(&host);
+        assert!(runner.is_exhausted());
+
+        // Тип запуска dmwappushservice неизвестен, поэтому её отмена — ошибка
+        let runner = ScriptedRunner::new()
+            .expect("sc", &["config", "DiagTrack", "start=", "auto"], CommandOutput::ok(""))
+            .expect("sc", &["start", "DiagTrack"], CommandOutput::ok(""))
+            .expect("powercfg", &["/hibernate", "on"], CommandOutput::ok(""));
+        let err = journal.revert(&Host::new(&reg, &runner), &catalog, &RevertScope::All).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert!(runner.is_exhausted());
+        assert_eq!(journal.entries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_revert_uses_captured_state() {
+        let dir = temp_dir("journal-captured");
+        let path = dir.join("journal.jsonl");
+        let reg = MemoryRegistry::new();
+        let catalog = Catalog::builtin();
+        reg.write_value(RegRoot::LocalMachine, &tweaks::service_key("DiagTrack"), "Start", &RegValue::Dword(2)).unwrap();
+        let hibernate_off = Operation::Run { program: "powercfg".into(), args: vec!["/hibernate".into(), "off".into()] };
+        let stop_diagtrack = Operation::Service { name: "DiagTrack".into(), start: StartType::Disabled, stop: true };
+
+        // Состояние гибернации не определено, служба уже остановлена
+        let journal = Journal::open(&path).unwrap();
+        let runner = ScriptedRunner::new()
+            .expect("powercfg", &["/hibernate", "off"], CommandOutput::ok(""))
+            .expect("sc", &["query", "DiagTrack"], CommandOutput::ok(RUNNING.replace("4  RUNNING", "1  STOPPED").as_str()))
+            .expect("sc", &["stop", "DiagTrack"], CommandOutput::ok(""))
+            .expect("sc", &["config", "DiagTrack", "start=", "disabled"], CommandOutput::ok(""));
+        let host = Host::new(&reg, &runner).with_journal(&journal, &catalog);
+        host.run_operation("disable_hibernation", true, &hibernate_off).unwrap();
+        host.run_operation("disable_telemetry", true, &stop_diagtrack).unwrap();
+        assert!(runner.is_exhausted());
+
+        // Служба не запускается, для команды обратной нет — ничего, кроме смены типа запуска
+        let runner = ScriptedRunner::new().expect("sc", &["config", "DiagTrack", "start=", "auto"], CommandOutput::ok(""));
+        let err = journal.revert(&Host::new(&reg, &runner), &catalog, &RevertScope::All).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert!(runner.is_exhausted());
+
+        // Гибернация уже была выключена: отменять нечего
+        reg.write_value(RegRoot::LocalMachine, POWER, "HibernateEnabled", &RegValue::Dword(0)).unwrap();
+        let journal = Journal::open(&dir.join("hibernated.jsonl")).unwrap();
+        let runner = ScriptedRunner::new().expect("powercfg", &["/hibernate", "off"], CommandOutput::ok(""));
+        Host::new(&reg, &runner).with_journal(&journal, &catalog).run_operation("disable_hibernation", true, &hibernate_off).unwrap();
+        let runner = ScriptedRunner::new();
+        assert_eq!(journal.revert(&Host::new(&reg, &runner), &catalog, &RevertScope::All).unwrap(), 1);
+        assert!(runner.calls().is_empty());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13:20 UTC");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_file_stamp(1_700_000_000), "20231114-221320");
+        assert_eq!(format_iso8601(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::modules::packages::{self, VersionPin};
+use crate::utils::command::CommandRunner;
+use crate::utils::filesystem;
+
+/// Имя лок-файла в директории данных программы
+pub const LOCK_FILE: &str = "winget.lock.json";
+
+/// Версии пакетов, которые действительно установлены через win-tool.
+/// Файл переносится на другую машину, и `sync` ставит там те же версии.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockFile {
+    /// Id пакета → установленная версия
+    #[serde(default)]
+    pub packages: BTreeMap<String, String>,
+}
+
+impl LockFile {
+    /// Путь по умолчанию: `%LOCALAPPDATA%\win-tool\winget.lock.json`
+    pub fn default_path() -> Option<PathBuf> {
+        filesystem::get_tool_data_dir().map(|dir| dir.join(LOCK_FILE))
+    }
+
+    /// Загружает лок-файл; отсутствующий файл — пустой список
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, json)
+    }
+
+    /// Точные версии для `sync`
+    pub fn pins(&self) -> Vec<(String, VersionPin)> {
+        self.packages
+            .iter()
+            .map(|(id, version)| (id.clone(), VersionPin::Exact(version.clone())))
+            .collect()
+    }
+
+    /// Записывает фактические версии пакетов `ids` по `winget list`;
+    /// неустановленные пакеты из файла убираются
+    pub fn record(&mut self, runner: &dyn CommandRunner, ids: &[String]) -> io::Result<()> {
+        let installed = packages::get_installed_packages(runner)?;
+        for id in ids {
+            match installed.iter().find(|e| e.id_matches(id) && !e.is_truncated()) {
+                Some(entry) => {
+                    self.packages.insert(id.clone(), entry.version.clone());
+                }
+                None => {
+                    self.packages.remove(id);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Обновляет лок-файл после установки, удаления или обновления пакетов
+pub fn record_packages(path: &Path, runner: &dyn CommandRunner, ids: &[String]) -> io::Result<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let mut lock = LockFile::load(path)?;
+    lock.record(runner, ids)?;
+    lock.save(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+    use crate::utils::test_support::temp_dir;
+
+    #[test]
+    fn test_record_and_sync_to_lock() {
+        let dir = temp_dir("lock");
+        let path = dir.join(LOCK_FILE);
+
+        let list = "Name     Id                   Version\n\
+                    -------------------------------------\n\
+                    Python   Python.Python.3.12   3.12.2\n\
+                    Node.js  OpenJS.NodeJS        21.7.1";
+        let runner = ScriptedRunner::new().expect("winget", &["list"], CommandOutput::ok(list));
+        let ids = vec!["Python.Python.3.12".to_string(), "Git.Git".to_string(), "OpenJS.NodeJS".to_string()];
+        record_packages(&path, &runner, &ids).unwrap();
+
+        let mut lock = LockFile::load(&path).unwrap();
+        assert_eq!(lock.packages.get("Python.Python.3.12").map(String::as_str), Some("3.12.2"));
+        assert!(!lock.packages.contains_key("Git.Git"));
+
+        // На другой машине Python новее, Node старше, Git нет
+        lock.packages.insert("Git.Git".to_string(), "2.44.0".to_string());
+        lock.packages.insert("OpenJS.NodeJS".to_string(), "20.11.1".to_string());
+        let list = "Name     Id                   Version\n\
+                    -------------------------------------\n\
+                    Python   Python.Python.3.12   3.12.3\n\
+                    Node.js  OpenJS.NodeJS        18.19.0";
+        let agreements = ["--silent", "--accept-source-agreements", "--accept-package-agreements"];
+        let with_agreements = |args: &[&'static str]| -> Vec<&'static str> { args.iter().chain(&agreements).copied().collect() };
+        let runner = ScriptedRunner::new()
+            .expect("winget", &["list"], CommandOutput::ok(list))
+            .expect("winget", &with_agreements(&["install", "--id", "Git.Git", "--exact", "--version", "2.44.0"]), CommandOutput::ok(""))
+            .expect(
+                "winget",
+                &with_agreements(&["upgrade", "--id", "OpenJS.NodeJS", "--exact", "--version", "20.11.1"]),
+                CommandOutput::ok(""),
+            )
+            .expect("winget", &["uninstall", "--id", "Python.Python.3.12", "--silent"], CommandOutput::ok(""))
+            .expect(
+                "winget",
+                &with_agreements(&["install", "--id", "Python.Python.3.12", "--exact", "--version", "3.12.2"]),
+                CommandOutput::ok(""),
+            );
+
+        let report = packages::sync_packages(&runner, &lock.pins()).unwrap();
+        let messages: Vec<&str> = report.results.iter().map(|r| r.message.as_str()).collect();
+        assert_eq!(messages, vec!["установка 2.44.0", "обновление 18.19.0 → 20.11.1", "откат 3.12.3 → 3.12.2"]);
+        assert!(!report.has_failures());
+        assert!(runner.is_exhausted());
+
+        // Сбой winget list не стирает версии из лок-файла
+        let runner = ScriptedRunner::new().expect("winget", &["list"], CommandOutput::with_code(1, "Failed when opening source(s)"));
+        assert!(record_packages(&path, &runner, &["Python.Python.3.12".to_string()]).is_err());
+        assert!(LockFile::load(&path).unwrap().packages.contains_key("Python.Python.3.12"));
+    }
+}
@@ -1,5 +1,22 @@
 pub mod packages;
 pub mod uwp;
+pub mod chocolatey;
+pub mod compliance;
+pub mod dsc;
 pub mod explorer;
+pub mod firefox;
+pub mod hiddify;
+pub mod hooks;
 pub mod system;
 pub mod personalization;
+pub mod installers;
+pub mod journal;
+pub mod lockfile;
+pub mod package_list;
+pub mod package_manager;
+pub mod plan;
+pub mod profile;
+pub mod results;
+pub mod scoop;
+pub mod tweaks;
+pub mod winget;
@@ -0,0 +1,217 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::modules::journal;
+use crate::modules::packages::{self, VersionPin};
+use crate::utils::command::CommandRunner;
+use crate::utils::filesystem;
+
+/// Схема файлов `winget export`
+pub const SCHEMA: &str = "https://aka.ms/winget-packages.schema.2.0.json";
+/// Имя файла по умолчанию в папке «Документы»
+pub const DEFAULT_FILE: &str = "winget-packages.json";
+
+/// Список пакетов в формате `winget export` / `winget import`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PackageList {
+    #[serde(rename = "$schema", default = "default_schema")]
+    pub schema: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creation_date: Option<String>,
+    #[serde(default)]
+    pub sources: Vec<PackageSource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub win_get_version: Option<String>,
+}
+
+/// Пакеты одного источника
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PackageSource {
+    #[serde(default)]
+    pub packages: Vec<ExportedPackage>,
+    pub source_details: SourceDetails,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ExportedPackage {
+    pub package_identifier: String,
+    /// Есть только при `winget export --include-versions`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SourceDetails {
+    pub argument: String,
+    pub identifier: String,
+    pub name: String,
+    #[serde(rename = "Type")]
+    pub kind: String,
+}
+
+fn default_schema() -> String {
+    SCHEMA.to_string()
+}
+
+impl SourceDetails {
+    /// Описание стандартного источника; `None` для пользовательских
+    pub fn known(name: &str) -> Option<Self> {
+        let (argument, identifier, kind) = match name {
+            "winget" => (
+                "https://cdn.winget.microsoft.com/cache",
+                "Microsoft.Winget.Source_8wekyb3d8bbwe",
+                "Microsoft.PreIndexed.Package",
+            ),
+            "msstore" => ("https://storeedgefd.dsx.mp.microsoft.com/v9.0", "StoreEdgeFD", "Microsoft.Rest"),
+            _ => return None,
+        };
+        Some(Self {
+            argument: argument.to_string(),
+            identifier: identifier.to_string(),
+            name: name.to_string(),
+            kind: kind.to_string(),
+        })
+    }
+}
+
+impl PackageList {
+    /// Путь по умолчанию: `%USERPROFILE%\Documents\winget-packages.json`
+    pub fn default_path() -> Option<PathBuf> {
+        filesystem::get_documents_dir().map(|dir| dir.join(DEFAULT_FILE))
+    }
+
+    /// Указанный файл или файл по умолчанию
+    pub fn resolve_path(path: Option<&Path>) -> io::Result<PathBuf> {
+        path.map(Path::to_path_buf)
+            .or_else(Self::default_path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "не найдена папка пользователя, укажите файл"))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        // winget пишет файл с BOM
+        serde_json::from_str(json.trim_start_matches('\u{feff}'))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, json)
+    }
+
+    /// Список установленных пакетов из известных источников.
+    /// Программы не из winget и обрезанные Id пропускаются: импортировать их нельзя.
+    pub fn from_installed(runner: &dyn CommandRunner, include_versions: bool) -> io::Result<Self> {
+        let installed = packages::get_installed_packages(runner)?;
+        let sources = ["winget", "msstore"]
+            .into_iter()
+            .filter_map(|name| {
+                let packages: Vec<ExportedPackage> = installed
+                    .iter()
+                    .filter(|e| e.source.as_deref() == Some(name) && !e.is_truncated())
+                    .map(|e| ExportedPackage {
+                        package_identifier: e.id.clone(),
+                        version: include_versions.then(|| e.version.clone()),
+                    })
+                    .collect();
+                let source_details = SourceDetails::known(name)?;
+                (!packages.is_empty()).then_some(PackageSource { packages, source_details })
+            })
+            .collect();
+        Ok(Self {
+            schema: default_schema(),
+            creation_date: Some(journal::format_iso8601(journal::now())),
+            sources,
+            win_get_version: None,
+        })
+    }
+
+    /// Пакеты для установки: версия из файла — точная, без версии — последняя
+    pub fn wanted(&self, ignore_versions: bool) -> Vec<(String, VersionPin)> {
+        let mut wanted: Vec<(String, VersionPin)> = Vec::new();
+        for package in self.sources.iter().flat_map(|s| &s.packages) {
+            if wanted.iter().any(|(id, _)| id.eq_ignore_ascii_case(&package.package_identifier)) {
+                continue;
+            }
+            let pin = match &package.version {
+                Some(version) if !ignore_versions => VersionPin::Exact(version.clone()),
+                _ => VersionPin::Latest,
+            };
+            wanted.push((package.package_identifier.clone(), pin));
+        }
+        wanted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+    use crate::utils::test_support::temp_dir;
+
+    #[test]
+    fn test_export_and_import() {
+        let list = "Name               Id                    Version       Available Source\n\
+                    -----------------------------------------------------------------------\n\
+                    7-Zip 23.01 (x64)  7zip.7zip             23.01                   winget\n\
+                    Windows Terminal   9N0DX20HK701          1.19.10573.0            msstore\n\
+                    Some Local Tool    ARP\\Machine\\X64\\Tool  1.0";
+        let runner = ScriptedRunner::new().expect("winget", &["list"], CommandOutput::ok(list));
+        let exported = PackageList::from_installed(&runner, true).unwrap();
+        assert_eq!(exported.sources.len(), 2);
+        assert_eq!(exported.sources[0].source_details.name, "winget");
+        assert_eq!(exported.sources[0].packages[0].version.as_deref(), Some("23.01"));
+
+        let dir = temp_dir("packages-export");
+        let path = dir.join("packages.json");
+        exported.save(&path).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["$schema"], SCHEMA);
+        assert_eq!(json["Sources"][1]["Packages"][0]["PackageIdentifier"], "9N0DX20HK701");
+        assert_eq!(json["Sources"][0]["SourceDetails"]["Type"], "Microsoft.PreIndexed.Package");
+        assert_eq!(PackageList::load(&path).unwrap(), exported);
+    }
+
+    #[test]
+    fn test_load_winget_file() {
+        let json = "\u{feff}{
+            \"$schema\": \"https://aka.ms/winget-packages.schema.2.0.json\",
+            \"CreationDate\": \"2024-04-01T12:00:00.000-00:00\",
+            \"Sources\": [{
+                \"Packages\": [
+                    {\"PackageIdentifier\": \"Git.Git\", \"Version\": \"2.44.0\"},
+                    {\"PackageIdentifier\": \"7zip.7zip\"},
+                    {\"PackageIdentifier\": \"git.git\"}
+                ],
+                \"SourceDetails\": {
+                    \"Argument\": \"https://cdn.winget.microsoft.com/cache\",
+                    \"Identifier\": \"Microsoft.Winget.Source_8wekyb3d8bbwe\",
+                    \"Name\": \"winget\",
+                    \"Type\": \"Microsoft.PreIndexed.Package\"
+                }
+            }],
+            \"WinGetVersion\": \"1.7.10861\"
+        }";
+        let dir = temp_dir("packages-import");
+        let path = dir.join("packages.json");
+        fs::write(&path, json).unwrap();
+        let list = PackageList::load(&path).unwrap();
+        assert_eq!(list.win_get_version.as_deref(), Some("1.7.10861"));
+        assert_eq!(
+            list.wanted(false),
+            vec![
+                ("Git.Git".to_string(), VersionPin::Exact("2.44.0".to_string())),
+                ("7zip.7zip".to_string(), VersionPin::Latest),
+            ]
+        );
+        assert_eq!(list.wanted(true)[0].1, VersionPin::Latest);
+    }
+}
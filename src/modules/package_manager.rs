@@ -0,0 +1,241 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+use crate::modules::chocolatey::Chocolatey;
+use crate::modules::package_list::{self, PackageList};
+use crate::modules::packages::{self, VersionPin};
+use crate::modules::results::{ApplyStatus, Outcome};
+use crate::modules::scoop::{self, Scoop};
+use crate::modules::winget::{self, WingetEntry};
+use crate::utils::command::{self, CommandOutput, CommandRunner, CommandSpec};
+
+/// Менеджер пакетов
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Backend {
+    #[default]
+    Winget,
+    Scoop,
+    Chocolatey,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 3] = [Backend::Winget, Backend::Scoop, Backend::Chocolatey];
+
+    /// Имя в командной строке: winget, scoop, choco
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Winget => "winget",
+            Backend::Scoop => "scoop",
+            Backend::Chocolatey => "choco",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "winget" => Some(Backend::Winget),
+            "scoop" => Some(Backend::Scoop),
+            "choco" | "chocolatey" => Some(Backend::Chocolatey),
+            _ => None,
+        }
+    }
+
+    /// Исполняемый файл менеджера
+    pub fn program(&self) -> &'static str {
+        match self {
+            Backend::Scoop => scoop::PROGRAM,
+            other => other.name(),
+        }
+    }
+
+    /// Файл экспорта по умолчанию: у каждого менеджера свой формат
+    pub fn export_file(&self) -> &'static str {
+        match self {
+            Backend::Winget => package_list::DEFAULT_FILE,
+            Backend::Scoop => "scoop-apps.json",
+            Backend::Chocolatey => "packages.config",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Пакет в выводе менеджера: установленный или найденный
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageEntry {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    /// Доступная версия, если менеджер её сообщает
+    pub available: Option<String>,
+    /// Источник: winget, msstore, бакет scoop
+    pub source: Option<String>,
+}
+
+impl PackageEntry {
+    /// Обрезан ли Id (winget заменяет конец длинного Id на «…»)
+    pub fn is_truncated(&self) -> bool {
+        self.id.ends_with(winget::ELLIPSIS)
+    }
+
+    /// Совпадает ли Id без учёта регистра; обрезанный Id сравнивается по началу.
+    /// Id scoop может включать бакет (`extras/firefox`), а в списке установленных бакет идёт отдельно.
+    pub fn matches(&self, id: &str) -> bool {
+        let bare = id.rsplit('/').next().unwrap_or(id);
+        [id, bare].iter().any(|id| match self.id.strip_suffix(winget::ELLIPSIS) {
+            Some(prefix) => id.to_lowercase().starts_with(&prefix.to_lowercase()),
+            None => self.id.eq_ignore_ascii_case(id),
+        })
+    }
+}
+
+impl From<WingetEntry> for PackageEntry {
+    fn from(entry: WingetEntry) -> Self {
+        Self {
+            id: entry.id,
+            name: entry.name,
+            version: entry.version,
+            available: entry.available,
+            source: entry.source,
+        }
+    }
+}
+
+/// Операции менеджера пакетов. Ошибка — `Err`, безвредные исходы
+/// («уже установлен», «обновление не требуется») — `Outcome` с пояснением.
+pub trait PackageManager {
+    fn backend(&self) -> Backend;
+
+    fn list_installed(&self) -> io::Result<Vec<PackageEntry>>;
+
+    fn search(&self, query: &str) -> io::Result<Vec<PackageEntry>>;
+
+    fn install(&self, id: &str, pin: &VersionPin) -> io::Result<Outcome>;
+
+    fn uninstall(&self, id: &str) -> io::Result<Outcome>;
+
+    /// Обновляет пакет до последней версии
+    fn upgrade(&self, id: &str) -> io::Result<Outcome>;
+
+    /// Сохраняет установленные пакеты в родном формате менеджера
+    fn export(&self, path: &Path) -> io::Result<()>;
+}
+
+/// winget: обёртка над функциями [`packages`]
+pub struct Winget<'a> {
+    runner: &'a dyn CommandRunner,
+}
+
+impl<'a> Winget<'a> {
+    pub fn new(runner: &'a dyn CommandRunner) -> Self {
+        Self { runner }
+    }
+}
+
+impl PackageManager for Winget<'_> {
+    fn backend(&self) -> Backend {
+        Backend::Winget
+    }
+
+    fn list_installed(&self) -> io::Result<Vec<PackageEntry>> {
+        Ok(packages::get_installed_packages(self.runner)?.into_iter().map(PackageEntry::from).collect())
+    }
+
+    fn search(&self, query: &str) -> io::Result<Vec<PackageEntry>> {
+        Ok(packages::search_packages(self.runner, query)?.into_iter().map(PackageEntry::from).collect())
+    }
+
+    fn install(&self, id: &str, pin: &VersionPin) -> io::Result<Outcome> {
+        packages::install_package_pinned(self.runner, id, pin)
+    }
+
+    fn uninstall(&self, id: &str) -> io::Result<Outcome> {
+        packages::uninstall_package(self.runner, id)
+    }
+
+    fn upgrade(&self, id: &str) -> io::Result<Outcome> {
+        packages::upgrade_package(self.runner, id)
+    }
+
+    fn export(&self, path: &Path) -> io::Result<()> {
+        PackageList::from_installed(self.runner, true)?.save(path)
+    }
+}
+
+/// Итог команды менеджера, который сообщает о безвредных исходах только текстом:
+/// первый найденный в выводе маркер задаёт статус и пояснение
+pub(crate) fn outcome_from_output(
+    spec: &CommandSpec,
+    output: CommandOutput,
+    markers: &[(&str, ApplyStatus, &str)],
+) -> io::Result<Outcome> {
+    let text = format!("{}\n{}", output.stdout, output.stderr).to_lowercase();
+    if let Some((_, status, note)) = markers.iter().find(|(marker, _, _)| text.contains(&marker.to_lowercase())) {
+        return Ok(Outcome::new(*status, note));
+    }
+    output.check(spec).map(|_| Outcome::applied())
+}
+
+/// Команда менеджера пакетов: установка, удаление и обновление ждут дольше запросов
+pub(crate) fn spec(program: &str, args: &[&str]) -> CommandSpec {
+    let timeout = match args.first() {
+        Some(&("install" | "uninstall" | "update" | "upgrade")) => command::INSTALL_TIMEOUT,
+        _ => command::QUERY_TIMEOUT,
+    };
+    CommandSpec::new(program).args(args).timeout(timeout)
+}
+
+/// Менеджер для выбранного бэкенда
+pub fn manager<'a>(backend: Backend, runner: &'a dyn CommandRunner) -> Box<dyn PackageManager + 'a> {
+    match backend {
+        Backend::Winget => Box::new(Winget::new(runner)),
+        Backend::Scoop => Box::new(Scoop::new(runner)),
+        Backend::Chocolatey => Box::new(Chocolatey::new(runner)),
+    }
+}
+
+/// Менеджеры, которые установлены на машине
+pub fn available(runner: &dyn CommandRunner) -> Vec<Backend> {
+    Backend::ALL
+        .into_iter()
+        .filter(|backend| {
+            runner
+                .run(&spec(backend.program(), &["--version"]))
+                .is_ok_and(|output| output.success())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::ScriptedRunner;
+
+    #[test]
+    fn test_spec_timeouts() {
+        assert_eq!(spec("scoop", &["install", "git"]).timeout, Some(command::INSTALL_TIMEOUT));
+        assert_eq!(spec("choco", &["upgrade", "git"]).timeout, Some(command::INSTALL_TIMEOUT));
+        assert_eq!(spec("winget", &["search", "git"]).timeout, Some(command::QUERY_TIMEOUT));
+        assert_eq!(spec("winget", &["--version"]).timeout, Some(command::QUERY_TIMEOUT));
+    }
+
+    #[test]
+    fn test_available_backends() {
+        let runner = ScriptedRunner::new()
+            .expect("winget", &["--version"], CommandOutput::ok("v1.7.10861"))
+            .expect(scoop::PROGRAM, &["--version"], CommandOutput::with_code(1, ""))
+            .expect("choco", &["--version"], CommandOutput::ok("2.2.2"));
+        assert_eq!(available(&runner), vec![Backend::Winget, Backend::Chocolatey]);
+        assert_eq!(Backend::parse("Chocolatey"), Some(Backend::Chocolatey));
+
+        let entry = PackageEntry {
+            id: "firefox".to_string(),
+            ..Default::default()
+        };
+        assert!(entry.matches("extras/firefox"));
+        assert!(!entry.matches("extras/firefox-esr"));
+    }
+}
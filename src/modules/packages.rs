@@ -1,8 +1,15 @@
-use std::process::Command;
+use std::cmp::Ordering;
+use std::fmt;
 use std::io;
-use std::path::Path;
+use crate::modules::package_manager::{self, Backend, PackageEntry, PackageManager};
+use crate::modules::results::{ApplyReport, ApplyResult, ApplyStatus, Outcome};
+use crate::modules::tweaks::Selection;
+use crate::modules::winget::{self, Action, ExitCode, WingetEntry};
+use crate::progress;
+use crate::utils::command::{self, CommandRunner, CommandSpec};
 
-/// Список рекомендуемых Winget пакетов
+/// Список рекомендуемых Winget пакетов.
+/// Версия: "latest", точная ("3.12.2") или минимальная (">=20.0.0"), см. [`VersionPin`]
 pub const WINGET_PACKAGES: &[(&str, &str, &str)] = &[
     ("Valve.Steam", "latest", "🎮 Платформа цифровой дистрибуции игр"),
     ("Mozilla.Firefox", "latest", "🌐 Браузер с открытым исходным кодом"),
@@ -21,140 +28,694 @@ pub const WINGET_PACKAGES: &[(&str, &str, &str)] = &[
     ("Microsoft.VCRedist.2015+.x86", "latest", "Visual C++ 2015-2022 x86"),
 ];
 
+/// Id рекомендуемых пакетов в других менеджерах; у scoop — вместе с бакетом.
+/// Пакет без строки для менеджера через этот менеджер не ставится.
+pub const ALTERNATIVE_IDS: &[(&str, Backend, &str)] = &[
+    ("Valve.Steam", Backend::Chocolatey, "steam"),
+    ("Mozilla.Firefox", Backend::Scoop, "extras/firefox"),
+    ("Mozilla.Firefox", Backend::Chocolatey, "firefox"),
+    ("7zip.7zip", Backend::Scoop, "main/7zip"),
+    ("7zip.7zip", Backend::Chocolatey, "7zip"),
+    ("clsid2.mpc-hc", Backend::Scoop, "extras/mpc-hc-fork"),
+    ("clsid2.mpc-hc", Backend::Chocolatey, "mpc-hc-clsid2"),
+    ("Microsoft.VisualStudioCode", Backend::Scoop, "extras/vscode"),
+    ("Microsoft.VisualStudioCode", Backend::Chocolatey, "vscode"),
+    ("Notepad++.Notepad++", Backend::Scoop, "extras/notepadplusplus"),
+    ("Notepad++.Notepad++", Backend::Chocolatey, "notepadplusplus"),
+    ("Python.Python.3.12", Backend::Scoop, "versions/python312"),
+    ("Python.Python.3.12", Backend::Chocolatey, "python312"),
+    ("OpenJS.NodeJS", Backend::Scoop, "main/nodejs"),
+    ("OpenJS.NodeJS", Backend::Chocolatey, "nodejs"),
+    ("Git.Git", Backend::Scoop, "main/git"),
+    ("Git.Git", Backend::Chocolatey, "git"),
+    ("JetBrains.JetBrainsMono.NF", Backend::Scoop, "nerd-fonts/JetBrainsMono-NF"),
+    ("JetBrains.JetBrainsMono.NF", Backend::Chocolatey, "nerd-fonts-jetbrainsmono"),
+    ("Microsoft.DotNet.Runtime.8", Backend::Chocolatey, "dotnet-8.0-runtime"),
+    ("Microsoft.VCRedist.2015+.x64", Backend::Chocolatey, "vcredist140"),
+];
+
+/// Требование к версии пакета
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum VersionPin {
+    /// Любая версия; ставится последняя
+    #[default]
+    Latest,
+    /// Ровно эта версия: `3.12.2` или `=3.12.2`
+    Exact(String),
+    /// Эта версия или новее: `>=20.0.0`
+    Minimum(String),
+}
+
+impl VersionPin {
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        if text.is_empty() || text.eq_ignore_ascii_case("latest") {
+            VersionPin::Latest
+        } else if let Some(version) = text.strip_prefix(">=") {
+            VersionPin::Minimum(version.trim().to_string())
+        } else {
+            VersionPin::Exact(text.trim_start_matches('=').trim().to_string())
+        }
+    }
+
+    /// Подходит ли установленная версия
+    pub fn is_satisfied_by(&self, installed: &str) -> bool {
+        match self {
+            VersionPin::Latest => true,
+            VersionPin::Exact(version) => compare_versions(installed, version) == Ordering::Equal,
+            VersionPin::Minimum(version) => compare_versions(installed, version) != Ordering::Less,
+        }
+    }
+}
+
+impl fmt::Display for VersionPin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VersionPin::Latest => write!(f, "latest"),
+            VersionPin::Exact(version) => write!(f, "{}", version),
+            VersionPin::Minimum(version) => write!(f, ">={}", version),
+        }
+    }
+}
+
+fn version_parts(version: &str) -> Vec<&str> {
+    version.trim().trim_start_matches(['v', 'V']).split(['.', '-', '+']).collect()
+}
+
+/// Сравнивает версии по частям через точку: числа — как числа, остальное — как строки.
+/// Недостающие части считаются нулями: 1.2 == 1.2.0
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a, b) = (version_parts(a), version_parts(b));
+    for i in 0..a.len().max(b.len()) {
+        let x = a.get(i).copied().unwrap_or("0");
+        let y = b.get(i).copied().unwrap_or("0");
+        let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y),
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Разбирает `id` или `id@версия` (`Python.Python.3.12@3.12.2`, `OpenJS.NodeJS@>=20`)
+pub fn parse_package_spec(spec: &str) -> (String, VersionPin) {
+    match spec.split_once('@') {
+        Some((id, pin)) => (id.to_string(), VersionPin::parse(pin)),
+        None => (spec.to_string(), VersionPin::Latest),
+    }
+}
+
+/// Обратное к [`parse_package_spec`]: `latest` не записывается
+pub fn format_package_spec(id: &str, pin: &VersionPin) -> String {
+    match pin {
+        VersionPin::Latest => id.to_string(),
+        pin => format!("{}@{}", id, pin),
+    }
+}
+
 /// Структура для представления пакета
 #[derive(Debug, Clone)]
 pub struct Package {
     pub id: String,
+    /// Менеджер, к которому относится `id`
+    pub manager: Backend,
+    /// Id того же пакета в других менеджерах
+    pub alternatives: Vec<(Backend, String)>,
     pub version: String,
+    pub pin: VersionPin,
     pub description: String,
     pub installed: bool,
 }
 
-/// Проверяет установлен ли пакет
-pub fn is_package_installed(package_id: &str) -> io::Result<bool> {
-    let output = Command::new("winget")
-        .args(&["list", "--id", package_id])
-        .output()?;
+impl Package {
+    fn new(id: &str, manager: Backend, pin: VersionPin, description: String) -> Self {
+        Self {
+            id: id.to_string(),
+            manager,
+            alternatives: Vec::new(),
+            version: pin.to_string(),
+            pin,
+            description,
+            installed: false,
+        }
+    }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    Ok(stdout.contains(package_id))
+    /// Id пакета в менеджере или `None`, если там его нет
+    pub fn id_for(&self, backend: Backend) -> Option<&str> {
+        if backend == self.manager {
+            return Some(&self.id);
+        }
+        self.alternatives.iter().find(|(b, _)| *b == backend).map(|(_, id)| id.as_str())
+    }
 }
 
-/// Устанавливает пакет через winget
-pub fn install_package(id: &str) -> io::Result<()> {
-    println!("🔄 Установка пакета {}...", id);
-    
-    let status = Command::new("winget")
-        .args(&["install", "--id", id, "--silent", "--accept-source-agreements", "--accept-package-agreements"])
-        .status()?;
-
-    if status.success() {
-        println!("✅ Пакет {} успешно установлен.", id);
-        
-        // Специальная обработка для Firefox
-        if id.contains("Firefox") {
-            configure_firefox()?;
-        }
-        
-        // Специальная обработка для Hiddify
-        if id.contains("Hiddify") {
-            configure_hiddify()?;
+/// Рекомендуемые пакеты без проверки установки
+pub fn curated_packages() -> Vec<Package> {
+    WINGET_PACKAGES
+        .iter()
+        .map(|(id, version, description)| Package {
+            id: id.to_string(),
+            manager: Backend::Winget,
+            alternatives: ALTERNATIVE_IDS
+                .iter()
+                .filter(|(winget_id, _, _)| winget_id == id)
+                .map(|(_, backend, alternative)| (*backend, alternative.to_string()))
+                .collect(),
+            version: version.to_string(),
+            pin: VersionPin::parse(version),
+            description: description.to_string(),
+            installed: false,
+        })
+        .collect()
+}
+
+/// Получает рекомендуемые пакеты с отметкой, установлены ли они;
+/// у установленных версия — установленная
+pub fn get_winget_packages(runner: &dyn CommandRunner) -> io::Result<Vec<Package>> {
+    let installed: Vec<PackageEntry> = get_installed_packages(runner)?.into_iter().map(PackageEntry::from).collect();
+    Ok(mark_installed(curated_packages(), Backend::Winget, &installed))
+}
+
+/// Запись о пакете в списке установленных менеджером `backend`
+fn find_installed<'a>(package: &Package, backend: Backend, installed: &'a [PackageEntry]) -> Option<&'a PackageEntry> {
+    let id = package.id_for(backend)?;
+    installed.iter().find(|e| e.matches(id))
+}
+
+/// Отмечает пакеты, установленные менеджером `backend`, и подставляет их версии
+pub fn mark_installed(mut packages: Vec<Package>, backend: Backend, installed: &[PackageEntry]) -> Vec<Package> {
+    for package in &mut packages {
+        if let Some(entry) = find_installed(package, backend, installed) {
+            package.installed = true;
+            package.version = entry.version.clone();
         }
-    } else {
-        println!("❌ Не удалось установить пакет {}", id);
     }
+    packages
+}
 
-    Ok(())
+/// Ищет пакеты в источниках winget по названию, Id, моникеру или тегу
+pub fn search_packages(runner: &dyn CommandRunner, query: &str) -> io::Result<Vec<WingetEntry>> {
+    query_winget(runner, &package_manager::spec("winget", &["search", query, "--accept-source-agreements"]))
 }
 
-/// Удаляет пакет через winget
-pub fn uninstall_package(id: &str) -> io::Result<()> {
-    println!("🔄 Удаление пакета {}...", id);
-    
-    let status = Command::new("winget")
-        .args(&["uninstall", "--id", id, "--silent"])
-        .status()?;
+/// Таблица из `winget list/search/upgrade`. «Ничего не найдено» — пустой список,
+/// другой ненулевой код — ошибка: пустой список из-за сбоя winget выглядел бы как
+/// «ничего не установлено»
+fn query_winget(runner: &dyn CommandRunner, spec: &CommandSpec) -> io::Result<Vec<WingetEntry>> {
+    let output = runner.run(spec)?;
+    if matches!(
+        output.code.map(ExitCode::from_code),
+        Some(ExitCode::NoPackagesFound | ExitCode::UpdateNotApplicable)
+    ) {
+        return Ok(Vec::new());
+    }
+    Ok(winget::parse_table(&output.check(spec)?.stdout))
+}
 
-    if status.success() {
-        println!("✅ Пакет {} успешно удалён.", id);
-    } else {
-        println!("❌ Не удалось удалить пакет {}", id);
+/// Добавляет к списку пакеты, найденные менеджером `backend`, которых в нём ещё нет.
+/// Обрезанные Id пропускаются: установить по ним нельзя.
+pub fn merge_found(packages: &mut Vec<Package>, backend: Backend, found: &[PackageEntry], installed: &[PackageEntry]) {
+    for entry in found.iter().filter(|e| !e.is_truncated()) {
+        if packages.iter().any(|p| p.id_for(backend).is_some_and(|id| entry.matches(id))) {
+            continue;
+        }
+        let mut package = Package::new(&entry.id, backend, VersionPin::Latest, format!("🔍 {}", entry.name));
+        package.version = entry.version.clone();
+        if let Some(installed) = find_installed(&package, backend, installed) {
+            package.installed = true;
+            package.version = installed.version.clone();
+        }
+        packages.push(package);
     }
+}
 
-    Ok(())
+/// Добавляет к списку winget-пакеты из импортированного файла: рекомендуемым
+/// назначается версия из файла, остальные добавляются с отметкой установки
+/// по списку `installed` менеджера `backend`
+pub fn merge_imported(packages: &mut Vec<Package>, wanted: &[(String, VersionPin)], backend: Backend, installed: &[PackageEntry]) {
+    for (id, pin) in wanted {
+        match packages.iter_mut().find(|p| p.id_for(Backend::Winget).is_some_and(|w| w.eq_ignore_ascii_case(id))) {
+            Some(package) => {
+                if *pin != VersionPin::Latest {
+                    package.pin = pin.clone();
+                }
+            }
+            None => {
+                let mut package = Package::new(id, Backend::Winget, pin.clone(), "📥 Из импортированного списка".to_string());
+                if let Some(entry) = find_installed(&package, backend, installed) {
+                    package.installed = true;
+                    package.version = entry.version.clone();
+                }
+                packages.push(package);
+            }
+        }
+    }
 }
 
-/// Обновляет все пакеты через winget
-pub fn update_all_packages() -> io::Result<()> {
-    println!("🔄 Обновление всех пакетов через winget...");
-    
-    let status = Command::new("winget")
-        .args(&["upgrade", "--all", "--silent", "--accept-source-agreements", "--accept-package-agreements"])
-        .status()?;
+/// Устанавливает отмеченные пакеты и удаляет снятые через менеджер `manager`.
+/// Ошибка одного пакета не останавливает остальные; пакет, которого нет в менеджере, пропускается.
+/// Результаты записываются под Id каталога (winget), даже если менеджер знает пакет под другим Id.
+pub fn apply_package_selection(manager: &dyn PackageManager, packages: &[Package], selection: &Selection) -> ApplyReport {
+    let backend = manager.backend();
+    packages
+        .iter()
+        .filter_map(|package| {
+            let (message, install) = match selection.get(&package.id) {
+                Some(true) if !package.installed => ("установка", true),
+                Some(false) if package.installed => ("удаление", false),
+                _ => return None,
+            };
+            let Some(id) = package.id_for(backend) else {
+                let outcome = Outcome::new(ApplyStatus::Skipped, &format!("нет в {}", backend));
+                return Some(ApplyResult::from_outcome(&package.id, message, Ok(outcome)));
+            };
+            let outcome = if install { manager.install(id, &package.pin) } else { manager.uninstall(id) };
+            let message = if id == package.id { message.to_string() } else { format!("{} ({})", message, id) };
+            Some(ApplyResult::from_outcome(&package.id, &message, outcome))
+        })
+        .collect()
+}
+
+/// Пакеты, которые были отмечены к установке и установились: для них выполняются действия после установки
+pub fn newly_installed(report: &ApplyReport, selection: &Selection) -> Vec<String> {
+    report.applied_ids().into_iter().filter(|id| selection.get(id) == Some(&true)).collect()
+}
+
+/// Проверяет установлен ли пакет
+pub fn is_package_installed(runner: &dyn CommandRunner, package_id: &str) -> io::Result<bool> {
+    Ok(installed_version(runner, package_id)?.is_some())
+}
+
+/// Установленная версия пакета или `None`, если пакет не установлен
+pub fn installed_version(runner: &dyn CommandRunner, package_id: &str) -> io::Result<Option<String>> {
+    let spec = package_manager::spec("winget", &["list", "--id", package_id, "--exact"]);
+    Ok(query_winget(runner, &spec)?
+        .into_iter()
+        .find(|e| e.id_matches(package_id))
+        .map(|e| e.version))
+}
 
-    if status.success() {
-        println!("✅ Все пакеты успешно обновлены.");
-    } else {
-        println!("❌ Не удалось обновить пакеты.");
+/// Запускает winget и толкует код завершения: безвредные коды («уже установлен»,
+/// «обновление не требуется») дают итог с пояснением, остальные — ошибку
+fn run_winget(runner: &dyn CommandRunner, spec: &CommandSpec, action: Action) -> io::Result<Outcome> {
+    let output = runner.run(spec)?;
+    let code = match output.code {
+        Some(code) => ExitCode::from_code(code),
+        None => return output.check(spec).map(|_| Outcome::applied()),
+    };
+    match code {
+        ExitCode::Success => Ok(Outcome::applied()),
+        // Неизвестный код — ошибка с выводом winget
+        ExitCode::Other(_) => output.check(spec).map(|_| Outcome::applied()),
+        code if code.status(action).is_failure() => {
+            let kind = match code {
+                ExitCode::NoPackagesFound | ExitCode::NoApplicableInstaller => io::ErrorKind::NotFound,
+                _ => io::ErrorKind::Other,
+            };
+            Err(io::Error::new(kind, format!("{}: {}", spec, code)))
+        }
+        code => Ok(Outcome::new(code.status(action), code.message())),
     }
+}
 
-    Ok(())
+/// Выводит итог операции с пакетом
+pub(crate) fn report_outcome(id: &str, outcome: &Outcome, done: &str) {
+    match &outcome.note {
+        Some(note) => progress!("{} Пакет {}: {}.", outcome.status.icon(), id, note),
+        None => progress!("✅ Пакет {} {}.", id, done),
+    }
 }
 
-/// Конфигурирует Firefox после установки
-fn configure_firefox() -> io::Result<()> {
-    println!("⚙️  Настройка Firefox...");
-    
-    // Путь к профилю Firefox
-    let appdata = std::env::var("APPDATA").unwrap_or_default();
-    let firefox_path = Path::new(&appdata).join("Mozilla").join("Firefox").join("Profiles");
+/// Устанавливает пакет через winget. Неуспешный код возврата winget — ошибка.
+pub fn install_package(runner: &dyn CommandRunner, id: &str) -> io::Result<Outcome> {
+    install_package_pinned(runner, id, &VersionPin::Latest)
+}
+
+/// Устанавливает пакет с учётом закреплённой версии.
+/// Точная версия передаётся winget через `--version`; для минимальной ставится последняя.
+pub fn install_package_pinned(runner: &dyn CommandRunner, id: &str, pin: &VersionPin) -> io::Result<Outcome> {
+    progress!("🔄 Установка пакета {} ({})...", id, pin);
     
-    if firefox_path.exists() {
-        println!("ℹ️  Найдена папка профилей Firefox: {:?}", firefox_path);
-        println!("ℹ️  Для применения настроек, скопируйте конфигурационные файлы из data/config_templates/firefox/");
-    } else {
-        println!("⚠️  Папка профилей Firefox не найдена. Запустите Firefox один раз для создания профиля.");
+    let mut spec = package_manager::spec("winget", &["install", "--id", id]);
+    if let VersionPin::Exact(version) = pin {
+        spec = spec.args(["--exact", "--version", version]);
     }
-    
-    Ok(())
+    let spec = spec.args(["--silent", "--accept-source-agreements", "--accept-package-agreements"]);
+    let outcome = run_winget(runner, &spec, Action::Install)?;
+    report_outcome(id, &outcome, "успешно установлен");
+    Ok(outcome)
 }
 
-/// Конфигурирует Hiddify после установки
-fn configure_hiddify() -> io::Result<()> {
-    println!("⚙️  Настройка Hiddify...");
-    
-    let appdata = std::env::var("APPDATA").unwrap_or_default();
-    let hiddify_path = Path::new(&appdata).join("Hiddify").join("hiddify");
+/// Удаляет пакет через winget
+pub fn uninstall_package(runner: &dyn CommandRunner, id: &str) -> io::Result<Outcome> {
+    progress!("🔄 Удаление пакета {}...", id);
     
-    if hiddify_path.exists() {
-        println!("ℹ️  Найдена папка Hiddify: {:?}", hiddify_path);
-        println!("ℹ️  Для применения настроек, замените файлы конфигурации из data/config_templates/hiddify/");
-    } else {
-        println!("⚠️  Папка Hiddify не найдена.");
+    let spec = package_manager::spec("winget", &["uninstall", "--id", id, "--silent"]);
+    let outcome = run_winget(runner, &spec, Action::Uninstall)?;
+    report_outcome(id, &outcome, "успешно удалён");
+    Ok(outcome)
+}
+
+/// Пакеты, для которых winget нашёл обновление
+pub fn get_upgrades(runner: &dyn CommandRunner) -> io::Result<Vec<WingetEntry>> {
+    // Без --id upgrade только показывает доступные обновления
+    let spec = package_manager::spec("winget", &["upgrade", "--accept-source-agreements"]).timeout(command::QUERY_TIMEOUT);
+    Ok(query_winget(runner, &spec)?
+        .into_iter()
+        .filter(|e| e.available.is_some())
+        .collect())
+}
+
+/// Обновляет один пакет через winget до последней версии
+pub fn upgrade_package(runner: &dyn CommandRunner, id: &str) -> io::Result<Outcome> {
+    upgrade_package_to(runner, id, None)
+}
+
+/// Обновляет пакет до указанной версии или, без неё, до последней
+fn upgrade_package_to(runner: &dyn CommandRunner, id: &str, version: Option<&str>) -> io::Result<Outcome> {
+    progress!("🔄 Обновление пакета {}...", id);
+
+    let mut spec = package_manager::spec("winget", &["upgrade", "--id", id, "--exact"]);
+    if let Some(version) = version {
+        spec = spec.args(["--version", version]);
     }
-    
-    Ok(())
+    let spec = spec.args(["--silent", "--accept-source-agreements", "--accept-package-agreements"]);
+    let outcome = run_winget(runner, &spec, Action::Upgrade)?;
+    report_outcome(id, &outcome, "обновлён");
+    Ok(outcome)
 }
 
-/// Получает список всех установленных winget пакетов
-pub fn get_installed_packages() -> io::Result<Vec<String>> {
-    let output = Command::new("winget")
-        .args(&["list"])
-        .output()?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let packages: Vec<String> = stdout
-        .lines()
-        .skip(2) // Пропускаем заголовки
-        .filter_map(|line| {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                Some(parts[0].to_string())
-            } else {
-                None
+/// Обновляет только отмеченные пакеты, по одному.
+/// Ошибка одного пакета не останавливает остальные.
+pub fn apply_upgrades(runner: &dyn CommandRunner, upgrades: &[WingetEntry], selection: &Selection) -> ApplyReport {
+    upgrades
+        .iter()
+        .filter(|entry| selection.get(&entry.id) == Some(&true))
+        .map(|entry| {
+            let message = format!("обновление {} → {}", entry.version, entry.available.as_deref().unwrap_or("?"));
+            if entry.is_truncated() {
+                let error = io::Error::new(io::ErrorKind::InvalidData, "winget обрезал Id пакета, обновите его вручную");
+                return ApplyResult::from_error(&entry.id, &message, &error);
             }
+            ApplyResult::from_outcome(&entry.id, &message, upgrade_package(runner, &entry.id))
+        })
+        .collect()
+}
+
+/// Приводит пакеты к закреплённым версиям: ставит отсутствующие,
+/// обновляет устаревшие и откатывает более новые, чем точная версия.
+/// Откат — удаление и установка нужной версии: winget не понижает версию при обновлении.
+pub fn sync_packages(runner: &dyn CommandRunner, wanted: &[(String, VersionPin)]) -> io::Result<ApplyReport> {
+    let installed = get_installed_packages(runner)?;
+
+    Ok(wanted
+        .iter()
+        .map(|(id, pin)| {
+            let current = installed.iter().find(|e| e.id_matches(id)).map(|e| e.version.as_str());
+            let (message, result) = match (current, pin) {
+                (None, _) => (format!("установка {}", pin), install_package_pinned(runner, id, pin)),
+                (Some(version), pin) if pin.is_satisfied_by(version) => {
+                    return ApplyResult::new(id, ApplyStatus::Skipped, &format!("{} соответствует {}", version, pin));
+                }
+                (Some(version), VersionPin::Exact(target)) if compare_versions(version, target) == Ordering::Greater => (
+                    format!("откат {} → {}", version, target),
+                    uninstall_package(runner, id).and_then(|_| install_package_pinned(runner, id, pin)),
+                ),
+                (Some(version), VersionPin::Exact(target)) => {
+                    (format!("обновление {} → {}", version, target), upgrade_package_to(runner, id, Some(target)))
+                }
+                (Some(version), _) => (format!("обновление {} → {}", version, pin), upgrade_package(runner, id)),
+            };
+            ApplyResult::from_outcome(id, &message, result)
+        })
+        .collect())
+}
+
+/// Ставит пакеты, которых ещё нет, как `winget import`: установленные пропускаются,
+/// даже если их версия отличается от указанной
+pub fn install_missing(runner: &dyn CommandRunner, wanted: &[(String, VersionPin)]) -> io::Result<ApplyReport> {
+    let installed = get_installed_packages(runner)?;
+
+    Ok(wanted
+        .iter()
+        .map(|(id, pin)| match installed.iter().find(|e| e.id_matches(id)) {
+            Some(entry) => ApplyResult::new(id, ApplyStatus::Skipped, &format!("уже установлен {}", entry.version)),
+            None => ApplyResult::from_outcome(id, &format!("установка {}", pin), install_package_pinned(runner, id, pin)),
         })
-        .collect();
+        .collect())
+}
 
-    Ok(packages)
+/// Обновляет все пакеты через winget
+pub fn update_all_packages(runner: &dyn CommandRunner) -> io::Result<()> {
+    progress!("🔄 Обновление всех пакетов через winget...");
+    
+    let spec = package_manager::spec(
+        "winget",
+        &["upgrade", "--all", "--silent", "--accept-source-agreements", "--accept-package-agreements"],
+    );
+    runner.run(&spec)?.check(&spec)?;
+    progress!("✅ Все пакеты успешно обновлены.");
+
+    Ok(())
+}
+
+/// Получает список всех установленных программ из `winget list`
+pub fn get_installed_packages(runner: &dyn CommandRunner) -> io::Result<Vec<WingetEntry>> {
+    query_winget(runner, &package_manager::spec("winget", &["list"]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::package_manager::Winget;
+    use crate::modules::scoop::{self, Scoop};
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+
+    const INSTALL_ARGS: [&str; 3] = ["--silent", "--accept-source-agreements", "--accept-package-agreements"];
+
+    #[test]
+    fn test_apply_package_selection() {
+        let runner = ScriptedRunner::new().expect(
+            "winget",
+            &["list"],
+            CommandOutput::ok("Name   Id         Version\n--------------------------\n7-Zip  7zip.7zip  23.01\nGit    Git.Git    2.43.0"),
+        );
+        let packages = get_winget_packages(&runner).unwrap();
+        let zip = packages.iter().find(|p| p.id == "7zip.7zip").unwrap();
+        assert!(zip.installed);
+        assert_eq!(zip.version, "23.01");
+        assert!(!packages.iter().find(|p| p.id == "Valve.Steam").unwrap().installed);
+
+        let selection: Selection = [("7zip.7zip", false), ("Git.Git", true), ("Valve.Steam", true)]
+            .into_iter()
+            .map(|(id, on)| (id.to_string(), on))
+            .collect();
+        let mut install = vec!["install", "--id", "Valve.Steam"];
+        install.extend(INSTALL_ARGS);
+        let runner = ScriptedRunner::new()
+            .expect("winget", &install, CommandOutput::with_code(1, "ошибка"))
+            .expect("winget", &["uninstall", "--id", "7zip.7zip", "--silent"], CommandOutput::ok(""));
+
+        let report = apply_package_selection(&Winget::new(&runner), &packages, &selection);
+        assert_eq!(report.results[0].status, crate::modules::results::ApplyStatus::Failed);
+        assert_eq!(report.results[1].status, crate::modules::results::ApplyStatus::Applied);
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn test_winget_exit_codes_in_report() {
+        let packages: Vec<Package> = ["Valve.Steam", "clsid2.mpc-hc", "Git.Git"]
+            .into_iter()
+            .map(|id| Package {
+                installed: id == "Git.Git",
+                ..Package::new(id, Backend::Winget, VersionPin::Latest, String::new())
+            })
+            .collect();
+        let selection: Selection = [("Valve.Steam", true), ("clsid2.mpc-hc", true), ("Git.Git", false)]
+            .into_iter()
+            .map(|(id, on)| (id.to_string(), on))
+            .collect();
+        let install = |id: &'static str| -> Vec<&'static str> { ["install", "--id", id].iter().chain(&INSTALL_ARGS).copied().collect() };
+        let runner = ScriptedRunner::new()
+            .expect("winget", &install("Valve.Steam"), CommandOutput::with_code(ExitCode::AlreadyInstalled.code(), ""))
+            .expect("winget", &install("clsid2.mpc-hc"), CommandOutput::with_code(ExitCode::BlockedByPolicy.code(), ""))
+            .expect(
+                "winget",
+                &["uninstall", "--id", "Git.Git", "--silent"],
+                CommandOutput::with_code(ExitCode::NoPackagesFound.code(), ""),
+            );
+
+        let report = apply_package_selection(&Winget::new(&runner), &packages, &selection);
+        let statuses: Vec<ApplyStatus> = report.results.iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec![ApplyStatus::Skipped, ApplyStatus::Failed, ApplyStatus::Skipped]);
+        assert_eq!(report.results[0].details.as_deref(), Some("пакет уже установлен"));
+        assert!(report.results[1].details.as_deref().unwrap().contains("запрещена групповой политикой (0x8A150056)"));
+        assert_eq!(report.count(ApplyStatus::Failed), 1);
+    }
+
+    #[test]
+    fn test_alternative_ids() {
+        let installed = vec![PackageEntry {
+            id: "git".to_string(),
+            version: "2.44.0".to_string(),
+            ..Default::default()
+        }];
+        let packages = mark_installed(curated_packages(), Backend::Scoop, &installed);
+        let git = packages.iter().find(|p| p.id == "Git.Git").unwrap();
+        assert!(git.installed);
+        assert_eq!(git.id_for(Backend::Chocolatey), Some("git"));
+        assert_eq!(packages.iter().find(|p| p.id == "Valve.Steam").unwrap().id_for(Backend::Scoop), None);
+
+        let selection: Selection = [("Mozilla.Firefox", true), ("Valve.Steam", true), ("Git.Git", false)]
+            .into_iter()
+            .map(|(id, on)| (id.to_string(), on))
+            .collect();
+        let runner = ScriptedRunner::new()
+            .expect(scoop::PROGRAM, &["install", "extras/firefox"], CommandOutput::ok("'firefox' (124.0.2) was installed successfully!"))
+            .expect(scoop::PROGRAM, &["uninstall", "main/git"], CommandOutput::ok("'git' was uninstalled."));
+        let report = apply_package_selection(&Scoop::new(&runner), &packages, &selection);
+        let statuses: Vec<ApplyStatus> = report.results.iter().map(|r| r.status).collect();
+        // Порядок — как в каталоге: Steam, Firefox, Git
+        assert_eq!(statuses, vec![ApplyStatus::Skipped, ApplyStatus::Applied, ApplyStatus::Applied]);
+        assert_eq!(report.results[0].details.as_deref(), Some("нет в scoop"));
+        assert_eq!(report.results[1].id, "Mozilla.Firefox");
+        assert_eq!(report.results[1].message, "установка (extras/firefox)");
+        assert!(runner.is_exhausted());
+
+        // Действия после установки находятся по Id каталога; удалённый Git в них не попадает
+        let installed = newly_installed(&report, &selection);
+        assert_eq!(installed, vec!["Mozilla.Firefox".to_string()]);
+        assert!(!crate::modules::hooks::HookCatalog::builtin().actions_for(&installed[0]).is_empty());
+    }
+
+    #[test]
+    fn test_version_pins() {
+        assert_eq!(VersionPin::parse("latest"), VersionPin::Latest);
+        assert_eq!(VersionPin::parse("=3.12.2"), VersionPin::Exact("3.12.2".to_string()));
+        assert_eq!(VersionPin::parse(">= 20"), VersionPin::Minimum("20".to_string()));
+        assert_eq!(parse_package_spec("OpenJS.NodeJS@>=20.11").1.to_string(), ">=20.11");
+
+        assert_eq!(compare_versions("3.12.10", "3.12.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.2", "1.2.0"), Ordering::Equal);
+        assert!(VersionPin::parse(">=20").is_satisfied_by("21.7.1"));
+        assert!(!VersionPin::parse("3.12.2").is_satisfied_by("3.12.3"));
+    }
+
+    #[test]
+    fn test_install_missing() {
+        let list = "Name     Id                   Version\n\
+                    -------------------------------------\n\
+                    Python   Python.Python.3.12   3.12.3";
+        let runner = ScriptedRunner::new().expect("winget", &["list"], CommandOutput::ok(list)).expect(
+            "winget",
+            &["install", "--id", "Git.Git", "--exact", "--version", "2.44.0", "--silent", "--accept-source-agreements", "--accept-package-agreements"],
+            CommandOutput::ok(""),
+        );
+        let wanted = vec![
+            ("python.python.3.12".to_string(), VersionPin::Exact("3.12.2".to_string())),
+            ("Git.Git".to_string(), VersionPin::Exact("2.44.0".to_string())),
+        ];
+        // Более новый Python не откатывается
+        let report = install_missing(&runner, &wanted).unwrap();
+        let statuses: Vec<(&str, ApplyStatus)> = report.results.iter().map(|r| (r.message.as_str(), r.status)).collect();
+        assert_eq!(statuses, vec![("уже установлен 3.12.3", ApplyStatus::Skipped), ("установка 2.44.0", ApplyStatus::Applied)]);
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn test_merge_imported() {
+        let mut packages = curated_packages();
+        let installed = vec![PackageEntry {
+            id: "Discord.Discord".to_string(),
+            version: "1.0.9035".to_string(),
+            ..Default::default()
+        }];
+        let wanted = vec![
+            ("git.git".to_string(), VersionPin::Exact("2.44.0".to_string())),
+            ("Discord.Discord".to_string(), VersionPin::Latest),
+        ];
+        merge_imported(&mut packages, &wanted, Backend::Winget, &installed);
+
+        assert_eq!(packages.len(), WINGET_PACKAGES.len() + 1);
+        let git = packages.iter().find(|p| p.id == "Git.Git").unwrap();
+        assert_eq!(git.pin, VersionPin::Exact("2.44.0".to_string()));
+        let discord = packages.last().unwrap();
+        assert!(discord.installed);
+        assert_eq!(discord.version, "1.0.9035");
+    }
+
+    #[test]
+    fn test_search_and_merge() {
+        let output = "Name               Id                         Version Match           Source\n\
+                      ----------------------------------------------------------------------------\n\
+                      Visual Studio Code Microsoft.VisualStudioCode 1.88.0  Moniker: vscode winget\n\
+                      VSCodium           VSCodium.VSCodium          1.88.0  Tag: vscode     winget";
+        let runner = ScriptedRunner::new()
+            .expect("winget", &["search", "vscode", "--accept-source-agreements"], CommandOutput::ok(output))
+            .expect(
+                "winget",
+                &["search", "nothing", "--accept-source-agreements"],
+                CommandOutput::with_code(ExitCode::NoPackagesFound.code(), "No package found matching input criteria."),
+            );
+        let found: Vec<PackageEntry> = search_packages(&runner, "vscode").unwrap().into_iter().map(PackageEntry::from).collect();
+        assert!(search_packages(&runner, "nothing").unwrap().is_empty());
+
+        let installed = vec![PackageEntry {
+            id: "VSCodium.VSCodium".to_string(),
+            version: "1.87.2".to_string(),
+            ..Default::default()
+        }];
+        let mut packages = curated_packages();
+        merge_found(&mut packages, Backend::Winget, &found, &installed);
+        // Microsoft.VisualStudioCode уже есть в рекомендуемых
+        assert_eq!(packages.len(), WINGET_PACKAGES.len() + 1);
+        let codium = packages.last().unwrap();
+        assert_eq!(codium.description, "🔍 VSCodium");
+        assert_eq!(codium.version, "1.87.2");
+        assert!(codium.installed);
+    }
+
+    #[test]
+    fn test_winget_query_failures() {
+        let no_packages = CommandOutput::with_code(ExitCode::NoPackagesFound.code(), "No installed package found matching input criteria.");
+        let runner = ScriptedRunner::new()
+            .expect("winget", &["list"], no_packages.clone())
+            .expect("winget", &["list"], CommandOutput::with_code(1, "Failed when opening source(s)"))
+            .expect("winget", &["upgrade", "--accept-source-agreements"], CommandOutput::with_code(1, ""))
+            .expect("winget", &["list", "--id", "Git.Git", "--exact"], no_packages)
+            .expect("winget", &["list", "--id", "Git.Git", "--exact"], CommandOutput::with_code(1, ""));
+        // Пустой список — только когда winget сам сказал, что пакетов нет
+        assert!(get_installed_packages(&runner).unwrap().is_empty());
+        assert!(get_installed_packages(&runner).is_err());
+        assert!(get_upgrades(&runner).is_err());
+        assert_eq!(installed_version(&runner, "Git.Git").unwrap(), None);
+        assert!(installed_version(&runner, "Git.Git").is_err());
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn test_upgrade_only_selected() {
+        let table = "Имя     ИД       Версия  Доступно Источник\n\
+                     ------------------------------------------\n\
+                     Git     Git.Git  2.43.0  2.44.0   winget\n\
+                     Node.js OpenJS.… 20.11.0 21.7.1   winget\n\
+                     Доступно обновлений: 2.";
+        let runner = ScriptedRunner::new().expect("winget", &["upgrade", "--accept-source-agreements"], CommandOutput::ok(table));
+        let upgrades = get_upgrades(&runner).unwrap();
+        assert_eq!(upgrades.len(), 2);
+        assert_eq!(upgrades[0].available.as_deref(), Some("2.44.0"));
+
+        let selection: Selection = [("Git.Git".to_string(), true), ("OpenJS.…".to_string(), true)].into_iter().collect();
+        let mut upgrade = vec!["upgrade", "--id", "Git.Git", "--exact"];
+        upgrade.extend(INSTALL_ARGS);
+        let runner = ScriptedRunner::new().expect("winget", &upgrade, CommandOutput::ok(""));
+        let report = apply_upgrades(&runner, &upgrades, &selection);
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(report.results[0].message, "обновление 2.43.0 → 2.44.0");
+        assert!(report.results[1].status.is_failure());
+        assert!(runner.is_exhausted());
+    }
 }
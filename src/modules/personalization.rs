@@ -1,8 +1,12 @@
 use std::io;
-use winreg::enums::*;
-use winreg::RegKey;
+use crate::modules::plan::{self, Plan};
+use crate::modules::results::ApplyReport;
+use crate::modules::tweaks::{self, Catalog, Category, Host, Selection};
+use crate::progress;
+use crate::utils::registry::RegistryBackend;
 
 /// Настройки персонализации
+#[derive(Debug, Default)]
 pub struct PersonalizationSettings {
     pub add_end_task_button: bool,
     pub enable_dark_theme: bool,
@@ -11,124 +15,105 @@ pub struct PersonalizationSettings {
     pub remove_context_delay: bool,
 }
 
-impl Default for PersonalizationSettings {
-    fn default() -> Self {
+impl PersonalizationSettings {
+    /// Основные настройки в виде выбора твиков каталога
+    pub fn basic_selection(&self) -> Selection {
+        [
+            ("add_end_task_button", self.add_end_task_button),
+            ("enable_dark_theme", self.enable_dark_theme),
+            ("enable_verbose_status", self.enable_verbose_status),
+        ]
+        .into_iter()
+        .map(|(id, enable)| (id.to_string(), enable))
+        .collect()
+    }
+
+    /// Настройки контекстного меню в виде выбора твиков каталога
+    pub fn context_menu_selection(&self) -> Selection {
+        [
+            ("enable_old_context_menu", self.enable_old_context_menu),
+            ("remove_context_delay", self.remove_context_delay),
+        ]
+        .into_iter()
+        .map(|(id, enable)| (id.to_string(), enable))
+        .collect()
+    }
+
+    /// Настройки из выбора твиков; отсутствующие считаются выключенными
+    pub fn from_selection(selection: &Selection) -> Self {
+        let get = |id: &str| selection.get(id).copied().unwrap_or(false);
         Self {
-            add_end_task_button: false,
-            enable_dark_theme: false,
-            enable_verbose_status: false,
-            enable_old_context_menu: false,
-            remove_context_delay: false,
+            add_end_task_button: get("add_end_task_button"),
+            enable_dark_theme: get("enable_dark_theme"),
+            enable_verbose_status: get("enable_verbose_status"),
+            enable_old_context_menu: get("enable_old_context_menu"),
+            remove_context_delay: get("remove_context_delay"),
         }
     }
 }
 
-/// Добавляет кнопку "Завершить задачу" на панель задач
-pub fn add_end_task_button(enable: bool) -> io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced\\TaskbarDeveloperSettings")?;
-    
-    key.set_value("TaskbarEndTask", &(if enable { 1u32 } else { 0u32 }))?;
-    
-    println!("{} Кнопка \"Завершить задачу\" на панели задач {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "добавлена" } else { "убрана" }
-    );
-    
-    Ok(())
+/// Получает текущие настройки персонализации
+pub fn get_personalization_settings(reg: &dyn RegistryBackend, catalog: &Catalog) -> PersonalizationSettings {
+    let mut selection = tweaks::current_selection(reg, catalog, Category::PersonalizationBasic);
+    selection.extend(tweaks::current_selection(reg, catalog, Category::PersonalizationContext));
+    PersonalizationSettings::from_selection(&selection)
 }
 
-/// Включает тёмную тему
-pub fn enable_dark_theme(enable: bool) -> io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    
-    // Настройки для приложений
-    let (key, _) = hkcu.create_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize")?;
-    key.set_value("AppsUseLightTheme", &(if enable { 0u32 } else { 1u32 }))?;
-    key.set_value("SystemUsesLightTheme", &(if enable { 0u32 } else { 1u32 }))?;
-    
-    println!("{} Тёмная тема {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "включена" } else { "отключена" }
-    );
-    
-    Ok(())
+/// Планирует изменения для основных настроек персонализации, ничего не меняя
+pub fn plan_basic_personalization(reg: &dyn RegistryBackend, catalog: &Catalog, settings: &PersonalizationSettings) -> io::Result<Plan> {
+    plan::plan_selection(reg, catalog, &settings.basic_selection())
 }
 
-/// Включает суперподробные сведения о системе при загрузке
-pub fn enable_verbose_status(enable: bool) -> io::Result<()> {
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    
-    match hklm.open_subkey_with_flags("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Policies\\System", KEY_WRITE) {
-        Ok(key) => {
-            key.set_value("VerboseStatus", &(if enable { 1u32 } else { 0u32 }))?;
-            
-            println!("{} Подробные сведения о загрузке {}", 
-                if enable { "✅" } else { "❌" }, 
-                if enable { "включены" } else { "отключены" }
-            );
-        }
-        Err(_) => {
-            println!("❌ Недостаточно прав. Требуются права администратора.");
-        }
-    }
-    
-    Ok(())
+/// Планирует изменения для контекстного меню, ничего не меняя
+pub fn plan_context_menu_settings(reg: &dyn RegistryBackend, catalog: &Catalog, settings: &PersonalizationSettings) -> io::Result<Plan> {
+    plan::plan_selection(reg, catalog, &settings.context_menu_selection())
 }
 
-/// Включает старое контекстное меню Windows 10
-pub fn enable_old_context_menu(enable: bool) -> io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    
-    if enable {
-        let (key, _) = hkcu.create_subkey("Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32")?;
-        key.set_value("", &"")?;
-        println!("✅ Старое контекстное меню Windows 10 включено");
-    } else {
-        if let Ok(_) = hkcu.delete_subkey_all("Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}") {
-            println!("❌ Новое контекстное меню Windows 11 восстановлено");
-        }
-    }
-    
-    Ok(())
+/// Применяет основные настройки персонализации
+pub fn apply_basic_personalization(host: &Host, catalog: &Catalog, settings: &PersonalizationSettings) -> io::Result<ApplyReport> {
+    progress!("⚙️  Применение основных настроек персонализации...");
+    Ok(plan_basic_personalization(host.registry, catalog, settings)?.execute(host))
 }
 
-/// Убирает задержку контекстного меню
-pub fn remove_context_delay(enable: bool) -> io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey("Control Panel\\Desktop")?;
-    
-    key.set_value("MenuShowDelay", &(if enable { "0" } else { "400" }))?;
-    
-    println!("{} Задержка контекстного меню {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "убрана" } else { "установлена" }
-    );
-    
-    Ok(())
+/// Применяет настройки контекстного меню
+pub fn apply_context_menu_settings(host: &Host, catalog: &Catalog, settings: &PersonalizationSettings) -> io::Result<ApplyReport> {
+    progress!("⚙️  Применение настроек контекстного меню...");
+    Ok(plan_context_menu_settings(host.registry, catalog, settings)?.execute(host))
 }
 
-/// Применяет основные настройки персонализации
-pub fn apply_basic_personalization(settings: &PersonalizationSettings) -> io::Result<()> {
-    println!("⚙️  Применение основных настроек персонализации...");
-    
-    add_end_task_button(settings.add_end_task_button)?;
-    enable_dark_theme(settings.enable_dark_theme)?;
-    enable_verbose_status(settings.enable_verbose_status)?;
-    
-    println!("✅ Основные настройки персонализации применены.");
-    
-    Ok(())
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::ScriptedRunner;
+    use crate::utils::registry::{MemoryRegistry, RegRoot, RegValue};
 
-/// Применяет настройки контекстного меню
-pub fn apply_context_menu_settings(settings: &PersonalizationSettings) -> io::Result<()> {
-    println!("⚙️  Применение настроек контекстного меню...");
-    
-    enable_old_context_menu(settings.enable_old_context_menu)?;
-    remove_context_delay(settings.remove_context_delay)?;
-    
-    println!("✅ Настройки контекстного меню применены. Перезагрузите проводник для применения изменений.");
-    
-    Ok(())
+    const OLD_CONTEXT_MENU_CLSID: &str = "Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}";
+
+    #[test]
+    fn test_old_context_menu_toggle() {
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new();
+        let host = Host::new(&reg, &runner);
+        let catalog = Catalog::builtin();
+        let server_key = format!("{}\\InprocServer32", OLD_CONTEXT_MENU_CLSID);
+
+        tweaks::apply_by_id(&host, &catalog, "enable_old_context_menu", true).unwrap();
+        assert_eq!(reg.read_value(RegRoot::CurrentUser, &server_key, "").unwrap(), RegValue::String(String::new()));
+        assert!(get_personalization_settings(&reg, &catalog).enable_old_context_menu);
+
+        tweaks::apply_by_id(&host, &catalog, "enable_old_context_menu", false).unwrap();
+        assert!(!reg.key_exists(RegRoot::CurrentUser, OLD_CONTEXT_MENU_CLSID));
+    }
+
+    #[test]
+    fn test_dark_theme() {
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new();
+        let settings = PersonalizationSettings { enable_dark_theme: true, ..Default::default() };
+        apply_basic_personalization(&Host::new(&reg, &runner), &Catalog::builtin(), &settings).unwrap();
+
+        let key = "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize";
+        assert_eq!(reg.read_value(RegRoot::CurrentUser, key, "AppsUseLightTheme").unwrap(), RegValue::Dword(0));
+        assert_eq!(reg.read_value(RegRoot::CurrentUser, key, "SystemUsesLightTheme").unwrap(), RegValue::Dword(0));
+    }
 }
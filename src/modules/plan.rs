@@ -0,0 +1,337 @@
+use std::fmt;
+use std::io;
+use serde::{Deserialize, Serialize};
+use crate::modules::results::{ApplyReport, ApplyResult, ApplyStatus};
+use crate::modules::tweaks::{self, Catalog, Host, Operation, Restart, Selection, StartType, Tweak};
+use crate::utils::command::CommandSpec;
+use crate::utils::registry::{RegRoot, RegValue, RegistryBackend};
+
+/// Запланированное изменение с текущим и новым значением
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PlannedChange {
+    /// Записать значение реестра; `old` — `None`, если значения нет
+    RegistrySet {
+        root: RegRoot,
+        path: String,
+        name: String,
+        old: Option<RegValue>,
+        new: RegValue,
+    },
+    /// Удалить значение реестра
+    RegistryDeleteValue {
+        root: RegRoot,
+        path: String,
+        name: String,
+        old: RegValue,
+    },
+    /// Удалить ключ реестра со всеми подключами
+    RegistryDeleteKey { root: RegRoot, path: String },
+    /// Сменить тип запуска службы; `old` — `None`, если службы нет или тип неизвестен
+    ServiceStartType {
+        name: String,
+        old: Option<StartType>,
+        new: StartType,
+        stop: bool,
+    },
+    /// Запустить команду
+    Command { program: String, args: Vec<String> },
+}
+
+impl PlannedChange {
+    /// Операция, которая выполнит изменение
+    fn to_operation(&self) -> Operation {
+        match self {
+            PlannedChange::RegistrySet { root, path, name, new, .. } => Operation::SetValue {
+                root: *root,
+                path: path.clone(),
+                name: name.clone(),
+                value: new.clone(),
+                only_if_key_exists: false,
+            },
+            PlannedChange::RegistryDeleteValue { root, path, name, .. } => Operation::DeleteValue {
+                root: *root,
+                path: path.clone(),
+                name: name.clone(),
+            },
+            PlannedChange::RegistryDeleteKey { root, path } => Operation::DeleteKey {
+                root: *root,
+                path: path.clone(),
+            },
+            PlannedChange::ServiceStartType { name, new, stop, .. } => Operation::Service {
+                name: name.clone(),
+                start: *new,
+                stop: *stop,
+            },
+            PlannedChange::Command { program, args } => Operation::Run {
+                program: program.clone(),
+                args: args.clone(),
+            },
+        }
+    }
+}
+
+fn value_name(name: &str) -> &str {
+    if name.is_empty() {
+        "(по умолчанию)"
+    } else {
+        name
+    }
+}
+
+impl fmt::Display for PlannedChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlannedChange::RegistrySet { root, path, name, old, new } => {
+                let old = old.as_ref().map_or("(нет)".to_string(), |v| v.to_string());
+                write!(f, "{}\\{}\\{}: {} → {}", root, path, value_name(name), old, new)
+            }
+            PlannedChange::RegistryDeleteValue { root, path, name, old } => {
+                write!(f, "удалить {}\\{}\\{} (сейчас {})", root, path, value_name(name), old)
+            }
+            PlannedChange::RegistryDeleteKey { root, path } => write!(f, "удалить ключ {}\\{}", root, path),
+            PlannedChange::ServiceStartType { name, old, new, stop } => {
+                let old = old.map_or("(нет)", |s| s.sc_name());
+                write!(f, "служба {}: {} → {}", name, old, new.sc_name())?;
+                if *stop {
+                    write!(f, " с остановкой")?;
+                }
+                Ok(())
+            }
+            PlannedChange::Command { program, args } => {
+                write!(f, "команда: {}", CommandSpec::new(program).args(args))
+            }
+        }
+    }
+}
+
+/// Шаг плана: изменение и твик, к которому оно относится
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedStep {
+    pub tweak_id: String,
+    pub title: String,
+    pub enable: bool,
+    pub requires_admin: bool,
+    /// Изменение вступит в силу после перезагрузки
+    #[serde(default)]
+    pub needs_reboot: bool,
+    pub change: PlannedChange,
+}
+
+impl fmt::Display for PlannedStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}", self.tweak_id, self.change)
+    }
+}
+
+/// Список изменений, которые будут сделаны при применении
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub steps: Vec<PlannedStep>,
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.steps.is_empty() {
+            return writeln!(f, "Изменений нет.");
+        }
+        for step in &self.steps {
+            writeln!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+/// Изменение, которое сделает операция, или `None`, если делать нечего
+fn plan_operation(reg: &dyn RegistryBackend, op: &Operation) -> Option<PlannedChange> {
+    match op {
+        Operation::SetValue { root, path, name, value, only_if_key_exists } => {
+            if *only_if_key_exists && !reg.key_exists(*root, path) {
+                return None;
+            }
+            let old = reg.read_value(*root, path, name).ok();
+            if old.as_ref() == Some(value) {
+                return None;
+            }
+            Some(PlannedChange::RegistrySet {
+                root: *root,
+                path: path.clone(),
+                name: name.clone(),
+                old,
+                new: value.clone(),
+            })
+        }
+        Operation::DeleteValue { root, path, name } => {
+            let old = reg.read_value(*root, path, name).ok()?;
+            Some(PlannedChange::RegistryDeleteValue {
+                root: *root,
+                path: path.clone(),
+                name: name.clone(),
+                old,
+            })
+        }
+        Operation::DeleteKey { root, path } => reg.key_exists(*root, path).then(|| PlannedChange::RegistryDeleteKey {
+            root: *root,
+            path: path.clone(),
+        }),
+        Operation::Service { name, start, stop } => {
+            let old = match reg.read_value(RegRoot::LocalMachine, &tweaks::service_key(name), "Start") {
+                Ok(RegValue::Dword(v)) => StartType::from_registry_value(v),
+                _ => None,
+            };
+            if old == Some(*start) {
+                return None;
+            }
+            Some(PlannedChange::ServiceStartType {
+                name: name.clone(),
+                old,
+                new: *start,
+                stop: *stop,
+            })
+        }
+        Operation::Run { program, args } => Some(PlannedChange::Command {
+            program: program.clone(),
+            args: args.clone(),
+        }),
+    }
+}
+
+/// Планирует включение или выключение твика
+pub fn plan_tweak(reg: &dyn RegistryBackend, tweak: &Tweak, enable: bool) -> Vec<PlannedStep> {
+    tweak
+        .operations(enable)
+        .iter()
+        .filter_map(|op| plan_operation(reg, op))
+        .map(|change| PlannedStep {
+            tweak_id: tweak.id.clone(),
+            title: tweak.title.text().to_string(),
+            enable,
+            requires_admin: tweak.requires_admin,
+            needs_reboot: enable && tweak.restart == Restart::Reboot,
+            change,
+        })
+        .collect()
+}
+
+/// Планирует применение выбора твиков
+pub fn plan_selection(reg: &dyn RegistryBackend, catalog: &Catalog, selection: &Selection) -> io::Result<Plan> {
+    if let Some(id) = selection.keys().find(|id| catalog.get(id).is_none()) {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("твик \"{}\" не найден в каталоге", id)));
+    }
+
+    // Шаги идут в порядке каталога, а не в порядке выбора
+    let mut plan = Plan::default();
+    for tweak in &catalog.tweaks {
+        if let Some(enable) = selection.get(&tweak.id) {
+            plan.steps.extend(plan_tweak(reg, tweak, *enable));
+        }
+    }
+    Ok(plan)
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Выполняет план и возвращает результат каждого шага.
+    /// Ошибка одного шага не останавливает остальные.
+    pub fn execute(&self, host: &Host) -> ApplyReport {
+        self.steps
+            .iter()
+            .map(|step| {
+                let message = step.change.to_string();
+                match host.run_operation(&step.tweak_id, step.enable, &step.change.to_operation()) {
+                    Ok(ApplyStatus::Applied) if step.needs_reboot => {
+                        ApplyResult::new(&step.tweak_id, ApplyStatus::NeedsReboot, &message)
+                    }
+                    Ok(status) => ApplyResult::new(&step.tweak_id, status, &message),
+                    Err(e) => ApplyResult::from_error(&step.tweak_id, &message, &e),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+    use crate::utils::registry::MemoryRegistry;
+
+    fn selection(items: &[(&str, bool)]) -> Selection {
+        items.iter().map(|(id, on)| (id.to_string(), *on)).collect()
+    }
+
+    #[test]
+    fn test_plan_shows_old_and_new_values() {
+        let reg = MemoryRegistry::new();
+        let catalog = Catalog::builtin();
+        let key = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced";
+        reg.write_value(RegRoot::CurrentUser, key, "Hidden", &RegValue::Dword(2)).unwrap();
+
+        let plan = plan_selection(&reg, &catalog, &selection(&[("show_hidden_files", true), ("open_this_pc", true)])).unwrap();
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(
+            plan.steps[0].change,
+            PlannedChange::RegistrySet {
+                root: RegRoot::CurrentUser,
+                path: key.to_string(),
+                name: "Hidden".to_string(),
+                old: Some(RegValue::Dword(2)),
+                new: RegValue::Dword(1),
+            }
+        );
+        assert!(plan.to_string().contains("2 (DWORD) → 1 (DWORD)"));
+        assert!(plan.to_string().contains("LaunchTo: (нет) → 1 (DWORD)"));
+    }
+
+    #[test]
+    fn test_plan_does_not_touch_registry() {
+        let reg = MemoryRegistry::new();
+        let plan = plan_selection(&reg, &Catalog::builtin(), &selection(&[("enable_dark_theme", true)])).unwrap();
+        assert_eq!(plan.steps.len(), 2);
+        assert!(!reg.key_exists(RegRoot::CurrentUser, "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"));
+    }
+
+    #[test]
+    fn test_plan_skips_values_already_set() {
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new();
+        let catalog = Catalog::builtin();
+        let sel = selection(&[("enable_dark_theme", true), ("enable_old_context_menu", false)]);
+
+        assert!(!plan_selection(&reg, &catalog, &sel).unwrap().execute(&Host::new(&reg, &runner)).has_failures());
+        assert!(plan_selection(&reg, &catalog, &sel).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_plan_unknown_tweak() {
+        let reg = MemoryRegistry::new();
+        let err = plan_selection(&reg, &Catalog::builtin(), &selection(&[("no_such_tweak", true)])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_plan_services_and_commands() {
+        let reg = MemoryRegistry::new();
+        let catalog = Catalog::builtin();
+        reg.write_value(RegRoot::LocalMachine, &tweaks::service_key("DiagTrack"), "Start", &RegValue::Dword(2)).unwrap();
+
+        let plan = plan_selection(&reg, &catalog, &selection(&[("disable_telemetry", true), ("disable_hibernation", true)])).unwrap();
+        let text = plan.to_string();
+        assert!(text.contains("служба DiagTrack: auto → disabled с остановкой"));
+        assert!(text.contains("команда: powercfg /hibernate off"));
+
+        let runner = ScriptedRunner::new()
+            .expect("powercfg", &["/hibernate", "off"], CommandOutput::ok(""))
+            .expect("sc", &["stop", "DiagTrack"], CommandOutput::ok(""))
+            .expect("sc", &["config", "DiagTrack", "start=", "disabled"], CommandOutput::ok(""))
+            .expect("sc", &["stop", "dmwappushservice"], CommandOutput::ok(""))
+            .expect("sc", &["config", "dmwappushservice", "start=", "disabled"], CommandOutput::ok(""));
+        let report = plan.execute(&Host::new(&reg, &runner));
+        assert!(!report.has_failures());
+        assert_eq!(report.results.len(), 3);
+        assert!(runner.is_exhausted());
+    }
+}
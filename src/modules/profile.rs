@@ -0,0 +1,300 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::modules::plan::{self, Plan};
+use crate::modules::hooks::{self, HookCatalog};
+use crate::modules::package_manager::Backend;
+use crate::modules::packages::{self, Package};
+use crate::modules::results::{ApplyReport, ApplyResult, ApplyStatus};
+use crate::modules::tweaks::{self, Catalog, Host, Selection, TweakState};
+use crate::modules::uwp::{self, UwpApp};
+use crate::progress;
+use crate::utils::filesystem;
+use crate::utils::registry::RegistryBackend;
+
+/// Директория профилей внутри data/ и в директории данных утилиты
+pub const PROFILES_DIR: &str = "profiles";
+
+/// Именованный набор настроек, который применяется за один шаг
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Winget-пакеты для установки: `Id` или `Id@версия` (`@1.2` — точная, `@>=1.2` — не ниже)
+    #[serde(default)]
+    pub winget_packages: Vec<String>,
+    /// UWP-приложения для удаления
+    #[serde(default)]
+    pub uwp_remove: Vec<String>,
+    /// Твики проводника, системы и персонализации: id → включить/выключить
+    #[serde(default)]
+    pub tweaks: Selection,
+    /// Шаблоны из data/config_templates/
+    #[serde(default)]
+    pub config_templates: Vec<String>,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let profile: Self = serde_json::from_str(json).map_err(|e| invalid(format!("ошибка в профиле: {}", e)))?;
+        if profile.name.trim().is_empty() {
+            return Err(invalid("у профиля нет имени".to_string()));
+        }
+        Ok(profile)
+    }
+
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| invalid(e.to_string()))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|e| invalid(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_json()? + "\n")
+    }
+
+    /// Профиль с текущим состоянием твиков на этой машине.
+    /// Твики со своим значением или неприменимые не попадают в профиль.
+    pub fn from_machine(name: &str, reg: &dyn RegistryBackend, catalog: &Catalog) -> Self {
+        let tweaks = tweaks::detect_all(reg, catalog)
+            .into_iter()
+            .filter_map(|(id, state)| match state {
+                TweakState::On => Some((id, true)),
+                TweakState::Off => Some((id, false)),
+                TweakState::Custom | TweakState::NotApplicable => None,
+            })
+            .collect();
+        Self {
+            tweaks,
+            ..Self::new(name)
+        }
+    }
+
+    /// Добавляет пакеты и UWP-приложения так, как они отмечены на экранах.
+    /// Без отметки берётся текущее состояние: установленное остаётся, удалённое — удаляется.
+    /// Пакеты без Id winget в профиль не попадают.
+    pub fn with_selection(
+        mut self,
+        packages: &[Package],
+        package_selection: &Selection,
+        uwp_apps: &[UwpApp],
+        uwp_selection: &Selection,
+    ) -> Self {
+        self.winget_packages = packages
+            .iter()
+            .filter(|p| package_selection.get(&p.id).copied().unwrap_or(p.installed))
+            .filter_map(|p| Some(packages::format_package_spec(p.id_for(Backend::Winget)?, &p.pin)))
+            .collect();
+        self.uwp_remove = uwp_apps
+            .iter()
+            .filter(|a| !uwp_selection.get(&a.name).copied().unwrap_or(a.installed))
+            .map(|a| a.name.clone())
+            .collect();
+        self
+    }
+
+    /// Проверяет, что все твики есть в каталоге
+    pub fn validate(&self, catalog: &Catalog) -> io::Result<()> {
+        match self.tweaks.keys().find(|id| catalog.get(id).is_none()) {
+            Some(id) => Err(invalid(format!("профиль \"{}\": твик \"{}\" не найден в каталоге", self.name, id))),
+            None => Ok(()),
+        }
+    }
+
+    /// Id winget-пакетов без версий
+    pub fn package_ids(&self) -> Vec<String> {
+        self.winget_packages.iter().map(|spec| packages::parse_package_spec(spec).0).collect()
+    }
+
+    /// План изменений твиков профиля
+    pub fn plan(&self, reg: &dyn RegistryBackend, catalog: &Catalog) -> io::Result<Plan> {
+        plan::plan_selection(reg, catalog, &self.tweaks)
+    }
+
+    /// Применяет профиль: твики, пакеты с действиями после установки, удаление UWP
+    /// и шаблоны конфигураций. Ошибка одного пункта не останавливает остальные.
+    pub fn apply(&self, host: &Host, catalog: &Catalog, hooks: &HookCatalog) -> ApplyReport {
+        progress!("📋 Применение профиля \"{}\"...", self.name);
+
+        let mut report = match self.plan(host.registry, catalog) {
+            Ok(plan) => plan.execute(host),
+            Err(e) => [ApplyResult::from_error(&self.name, "план твиков", &e)].into_iter().collect(),
+        };
+        let mut installed = Vec::new();
+        for spec in &self.winget_packages {
+            let (id, pin) = packages::parse_package_spec(spec);
+            let result = ApplyResult::from_outcome(&id, "установка", packages::install_package_pinned(host.runner, &id, &pin));
+            if result.status == ApplyStatus::Applied {
+                installed.push(id);
+            }
+            report.push(result);
+        }
+        report.extend(hooks::run_after_install(host, hooks, &installed));
+        for name in &self.uwp_remove {
+            report.push(ApplyResult::from_result(name, "удаление", uwp::uninstall_uwp(host.runner, name)));
+        }
+        for name in &self.config_templates {
+            report.push(ApplyResult::from_outcome(name, "шаблон конфигурации", hooks::deploy_template(name)));
+        }
+        report
+    }
+}
+
+/// Директории, в которых ищутся профили: data/profiles и профили пользователя
+pub fn profile_dirs() -> Vec<PathBuf> {
+    filesystem::find_data_dir()
+        .map(|dir| dir.join(PROFILES_DIR))
+        .into_iter()
+        .chain(user_profiles_dir())
+        .collect()
+}
+
+/// Директория, куда сохраняются профили пользователя
+pub fn user_profiles_dir() -> Option<PathBuf> {
+    filesystem::get_tool_data_dir().map(|dir| dir.join(PROFILES_DIR))
+}
+
+/// Профили из директорий и файлы, которые не удалось прочитать
+#[derive(Debug, Default)]
+pub struct ProfileList {
+    pub profiles: Vec<(PathBuf, Profile)>,
+    /// Ошибки чтения; вызывающий показывает их сам — интерфейсу stderr не подходит
+    pub errors: Vec<String>,
+}
+
+/// Все профили из указанных директорий, отсортированные по имени.
+/// Файлы с ошибками пропускаются и попадают в `errors`.
+pub fn list_profiles(dirs: &[PathBuf]) -> ProfileList {
+    let mut list = ProfileList::default();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else { continue };
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+                match Profile::load(&path) {
+                    Ok(profile) => list.profiles.push((path, profile)),
+                    Err(e) => list.errors.push(e.to_string()),
+                }
+            }
+        }
+    }
+    list.profiles.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+    list
+}
+
+/// Находит профиль по пути к файлу, имени файла без .json или имени профиля
+pub fn find_profile(dirs: &[PathBuf], name: &str) -> io::Result<Profile> {
+    let path = Path::new(name);
+    if path.is_file() {
+        return Profile::load(path);
+    }
+    let wanted = name.to_lowercase();
+    list_profiles(dirs)
+        .profiles
+        .into_iter()
+        .find(|(path, profile)| {
+            let stem = path.file_stem().map(|s| s.to_string_lossy().to_lowercase());
+            profile.name.to_lowercase() == wanted || stem.as_deref() == Some(wanted.as_str())
+        })
+        .map(|(_, profile)| profile)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("профиль \"{}\" не найден", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+    use crate::utils::registry::{MemoryRegistry, RegRoot, RegValue};
+    use crate::utils::test_support::temp_dir;
+
+    const STANDARD: &str = include_str!("../../data/profiles/standard.json");
+
+    #[test]
+    fn test_standard_profile_matches_catalog() {
+        let profile = Profile::from_json(STANDARD).unwrap();
+        profile.validate(&Catalog::builtin()).unwrap();
+        assert!(!profile.winget_packages.is_empty());
+    }
+
+    #[test]
+    fn test_save_load_and_find() {
+        let dir = temp_dir("profiles");
+        let mut profile = Profile::new("Офис");
+        profile.winget_packages.push("7zip.7zip".to_string());
+        profile.tweaks.insert("show_file_extensions".to_string(), true);
+        profile.save(&dir.join("office.json")).unwrap();
+        fs::write(dir.join("broken.json"), "{").unwrap();
+
+        let dirs = vec![dir.to_path_buf()];
+        let list = list_profiles(&dirs);
+        assert_eq!(list.profiles.len(), 1);
+        assert_eq!(list.errors.len(), 1);
+        assert!(list.errors[0].contains("broken.json"));
+        assert_eq!(find_profile(&dirs, "office").unwrap(), profile);
+        assert_eq!(find_profile(&dirs, "офис").unwrap(), profile);
+        assert_eq!(find_profile(&dirs, "нет").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_from_machine_and_apply() {
+        let source = MemoryRegistry::new();
+        let catalog = Catalog::builtin();
+        let key = "Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Advanced";
+        source.write_value(RegRoot::CurrentUser, key, "HideFileExt", &RegValue::Dword(0)).unwrap();
+        let mut profile = Profile::from_machine("Эталон", &source, &catalog);
+        assert_eq!(profile.tweaks.get("show_file_extensions"), Some(&true));
+        profile.tweaks.retain(|id, _| id == "show_file_extensions");
+        profile.winget_packages.push("7zip.7zip".to_string());
+
+        let target = MemoryRegistry::new();
+        let runner = ScriptedRunner::new().expect(
+            "winget",
+            &["install", "--id", "7zip.7zip", "--silent", "--accept-source-agreements", "--accept-package-agreements"],
+            CommandOutput::ok(""),
+        );
+        assert!(!profile.apply(&Host::new(&target, &runner), &catalog, &HookCatalog::default()).has_failures());
+        assert_eq!(target.read_value(RegRoot::CurrentUser, key, "HideFileExt").unwrap(), RegValue::Dword(0));
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn test_with_selection() {
+        let mut packages: Vec<Package> = packages::curated_packages().into_iter().take(3).collect();
+        packages[0].installed = true;
+        packages[1].pin = packages::VersionPin::Exact("1.0".to_string());
+        packages[2].installed = true;
+        let package_selection = Selection::from([(packages[1].id.clone(), true), (packages[2].id.clone(), false)]);
+        let app = |name: &str, installed| UwpApp { name: name.to_string(), package_full_name: name.to_string(), installed };
+        let apps = vec![app("Microsoft.Paint", true), app("Microsoft.BingNews", true), app("Microsoft.People", false)];
+        let uwp_selection = Selection::from([("Microsoft.BingNews".to_string(), false)]);
+
+        let profile = Profile::new("Мой").with_selection(&packages, &package_selection, &apps, &uwp_selection);
+        assert_eq!(profile.winget_packages, vec![packages[0].id.clone(), format!("{}@1.0", packages[1].id)]);
+        assert_eq!(profile.uwp_remove, vec!["Microsoft.BingNews", "Microsoft.People"]);
+    }
+
+    #[test]
+    fn test_unknown_tweak_rejected() {
+        let profile = Profile::from_json(r#"{ "name": "x", "tweaks": { "no_such": true } }"#).unwrap();
+        assert!(profile.validate(&Catalog::builtin()).is_err());
+        assert!(Profile::from_json(r#"{ "name": "" }"#).is_err());
+    }
+}
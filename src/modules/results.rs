@@ -0,0 +1,234 @@
+use std::fmt;
+use std::io;
+use serde::{Deserialize, Serialize};
+
+/// Чем закончилась операция
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplyStatus {
+    /// Изменение выполнено
+    Applied,
+    /// Делать было нечего
+    Skipped,
+    Failed,
+    /// Не хватило прав: нужен запуск от имени администратора
+    NeedsAdmin,
+    /// Изменение выполнено, но вступит в силу после перезагрузки
+    NeedsReboot,
+}
+
+impl ApplyStatus {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            ApplyStatus::Applied => "✅",
+            ApplyStatus::Skipped => "⏭️ ",
+            ApplyStatus::Failed => "❌",
+            ApplyStatus::NeedsAdmin => "🔒",
+            ApplyStatus::NeedsReboot => "🔁",
+        }
+    }
+
+    /// Изменение не выполнено
+    pub fn is_failure(&self) -> bool {
+        matches!(self, ApplyStatus::Failed | ApplyStatus::NeedsAdmin)
+    }
+}
+
+/// Результат одной операции
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplyResult {
+    /// Твик, пакет или приложение
+    pub id: String,
+    pub status: ApplyStatus,
+    pub message: String,
+    /// Текст ошибки или вывод команды
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+/// Итог выполненной операции: статус и пояснение, если статус не очевиден
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub status: ApplyStatus,
+    pub note: Option<String>,
+}
+
+impl Outcome {
+    pub fn applied() -> Self {
+        Self {
+            status: ApplyStatus::Applied,
+            note: None,
+        }
+    }
+
+    pub fn new(status: ApplyStatus, note: &str) -> Self {
+        Self {
+            status,
+            note: Some(note.to_string()),
+        }
+    }
+
+    /// Итог по ошибке: нехватка прав — `NeedsAdmin`, остальное — `Failed`
+    pub fn from_error(error: &io::Error) -> Self {
+        let status = match error.kind() {
+            io::ErrorKind::PermissionDenied => ApplyStatus::NeedsAdmin,
+            _ => ApplyStatus::Failed,
+        };
+        Self::new(status, &error.to_string())
+    }
+}
+
+impl ApplyResult {
+    pub fn new(id: &str, status: ApplyStatus, message: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            status,
+            message: message.to_string(),
+            details: None,
+        }
+    }
+
+    /// Результат по ошибке: нехватка прав — `NeedsAdmin`, остальное — `Failed`
+    pub fn from_error(id: &str, message: &str, error: &io::Error) -> Self {
+        Self::from_outcome(id, message, Ok(Outcome::from_error(error)))
+    }
+
+    /// Результат действия с итогом: статус из итога, пояснение — в `details`
+    pub fn from_outcome(id: &str, message: &str, result: io::Result<Outcome>) -> Self {
+        match result {
+            Ok(outcome) => Self {
+                details: outcome.note,
+                ..Self::new(id, outcome.status, message)
+            },
+            Err(e) => Self::from_error(id, message, &e),
+        }
+    }
+
+    /// Результат действия: `Applied` при успехе, иначе по ошибке
+    pub fn from_result(id: &str, message: &str, result: io::Result<()>) -> Self {
+        match result {
+            Ok(()) => Self::new(id, ApplyStatus::Applied, message),
+            Err(e) => Self::from_error(id, message, &e),
+        }
+    }
+}
+
+impl fmt::Display for ApplyResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [{}] {}", self.status.icon(), self.id, self.message)?;
+        match self.status {
+            ApplyStatus::NeedsAdmin => write!(f, ": требуются права администратора")?,
+            ApplyStatus::NeedsReboot => write!(f, " (требуется перезагрузка)")?,
+            _ => {}
+        }
+        if let Some(details) = &self.details {
+            write!(f, ": {}", details)?;
+        }
+        Ok(())
+    }
+}
+
+/// Результаты применения по операциям
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplyReport {
+    pub results: Vec<ApplyResult>,
+}
+
+impl ApplyReport {
+    pub fn push(&mut self, result: ApplyResult) {
+        self.results.push(result);
+    }
+
+    pub fn extend(&mut self, other: ApplyReport) {
+        self.results.extend(other.results);
+    }
+
+    pub fn count(&self, status: ApplyStatus) -> usize {
+        self.results.iter().filter(|r| r.status == status).count()
+    }
+
+    /// Есть ли невыполненные операции
+    pub fn has_failures(&self) -> bool {
+        self.results.iter().any(|r| r.status.is_failure())
+    }
+
+    /// Id операций, которые действительно что-то изменили
+    pub fn applied_ids(&self) -> Vec<String> {
+        self.results
+            .iter()
+            .filter(|r| r.status == ApplyStatus::Applied)
+            .map(|r| r.id.clone())
+            .collect()
+    }
+
+    pub fn needs_reboot(&self) -> bool {
+        self.count(ApplyStatus::NeedsReboot) > 0
+    }
+
+    /// Код возврата для скриптов: 0 — всё выполнено, 1 — есть ошибки
+    pub fn exit_code(&self) -> i32 {
+        i32::from(self.has_failures())
+    }
+
+    /// Краткая сводка: "выполнено 3, пропущено 1, ошибок 0, нужны права 0, нужна перезагрузка 1"
+    pub fn summary(&self) -> String {
+        format!(
+            "выполнено {}, пропущено {}, ошибок {}, нужны права {}, нужна перезагрузка {}",
+            self.count(ApplyStatus::Applied),
+            self.count(ApplyStatus::Skipped),
+            self.count(ApplyStatus::Failed),
+            self.count(ApplyStatus::NeedsAdmin),
+            self.count(ApplyStatus::NeedsReboot)
+        )
+    }
+
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl FromIterator<ApplyResult> for ApplyReport {
+    fn from_iter<I: IntoIterator<Item = ApplyResult>>(iter: I) -> Self {
+        Self {
+            results: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl fmt::Display for ApplyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for result in &self.results {
+            writeln!(f, "{}", result)?;
+        }
+        writeln!(f, "Итого: {}", self.summary())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_statuses() {
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "доступ запрещён");
+        let report: ApplyReport = [
+            ApplyResult::new("show_hidden_files", ApplyStatus::Applied, "включено"),
+            ApplyResult::new("disable_hibernation", ApplyStatus::NeedsReboot, "включено"),
+            ApplyResult::from_error("disable_uac", "включено", &denied),
+            ApplyResult::from_result("7zip.7zip", "установка", Err(io::Error::other("код 1"))),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(report.results[2].status, ApplyStatus::NeedsAdmin);
+        assert_eq!(report.results[3].status, ApplyStatus::Failed);
+        assert!(report.has_failures());
+        assert!(report.needs_reboot());
+        assert_eq!(report.exit_code(), 1);
+        assert_eq!(report.results[2].to_string(), "🔒 [disable_uac] включено: требуются права администратора: доступ запрещён");
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["results"][1]["status"], "needs_reboot");
+        assert!(json["results"][0].get("details").is_none());
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::modules::package_manager::{self, Backend, PackageEntry, PackageManager};
+use crate::modules::packages::{self, VersionPin};
+use crate::modules::results::{ApplyStatus, Outcome};
+use crate::modules::winget::ExitCode;
+use crate::progress;
+use crate::utils::command::{CommandOutput, CommandRunner, CommandSpec};
+
+/// Исполняемый файл: на Windows scoop — сценарий-обёртка `scoop.cmd`
+pub const PROGRAM: &str = if cfg!(windows) { "scoop.cmd" } else { "scoop" };
+
+/// Строка из дефисов под заголовком таблицы PowerShell: `----  -------  ------`
+fn is_dash_groups(line: &str) -> bool {
+    let line = line.trim();
+    line.contains('-') && line.chars().all(|c| c == '-' || c == ' ')
+}
+
+/// Разбирает таблицу PowerShell (`Format-Table`), в которой scoop печатает списки.
+/// Столбцы определяются по группам дефисов, поэтому заголовки с пробелами
+/// («Installed Version») не мешают. Строка — словарь «заголовок в нижнем регистре» → значение.
+fn parse_table(output: &str) -> Vec<HashMap<String, String>> {
+    let lines: Vec<&str> = output.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let Some(separator) = (1..lines.len()).find(|&i| is_dash_groups(lines[i])) else {
+        return Vec::new();
+    };
+    let dashes: Vec<char> = lines[separator].chars().collect();
+    let starts: Vec<usize> = (0..dashes.len())
+        .filter(|&i| dashes[i] == '-' && (i == 0 || dashes[i - 1] == ' '))
+        .collect();
+    let cells = |line: &str| -> Vec<String> {
+        let chars: Vec<char> = line.chars().collect();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(n, &start)| {
+                let start = start.min(chars.len());
+                let end = starts.get(n + 1).map_or(chars.len(), |&end| end.min(chars.len()));
+                chars[start..end].iter().collect::<String>().trim().to_string()
+            })
+            .collect()
+    };
+
+    let headers: Vec<String> = cells(lines[separator - 1]).into_iter().map(|h| h.to_lowercase()).collect();
+    lines[separator + 1..]
+        .iter()
+        .take_while(|line| !line.trim().is_empty())
+        .map(|line| headers.iter().cloned().zip(cells(line)).collect())
+        .collect()
+}
+
+/// Строки таблицы в виде пакетов: в scoop Id — это имя приложения
+fn entries(output: &str) -> Vec<PackageEntry> {
+    parse_table(output)
+        .into_iter()
+        .filter_map(|mut row| {
+            let id = row.remove("name").filter(|name| !name.is_empty())?;
+            Some(PackageEntry {
+                name: id.clone(),
+                id,
+                version: row.remove("version").unwrap_or_default(),
+                available: None,
+                source: row.remove("source").filter(|s| !s.is_empty()),
+            })
+        })
+        .collect()
+}
+
+/// Scoop: пакеты ставятся в профиль пользователя, права администратора не нужны
+pub struct Scoop<'a> {
+    runner: &'a dyn CommandRunner,
+}
+
+impl<'a> Scoop<'a> {
+    pub fn new(runner: &'a dyn CommandRunner) -> Self {
+        Self { runner }
+    }
+
+    fn run(&self, args: &[&str]) -> io::Result<(CommandSpec, CommandOutput)> {
+        let spec = package_manager::spec(PROGRAM, args);
+        let output = self.runner.run(&spec)?;
+        Ok((spec, output))
+    }
+}
+
+impl PackageManager for Scoop<'_> {
+    fn backend(&self) -> Backend {
+        Backend::Scoop
+    }
+
+    fn list_installed(&self) -> io::Result<Vec<PackageEntry>> {
+        let (spec, output) = self.run(&["list"])?;
+        Ok(entries(&output.check(&spec)?.stdout))
+    }
+
+    fn search(&self, query: &str) -> io::Result<Vec<PackageEntry>> {
+        let (spec, output) = self.run(&["search", query])?;
+        if output.stdout.contains("No matches found") {
+            return Ok(Vec::new());
+        }
+        Ok(entries(&output.check(&spec)?.stdout))
+    }
+
+    fn install(&self, id: &str, pin: &VersionPin) -> io::Result<Outcome> {
+        progress!("🔄 Установка пакета {} через scoop ({})...", id, pin);
+        let app = match pin {
+            VersionPin::Exact(version) => format!("{}@{}", id, version),
+            _ => id.to_string(),
+        };
+        let (spec, output) = self.run(&["install", &app])?;
+        let markers = [("is already installed", ApplyStatus::Skipped, ExitCode::AlreadyInstalled.message())];
+        let outcome = package_manager::outcome_from_output(&spec, output, &markers)?;
+        packages::report_outcome(id, &outcome, "успешно установлен");
+        Ok(outcome)
+    }
+
+    fn uninstall(&self, id: &str) -> io::Result<Outcome> {
+        progress!("🔄 Удаление пакета {} через scoop...", id);
+        let (spec, output) = self.run(&["uninstall", id])?;
+        let markers = [("isn't installed", ApplyStatus::Skipped, "пакет не установлен")];
+        let outcome = package_manager::outcome_from_output(&spec, output, &markers)?;
+        packages::report_outcome(id, &outcome, "успешно удалён");
+        Ok(outcome)
+    }
+
+    fn upgrade(&self, id: &str) -> io::Result<Outcome> {
+        progress!("🔄 Обновление пакета {} через scoop...", id);
+        let (spec, output) = self.run(&["update", id])?;
+        let note = ExitCode::UpdateNotApplicable.message();
+        let markers = [
+            ("is already up to date", ApplyStatus::Skipped, note),
+            ("latest versions for all apps are installed", ApplyStatus::Skipped, note),
+        ];
+        let outcome = package_manager::outcome_from_output(&spec, output, &markers)?;
+        packages::report_outcome(id, &outcome, "обновлён");
+        Ok(outcome)
+    }
+
+    /// `scoop export` печатает JSON со списком приложений и бакетов
+    fn export(&self, path: &Path) -> io::Result<()> {
+        let (spec, output) = self.run(&["export"])?;
+        fs::write(path, output.check(&spec)?.stdout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::ScriptedRunner;
+
+    const LIST: &str = "Installed apps:
+
+Name       Version          Source  Updated             Info
+----       -------          ------  -------             ----
+7zip       23.01            main    2024-01-05 10:00:00
+git        2.44.0.windows.1 main    2024-03-01 12:00:00
+firefox    124.0.2          extras  2024-04-03 09:30:00 Global install
+";
+
+    #[test]
+    fn test_list_and_install() {
+        let runner = ScriptedRunner::new()
+            .expect(PROGRAM, &["list"], CommandOutput::ok(LIST))
+            .expect(PROGRAM, &["install", "git@2.44.0.windows.1"], CommandOutput::ok("WARN  'git' (2.44.0.windows.1) is already installed."))
+            .expect(PROGRAM, &["uninstall", "neovim"], CommandOutput::with_code(1, "ERROR 'neovim' isn't installed."))
+            .expect(PROGRAM, &["install", "extras/vscode"], CommandOutput::with_code(1, "Couldn't find manifest for 'vscode'."));
+        let scoop = Scoop::new(&runner);
+
+        let installed = scoop.list_installed().unwrap();
+        assert_eq!(installed.len(), 3);
+        assert_eq!(installed[1].version, "2.44.0.windows.1");
+        assert_eq!(installed[2].source.as_deref(), Some("extras"));
+        assert!(installed[2].matches("extras/firefox"));
+
+        let pin = VersionPin::Exact("2.44.0.windows.1".to_string());
+        assert_eq!(scoop.install("git", &pin).unwrap().status, ApplyStatus::Skipped);
+        assert_eq!(scoop.uninstall("neovim").unwrap().status, ApplyStatus::Skipped);
+        assert!(scoop.install("extras/vscode", &VersionPin::Latest).is_err());
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn test_search() {
+        let found = "Results from local buckets...
+
+Name     Version Source Binaries
+----     ------- ------ --------
+vscode   1.88.0  extras
+vscodium 1.88.0  extras codium.exe
+";
+        let runner = ScriptedRunner::new()
+            .expect(PROGRAM, &["search", "vscode"], CommandOutput::ok(found))
+            .expect(PROGRAM, &["search", "nothing"], CommandOutput::ok("WARN  No matches found."));
+        let scoop = Scoop::new(&runner);
+        let entries = scoop.search("vscode").unwrap();
+        assert_eq!(entries.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), vec!["vscode", "vscodium"]);
+        assert!(scoop.search("nothing").unwrap().is_empty());
+    }
+}
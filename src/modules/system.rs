@@ -1,9 +1,12 @@
 use std::io;
-use std::process::Command;
-use winreg::enums::*;
-use winreg::RegKey;
+use crate::modules::plan::{self, Plan};
+use crate::modules::results::ApplyReport;
+use crate::modules::tweaks::{self, Catalog, Category, Host, Selection};
+use crate::progress;
+use crate::utils::registry::RegistryBackend;
 
 /// Системные настройки
+#[derive(Debug, Default)]
 pub struct SystemSettings {
     pub disable_sticky_keys: bool,
     pub enable_clipboard: bool,
@@ -15,198 +18,114 @@ pub struct SystemSettings {
     pub disable_telemetry: bool,
 }
 
-impl Default for SystemSettings {
-    fn default() -> Self {
+impl SystemSettings {
+    /// Значения в виде выбора твиков каталога
+    pub fn to_selection(&self) -> Selection {
+        [
+            ("disable_sticky_keys", self.disable_sticky_keys),
+            ("enable_clipboard", self.enable_clipboard),
+            ("disable_uac", self.disable_uac),
+            ("disable_smartscreen", self.disable_smartscreen),
+            ("disable_hibernation", self.disable_hibernation),
+            ("disable_bing_search", self.disable_bing_search),
+            ("disable_bitlocker", self.disable_bitlocker),
+            ("disable_telemetry", self.disable_telemetry),
+        ]
+        .into_iter()
+        .map(|(id, enable)| (id.to_string(), enable))
+        .collect()
+    }
+
+    /// Настройки из выбора твиков; отсутствующие считаются выключенными
+    pub fn from_selection(selection: &Selection) -> Self {
+        let get = |id: &str| selection.get(id).copied().unwrap_or(false);
         Self {
-            disable_sticky_keys: false,
-            enable_clipboard: false,
-            disable_uac: false,
-            disable_smartscreen: false,
-            disable_hibernation: false,
-            disable_bing_search: false,
-            disable_bitlocker: false,
-            disable_telemetry: false,
+            disable_sticky_keys: get("disable_sticky_keys"),
+            enable_clipboard: get("enable_clipboard"),
+            disable_uac: get("disable_uac"),
+            disable_smartscreen: get("disable_smartscreen"),
+            disable_hibernation: get("disable_hibernation"),
+            disable_bing_search: get("disable_bing_search"),
+            disable_bitlocker: get("disable_bitlocker"),
+            disable_telemetry: get("disable_telemetry"),
         }
     }
 }
 
-/// Отключает залипание клавиш
-pub fn disable_sticky_keys(enable: bool) -> io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey("Control Panel\\Accessibility\\StickyKeys")?;
-    
-    key.set_value("Flags", &(if enable { "506" } else { "510" }))?;
-    
-    println!("{} Залипание клавиш {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "отключено" } else { "включено" }
-    );
-    
-    Ok(())
+/// Получает текущие системные настройки
+pub fn get_system_settings(reg: &dyn RegistryBackend, catalog: &Catalog) -> SystemSettings {
+    SystemSettings::from_selection(&tweaks::current_selection(reg, catalog, Category::System))
 }
 
-/// Включает буфер обмена
-pub fn enable_clipboard(enable: bool) -> io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey("Software\\Microsoft\\Clipboard")?;
-    
-    key.set_value("EnableClipboardHistory", &(if enable { 1u32 } else { 0u32 }))?;
-    
-    println!("{} Буфер обмена {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "включен" } else { "отключен" }
-    );
-    
-    Ok(())
+/// Планирует изменения для системных настроек, ничего не меняя
+pub fn plan_system_settings(reg: &dyn RegistryBackend, catalog: &Catalog, settings: &SystemSettings) -> io::Result<Plan> {
+    plan::plan_selection(reg, catalog, &settings.to_selection())
 }
 
-/// Отключает контроль учётных записей (UAC)
-/// ВНИМАНИЕ: Снижает безопасность системы!
-pub fn disable_uac(enable: bool) -> io::Result<()> {
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    
-    match hklm.open_subkey_with_flags("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Policies\\System", KEY_WRITE) {
-        Ok(key) => {
-            key.set_value("EnableLUA", &(if enable { 0u32 } else { 1u32 }))?;
-            
-            println!("{} UAC {}", 
-                if enable { "⚠️" } else { "✅" }, 
-                if enable { "отключен (требуется перезагрузка)" } else { "включен" }
-            );
-        }
-        Err(_) => {
-            println!("❌ Недостаточно прав для изменения UAC. Требуются права администратора.");
-        }
-    }
-    
-    Ok(())
+/// Применяет все системные настройки
+pub fn apply_system_settings(host: &Host, catalog: &Catalog, settings: &SystemSettings) -> io::Result<ApplyReport> {
+    progress!("⚙️  Применение системных настроек...");
+    Ok(plan_system_settings(host.registry, catalog, settings)?.execute(host))
 }
 
-/// Отключает Smart Screen
-pub fn disable_smartscreen(enable: bool) -> io::Result<()> {
-    Command::new("powershell")
-        .args(&[
-            "-Command",
-            &format!(
-                "Set-ItemProperty -Path 'HKLM:\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Explorer' -Name 'SmartScreenEnabled' -Value '{}'",
-                if enable { "Off" } else { "Warn" }
-            )
-        ])
-        .output()?;
-    
-    println!("{} Smart Screen {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "отключен" } else { "включен" }
-    );
-    
-    Ok(())
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+    use crate::utils::registry::{MemoryRegistry, RegRoot, RegValue};
 
-/// Отключает гибернацию
-pub fn disable_hibernation(enable: bool) -> io::Result<()> {
-    let command = if enable { "off" } else { "on" };
-    
-    let status = Command::new("powercfg")
-        .args(&["/hibernate", command])
-        .status()?;
-    
-    if status.success() {
-        println!("{} Гибернация {}", 
-            if enable { "✅" } else { "❌" }, 
-            if enable { "отключена" } else { "включена" }
+    const POLICIES_SYSTEM_KEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Policies\\System";
+
+    #[test]
+    fn test_disable_sticky_keys() {
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new();
+        tweaks::apply_by_id(&Host::new(&reg, &runner), &Catalog::builtin(), "disable_sticky_keys", true).unwrap();
+        assert_eq!(
+            reg.read_value(RegRoot::CurrentUser, "Control Panel\\Accessibility\\StickyKeys", "Flags").unwrap(),
+            RegValue::String("506".to_string())
         );
-    } else {
-        println!("❌ Не удалось изменить настройку гибернации.");
     }
-    
-    Ok(())
-}
 
-/// Отключает онлайн поиск через Bing в Windows Search
-pub fn disable_bing_search(enable: bool) -> io::Result<()> {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let (key, _) = hkcu.create_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Search")?;
-    
-    key.set_value("BingSearchEnabled", &(if enable { 0u32 } else { 1u32 }))?;
-    key.set_value("CortanaConsent", &(if enable { 0u32 } else { 1u32 }))?;
-    
-    println!("{} Поиск Bing {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "отключен" } else { "включен" }
-    );
-    
-    Ok(())
-}
+    #[test]
+    fn test_disable_uac_without_rights() {
+        let reg = MemoryRegistry::new();
+        reg.deny_writes(RegRoot::LocalMachine, POLICIES_SYSTEM_KEY);
+        let runner = ScriptedRunner::new();
 
-/// Отключает автоматическое шифрование BitLocker
-pub fn disable_bitlocker_auto(enable: bool) -> io::Result<()> {
-    Command::new("powershell")
-        .args(&[
-            "-Command",
-            &format!(
-                "Set-ItemProperty -Path 'HKLM:\\SYSTEM\\CurrentControlSet\\Control\\BitLocker' -Name 'PreventDeviceEncryption' -Value {}",
-                if enable { "1" } else { "0" }
-            )
-        ])
-        .output()?;
-    
-    println!("{} Авто-шифрование BitLocker {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "отключено" } else { "включено" }
-    );
-    
-    Ok(())
-}
+        let result = tweaks::apply_by_id(&Host::new(&reg, &runner), &Catalog::builtin(), "disable_uac", true).unwrap();
+        assert_eq!(result.status, crate::modules::results::ApplyStatus::NeedsAdmin);
+        assert!(reg.read_value(RegRoot::LocalMachine, POLICIES_SYSTEM_KEY, "EnableLUA").is_err());
+    }
+
+    #[test]
+    fn test_telemetry_policy_only_when_key_exists() {
+        let key = "SOFTWARE\\Policies\\Microsoft\\Windows\\DataCollection";
+        let script = || {
+            ScriptedRunner::new()
+                .expect("sc", &["stop", "DiagTrack"], CommandOutput::ok(""))
+                .expect("sc", &["config", "DiagTrack", "start=", "disabled"], CommandOutput::ok(""))
+                .expect("sc", &["stop", "dmwappushservice"], CommandOutput::with_code(1062, ""))
+                .expect("sc", &["config", "dmwappushservice", "start=", "disabled"], CommandOutput::ok(""))
+        };
+        let reg = MemoryRegistry::new();
+        let catalog = Catalog::builtin();
 
-/// Отключает телеметрию Windows
-pub fn disable_telemetry(enable: bool) -> io::Result<()> {
-    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
-    
-    // Отключаем DiagTrack службу
-    Command::new("sc")
-        .args(&["stop", "DiagTrack"])
-        .output()?;
-    
-    Command::new("sc")
-        .args(&["config", "DiagTrack", "start=", if enable { "disabled" } else { "auto" }])
-        .output()?;
-    
-    // Отключаем dmwappushservice
-    Command::new("sc")
-        .args(&["stop", "dmwappushservice"])
-        .output()?;
-    
-    Command::new("sc")
-        .args(&["config", "dmwappushservice", "start=", if enable { "disabled" } else { "auto" }])
-        .output()?;
-    
-    // Изменяем настройки в реестре
-    if let Ok(key) = hklm.open_subkey_with_flags("SOFTWARE\\Policies\\Microsoft\\Windows\\DataCollection", KEY_WRITE) {
-        key.set_value("AllowTelemetry", &(if enable { 0u32 } else { 1u32 }))?;
+        let runner = script();
+        tweaks::apply_by_id(&Host::new(&reg, &runner), &catalog, "disable_telemetry", true).unwrap();
+        assert!(runner.is_exhausted());
+        assert!(!reg.key_exists(RegRoot::LocalMachine, key));
+
+        reg.create_key(RegRoot::LocalMachine, key).unwrap();
+        tweaks::apply_by_id(&Host::new(&reg, &script()), &catalog, "disable_telemetry", true).unwrap();
+        assert_eq!(reg.read_value(RegRoot::LocalMachine, key, "AllowTelemetry").unwrap(), RegValue::Dword(0));
     }
-    
-    println!("{} Телеметрия {}", 
-        if enable { "✅" } else { "❌" }, 
-        if enable { "отключена" } else { "включена" }
-    );
-    
-    Ok(())
-}
 
-/// Применяет все системные настройки
-pub fn apply_system_settings(settings: &SystemSettings) -> io::Result<()> {
-    println!("⚙️  Применение системных настроек...");
-    
-    disable_sticky_keys(settings.disable_sticky_keys)?;
-    enable_clipboard(settings.enable_clipboard)?;
-    disable_uac(settings.disable_uac)?;
-    disable_smartscreen(settings.disable_smartscreen)?;
-    disable_hibernation(settings.disable_hibernation)?;
-    disable_bing_search(settings.disable_bing_search)?;
-    disable_bitlocker_auto(settings.disable_bitlocker)?;
-    disable_telemetry(settings.disable_telemetry)?;
-    
-    println!("✅ Системные настройки применены.");
-    
-    Ok(())
+    #[test]
+    fn test_disable_hibernation() {
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new().expect("powercfg", &["/hibernate", "off"], CommandOutput::ok(""));
+        tweaks::apply_by_id(&Host::new(&reg, &runner), &Catalog::builtin(), "disable_hibernation", true).unwrap();
+        assert!(runner.is_exhausted());
+    }
 }
@@ -0,0 +1,551 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::modules::journal::{self, Journal};
+use crate::modules::results::{ApplyResult, ApplyStatus};
+use crate::utils::command::{CommandRunner, CommandSpec};
+use crate::utils::filesystem;
+use crate::utils::registry::{RegRoot, RegValue, RegistryBackend};
+
+/// Встроенный каталог, используется если в data/ нет своего tweaks.json
+const BUILTIN_CATALOG: &str = include_str!("../../data/tweaks.json");
+
+/// Выбранные значения твиков: id -> включить/выключить
+pub type Selection = BTreeMap<String, bool>;
+
+/// Системные зависимости, через которые применяются твики
+#[derive(Clone, Copy)]
+pub struct Host<'a> {
+    pub registry: &'a dyn RegistryBackend,
+    pub runner: &'a dyn CommandRunner,
+    /// Журнал отмены; без него изменения не записываются
+    pub journal: Option<&'a Journal>,
+    /// Каталог, по проверкам которого журнал запоминает состояние твика до команды
+    pub catalog: Option<&'a Catalog>,
+}
+
+impl<'a> Host<'a> {
+    pub fn new(registry: &'a dyn RegistryBackend, runner: &'a dyn CommandRunner) -> Self {
+        Self { registry, runner, journal: None, catalog: None }
+    }
+
+    /// Записывает все выполненные изменения в журнал
+    pub fn with_journal(mut self, journal: &'a Journal, catalog: &'a Catalog) -> Self {
+        self.journal = Some(journal);
+        self.catalog = Some(catalog);
+        self
+    }
+
+    /// Выполняет операцию твика и, если есть журнал, записывает прежнее состояние
+    pub fn run_operation(&self, tweak_id: &str, enable: bool, op: &Operation) -> io::Result<ApplyStatus> {
+        let journal = match self.journal {
+            Some(journal) => journal,
+            None => return execute_operation(self, op),
+        };
+        let tweak = self.catalog.and_then(|catalog| catalog.get(tweak_id));
+        let change = journal::capture(self, tweak, op);
+        let status = execute_operation(self, op)?;
+        if let (ApplyStatus::Applied, Some(change)) = (status, change) {
+            journal.record(tweak_id, enable, change)?;
+        }
+        Ok(status)
+    }
+}
+
+/// Текст на нескольких языках
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Localized {
+    pub ru: String,
+    #[serde(default)]
+    pub en: String,
+}
+
+impl Localized {
+    /// Текст на указанном языке ("ru", "en"), иначе на русском
+    pub fn get(&self, lang: &str) -> &str {
+        match lang {
+            "en" if !self.en.is_empty() => &self.en,
+            _ => &self.ru,
+        }
+    }
+
+    /// Текст на языке интерфейса
+    pub fn text(&self) -> &str {
+        self.get("ru")
+    }
+}
+
+/// Раздел меню, к которому относится твик
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Explorer,
+    System,
+    PersonalizationBasic,
+    PersonalizationContext,
+}
+
+/// Что нужно перезапустить, чтобы изменение вступило в силу
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Restart {
+    #[default]
+    None,
+    Explorer,
+    Reboot,
+}
+
+/// Тип запуска службы
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartType {
+    Auto,
+    Demand,
+    Disabled,
+}
+
+impl StartType {
+    /// Значение для `sc config <служба> start= ...`
+    pub fn sc_name(&self) -> &'static str {
+        match self {
+            StartType::Auto => "auto",
+            StartType::Demand => "demand",
+            StartType::Disabled => "disabled",
+        }
+    }
+
+    /// Значение `Start` в ключе службы
+    pub fn registry_value(&self) -> u32 {
+        match self {
+            StartType::Auto => 2,
+            StartType::Demand => 3,
+            StartType::Disabled => 4,
+        }
+    }
+
+    pub fn from_registry_value(value: u32) -> Option<Self> {
+        match value {
+            2 => Some(StartType::Auto),
+            3 => Some(StartType::Demand),
+            4 => Some(StartType::Disabled),
+            _ => None,
+        }
+    }
+}
+
+/// Ключ реестра со свойствами службы
+pub fn service_key(name: &str) -> String {
+    format!("SYSTEM\\CurrentControlSet\\Services\\{}", name)
+}
+
+/// Текущее состояние твика на машине
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TweakState {
+    On,
+    Off,
+    /// Значения не совпадают ни с "вкл", ни с "выкл"
+    Custom,
+    /// Проверить нечего: ключей или служб нет на этой машине
+    NotApplicable,
+}
+
+impl TweakState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TweakState::On => "включено",
+            TweakState::Off => "выключено",
+            TweakState::Custom => "своё значение",
+            TweakState::NotApplicable => "неприменимо",
+        }
+    }
+
+    /// Короткая подпись для колонки списка
+    pub fn short_label(&self) -> &'static str {
+        match self {
+            TweakState::On => "вкл",
+            TweakState::Off => "выкл",
+            TweakState::Custom => "своё",
+            TweakState::NotApplicable => "—",
+        }
+    }
+}
+
+/// Одна операция твика
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    /// Записать значение реестра
+    SetValue {
+        root: RegRoot,
+        path: String,
+        name: String,
+        value: RegValue,
+        /// Писать только если ключ уже существует
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        only_if_key_exists: bool,
+    },
+    /// Удалить значение реестра
+    DeleteValue { root: RegRoot, path: String, name: String },
+    /// Удалить ключ реестра со всеми подключами
+    DeleteKey { root: RegRoot, path: String },
+    /// Изменить тип запуска службы через `sc`
+    Service {
+        name: String,
+        start: StartType,
+        #[serde(default)]
+        stop: bool,
+    },
+    /// Запустить программу
+    Run { program: String, args: Vec<String> },
+}
+
+/// Описание твика из каталога
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tweak {
+    pub id: String,
+    pub category: Category,
+    pub title: Localized,
+    #[serde(default)]
+    pub description: Localized,
+    #[serde(default)]
+    pub requires_admin: bool,
+    #[serde(default)]
+    pub restart: Restart,
+    pub on: Vec<Operation>,
+    pub off: Vec<Operation>,
+    /// Проверки состояния, если операции `on`/`off` нельзя проверить напрямую
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detect: Option<Detect>,
+}
+
+/// Операции, по которым определяется состояние твика
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Detect {
+    pub on: Vec<Operation>,
+    pub off: Vec<Operation>,
+}
+
+impl Tweak {
+    /// Операции для включения или выключения
+    pub fn operations(&self, enable: bool) -> &[Operation] {
+        if enable {
+            &self.on
+        } else {
+            &self.off
+        }
+    }
+
+    /// Операции, по которым проверяется состояние
+    pub fn detect_operations(&self, enable: bool) -> &[Operation] {
+        match &self.detect {
+            Some(detect) if enable => &detect.on,
+            Some(detect) => &detect.off,
+            None => self.operations(enable),
+        }
+    }
+}
+
+/// Каталог твиков
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Catalog {
+    #[serde(default = "default_version")]
+    pub version: u32,
+    pub tweaks: Vec<Tweak>,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+impl Catalog {
+    /// Разбирает каталог из JSON и проверяет его
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let catalog: Catalog = serde_json::from_str(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("каталог твиков: {}", e)))?;
+        catalog.validate()?;
+        Ok(catalog)
+    }
+
+    /// Каталог, встроенный в программу
+    pub fn builtin() -> Self {
+        Self::from_json(BUILTIN_CATALOG).expect("встроенный каталог твиков должен быть корректным")
+    }
+
+    /// Загружает каталог из файла
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Загружает data/tweaks.json, если он есть, иначе встроенный каталог
+    pub fn load_default() -> io::Result<Self> {
+        match filesystem::find_data_dir().map(|dir| dir.join("tweaks.json")) {
+            Some(path) if path.is_file() => Self::load(&path),
+            _ => Ok(Self::builtin()),
+        }
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        let mut ids = HashSet::new();
+        for tweak in &self.tweaks {
+            if !ids.insert(tweak.id.as_str()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("каталог твиков: повторяющийся id \"{}\"", tweak.id),
+                ));
+            }
+            if tweak.on.is_empty() && tweak.off.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("каталог твиков: у \"{}\" нет операций", tweak.id),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Находит твик по id
+    pub fn get(&self, id: &str) -> Option<&Tweak> {
+        self.tweaks.iter().find(|t| t.id == id)
+    }
+
+    /// Твики раздела в порядке каталога
+    pub fn by_category(&self, category: Category) -> impl Iterator<Item = &Tweak> {
+        self.tweaks.iter().filter(move |t| t.category == category)
+    }
+}
+
+/// Проверяет, выполнено ли уже действие операции.
+/// `None` — проверить нельзя (команда, отсутствующая служба или ключ).
+pub fn operation_matches(reg: &dyn RegistryBackend, op: &Operation) -> Option<bool> {
+    match op {
+        Operation::SetValue { root, path, name, value, only_if_key_exists } => {
+            if *only_if_key_exists && !reg.key_exists(*root, path) {
+                return None;
+            }
+            Some(reg.read_value(*root, path, name).ok().as_ref() == Some(value))
+        }
+        Operation::DeleteValue { root, path, name } => Some(reg.read_value(*root, path, name).is_err()),
+        Operation::DeleteKey { root, path } => Some(!reg.key_exists(*root, path)),
+        Operation::Service { name, start, .. } => {
+            let key = service_key(name);
+            if !reg.key_exists(RegRoot::LocalMachine, &key) {
+                return None;
+            }
+            let current = match reg.read_value(RegRoot::LocalMachine, &key, "Start") {
+                Ok(RegValue::Dword(v)) => StartType::from_registry_value(v),
+                _ => None,
+            };
+            Some(current == Some(*start))
+        }
+        Operation::Run { .. } => None,
+    }
+}
+
+/// Совпадают ли все проверяемые операции. `None` — проверять нечего.
+fn operations_match(reg: &dyn RegistryBackend, ops: &[Operation]) -> Option<bool> {
+    let results: Vec<bool> = ops.iter().filter_map(|op| operation_matches(reg, op)).collect();
+    if results.is_empty() {
+        None
+    } else {
+        Some(results.iter().all(|m| *m))
+    }
+}
+
+/// Определяет текущее состояние твика по тем же ключам и службам,
+/// которые он меняет
+pub fn detect_state(reg: &dyn RegistryBackend, tweak: &Tweak) -> TweakState {
+    let on = operations_match(reg, tweak.detect_operations(true));
+    let off = operations_match(reg, tweak.detect_operations(false));
+    match (on, off) {
+        (Some(true), _) => TweakState::On,
+        (_, Some(true)) => TweakState::Off,
+        (None, None) => TweakState::NotApplicable,
+        _ => TweakState::Custom,
+    }
+}
+
+/// Состояние всех твиков каталога
+pub fn detect_all(reg: &dyn RegistryBackend, catalog: &Catalog) -> BTreeMap<String, TweakState> {
+    catalog
+        .tweaks
+        .iter()
+        .map(|t| (t.id.clone(), detect_state(reg, t)))
+        .collect()
+}
+
+/// Текущий выбор для раздела: включённые твики отмечены
+pub fn current_selection(reg: &dyn RegistryBackend, catalog: &Catalog, category: Category) -> Selection {
+    catalog
+        .by_category(category)
+        .map(|t| (t.id.clone(), detect_state(reg, t) == TweakState::On))
+        .collect()
+}
+
+fn unknown_tweak(id: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("твик \"{}\" не найден в каталоге", id))
+}
+
+/// Удаление отсутствующего — не ошибка, а пропуск
+fn skip_not_found(result: io::Result<()>) -> io::Result<ApplyStatus> {
+    match result {
+        Ok(()) => Ok(ApplyStatus::Applied),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(ApplyStatus::Skipped),
+        Err(e) => Err(e),
+    }
+}
+
+/// Запущена ли служба: `sc query` сообщает `STATE : 4 RUNNING`.
+/// Службы нет — не запущена.
+pub fn service_running(runner: &dyn CommandRunner, name: &str) -> io::Result<bool> {
+    let output = runner.run(&CommandSpec::new("sc").args(["query", name]))?;
+    Ok(output.success() && output.stdout.lines().any(|line| line.contains("STATE") && line.contains("RUNNING")))
+}
+
+/// Выполняет одну операцию; возвращает `Applied` или `Skipped`, если делать было нечего
+pub fn execute_operation(host: &Host, op: &Operation) -> io::Result<ApplyStatus> {
+    match op {
+        Operation::SetValue { root, path, name, value, only_if_key_exists } => {
+            if *only_if_key_exists && !host.registry.key_exists(*root, path) {
+                return Ok(ApplyStatus::Skipped);
+            }
+            host.registry.write_value(*root, path, name, value)?;
+        }
+        Operation::DeleteValue { root, path, name } => return skip_not_found(host.registry.delete_value(*root, path, name)),
+        Operation::DeleteKey { root, path } => return skip_not_found(host.registry.delete_key(*root, path)),
+        Operation::Service { name, start, stop } => {
+            // Код возврата sc stop не проверяется: служба может быть уже остановлена
+            if *stop {
+                host.runner.run(&CommandSpec::new("sc").args(["stop", name]))?;
+            }
+            let spec = CommandSpec::new("sc").args(["config", name, "start=", start.sc_name()]);
+            host.runner.run(&spec)?.check(&spec)?;
+        }
+        Operation::Run { program, args } => {
+            let spec = CommandSpec::new(program).args(args);
+            host.runner.run(&spec)?.check(&spec)?;
+        }
+    }
+    Ok(ApplyStatus::Applied)
+}
+
+/// Включает или выключает твик. Первая неудачная операция прерывает твик.
+pub fn apply_tweak(host: &Host, tweak: &Tweak, enable: bool) -> ApplyResult {
+    let message = format!("{}: {}", tweak.title.text(), if enable { "включено" } else { "выключено" });
+    let mut applied = false;
+    for op in tweak.operations(enable) {
+        match host.run_operation(&tweak.id, enable, op) {
+            Ok(status) => applied |= status == ApplyStatus::Applied,
+            Err(e) => return ApplyResult::from_error(&tweak.id, &message, &e),
+        }
+    }
+
+    let status = match tweak.restart {
+        _ if !applied => ApplyStatus::Skipped,
+        Restart::Reboot if enable => ApplyStatus::NeedsReboot,
+        _ => ApplyStatus::Applied,
+    };
+    ApplyResult::new(&tweak.id, status, &message)
+}
+
+/// Включает или выключает твик по id
+pub fn apply_by_id(host: &Host, catalog: &Catalog, id: &str, enable: bool) -> io::Result<ApplyResult> {
+    let tweak = catalog.get(id).ok_or_else(|| unknown_tweak(id))?;
+    Ok(apply_tweak(host, tweak, enable))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+    use crate::utils::registry::MemoryRegistry;
+
+    #[test]
+    fn test_builtin_catalog() {
+        let catalog = Catalog::builtin();
+        assert_eq!(catalog.by_category(Category::Explorer).count(), 4);
+        assert_eq!(catalog.by_category(Category::System).count(), 8);
+        assert_eq!(catalog.by_category(Category::PersonalizationBasic).count(), 3);
+        assert_eq!(catalog.by_category(Category::PersonalizationContext).count(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_ids_rejected() {
+        let json = r#"{ "tweaks": [
+            { "id": "a", "category": "system", "title": { "ru": "A" }, "on": [], "off": [{ "op": "run", "program": "x", "args": [] }] },
+            { "id": "a", "category": "system", "title": { "ru": "A" }, "on": [], "off": [{ "op": "run", "program": "x", "args": [] }] }
+        ] }"#;
+        assert_eq!(Catalog::from_json(json).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_apply_run_failure_is_error() {
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new().expect("powercfg", &["/hibernate", "off"], CommandOutput::with_code(1, ""));
+        let host = Host::new(&reg, &runner);
+
+        let result = apply_by_id(&host, &Catalog::builtin(), "disable_hibernation", true).unwrap();
+        assert_eq!(result.status, ApplyStatus::Failed);
+        assert!(result.details.unwrap().contains("powercfg /hibernate off"));
+    }
+
+    #[test]
+    fn test_apply_without_rights_needs_admin() {
+        let reg = MemoryRegistry::new();
+        reg.deny_writes(RegRoot::LocalMachine, "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Policies\\System");
+        let runner = ScriptedRunner::new();
+        let host = Host::new(&reg, &runner);
+
+        let result = apply_by_id(&host, &Catalog::builtin(), "disable_uac", true).unwrap();
+        assert_eq!(result.status, ApplyStatus::NeedsAdmin);
+        assert!(!reg.key_exists(RegRoot::LocalMachine, "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Policies\\System"));
+    }
+
+    #[test]
+    fn test_detect_state() {
+        let reg = MemoryRegistry::new();
+        let runner = ScriptedRunner::new();
+        let host = Host::new(&reg, &runner);
+        let catalog = Catalog::builtin();
+        let tweak = catalog.get("enable_dark_theme").unwrap();
+
+        assert_eq!(detect_state(&reg, tweak), TweakState::Custom);
+        assert_eq!(apply_tweak(&host, tweak, true).status, ApplyStatus::Applied);
+        assert_eq!(detect_state(&reg, tweak), TweakState::On);
+        apply_tweak(&host, tweak, false);
+        assert_eq!(detect_state(&reg, tweak), TweakState::Off);
+
+        let key = "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize";
+        reg.write_value(RegRoot::CurrentUser, key, "AppsUseLightTheme", &RegValue::Dword(0)).unwrap();
+        assert_eq!(detect_state(&reg, tweak), TweakState::Custom);
+    }
+
+    #[test]
+    fn test_detect_services_and_hibernation() {
+        let reg = MemoryRegistry::new();
+        let catalog = Catalog::builtin();
+        let telemetry = catalog.get("disable_telemetry").unwrap();
+        let hibernation = catalog.get("disable_hibernation").unwrap();
+
+        assert_eq!(detect_state(&reg, telemetry), TweakState::NotApplicable);
+
+        reg.write_value(RegRoot::LocalMachine, &service_key("DiagTrack"), "Start", &RegValue::Dword(4)).unwrap();
+        assert_eq!(detect_state(&reg, telemetry), TweakState::On);
+        reg.write_value(RegRoot::LocalMachine, &service_key("DiagTrack"), "Start", &RegValue::Dword(3)).unwrap();
+        assert_eq!(detect_state(&reg, telemetry), TweakState::Custom);
+
+        let power_key = "SYSTEM\\CurrentControlSet\\Control\\Power";
+        reg.write_value(RegRoot::LocalMachine, power_key, "HibernateEnabled", &RegValue::Dword(1)).unwrap();
+        assert_eq!(detect_state(&reg, hibernation), TweakState::Off);
+    }
+
+    #[test]
+    fn test_detect_deleted_key() {
+        let reg = MemoryRegistry::new();
+        let catalog = Catalog::builtin();
+        assert_eq!(detect_state(&reg, catalog.get("enable_old_context_menu").unwrap()), TweakState::Off);
+        assert_eq!(detect_state(&reg, catalog.get("remove_shortcut_suffix").unwrap()), TweakState::Off);
+    }
+}
@@ -1,5 +1,9 @@
-use std::process::Command;
 use std::io;
+use serde::Deserialize;
+use crate::modules::results::{ApplyReport, ApplyResult};
+use crate::modules::tweaks::Selection;
+use crate::progress;
+use crate::utils::command::{CommandRunner, CommandSpec};
 
 /// Структура для UWP приложения
 #[derive(Debug, Clone)]
@@ -9,104 +13,132 @@ pub struct UwpApp {
     pub installed: bool,
 }
 
-/// Получает список всех UWP приложений (установленных и доступных)
-pub fn get_uwp_apps() -> io::Result<Vec<UwpApp>> {
-    let _output = Command::new("powershell")
-        .args(&[
-            "-Command",
-            "Get-AppxPackage | Select-Object Name, PackageFullName | ConvertTo-Json"
-        ])
-        .output()?;
-    
-    // Простой парсинг вывода - можно расширить
-    let mut apps = Vec::new();
-    
-    // Добавляем некоторые стандартные UWP приложения
-    apps.push(UwpApp {
-        name: "Microsoft.WindowsTerminal".to_string(),
-        package_full_name: "Microsoft.WindowsTerminal_Win10_x64".to_string(),
-        installed: is_uwp_installed("Microsoft.WindowsTerminal")?,
-    });
-    
-    apps.push(UwpApp {
-        name: "Microsoft.WindowsCalculator".to_string(),
-        package_full_name: "Microsoft.WindowsCalculator_8wekyb3d8bbwe".to_string(),
-        installed: is_uwp_installed("Microsoft.WindowsCalculator")?,
-    });
-    
-    apps.push(UwpApp {
-        name: "Microsoft.WindowsNotepad".to_string(),
-        package_full_name: "Microsoft.WindowsNotepad_8wekyb3d8bbwe".to_string(),
-        installed: is_uwp_installed("Microsoft.WindowsNotepad")?,
-    });
-    
-    apps.push(UwpApp {
-        name: "Microsoft.Paint".to_string(),
-        package_full_name: "Microsoft.Paint_8wekyb3d8bbwe".to_string(),
-        installed: is_uwp_installed("Microsoft.Paint")?,
-    });
-    
-    apps.push(UwpApp {
-        name: "Microsoft.ScreenSketch".to_string(),
-        package_full_name: "Microsoft.ScreenSketch_8wekyb3d8bbwe".to_string(),
-        installed: is_uwp_installed("Microsoft.ScreenSketch")?,
-    });
+/// Стандартные UWP приложения, которые показываются в списке
+const STANDARD_APPS: &[(&str, &str)] = &[
+    ("Microsoft.WindowsTerminal", "Microsoft.WindowsTerminal_Win10_x64"),
+    ("Microsoft.WindowsCalculator", "Microsoft.WindowsCalculator_8wekyb3d8bbwe"),
+    ("Microsoft.WindowsNotepad", "Microsoft.WindowsNotepad_8wekyb3d8bbwe"),
+    ("Microsoft.Paint", "Microsoft.Paint_8wekyb3d8bbwe"),
+    ("Microsoft.ScreenSketch", "Microsoft.ScreenSketch_8wekyb3d8bbwe"),
+];
+
+/// Запись из вывода `Get-AppxPackage | ConvertTo-Json`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AppxPackage {
+    name: String,
+    package_full_name: String,
+}
+
+/// Разбирает JSON из `Get-AppxPackage | Select-Object Name, PackageFullName | ConvertTo-Json`.
+/// PowerShell выводит один объект вместо массива, если пакет единственный.
+fn parse_appx_json(json: &str) -> io::Result<Vec<AppxPackage>> {
+    let json = json.trim();
+    if json.is_empty() {
+        return Ok(Vec::new());
+    }
+    let result = if json.starts_with('[') {
+        serde_json::from_str(json)
+    } else {
+        serde_json::from_str(json).map(|p| vec![p])
+    };
+    result.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Получает список установленных UWP пакетов: (Name, PackageFullName)
+pub fn get_installed_uwp(runner: &dyn CommandRunner) -> io::Result<Vec<(String, String)>> {
+    let spec = CommandSpec::powershell("Get-AppxPackage | Select-Object Name, PackageFullName | ConvertTo-Json");
+    let output = runner.run(&spec)?.check(&spec)?;
+
+    Ok(parse_appx_json(&output.stdout)?
+        .into_iter()
+        .map(|p| (p.name, p.package_full_name))
+        .collect())
+}
+
+/// Получает список всех UWP приложений (установленных и доступных):
+/// стандартные приложения и нежелательные из `BLOATWARE_APPS`
+pub fn get_uwp_apps(runner: &dyn CommandRunner) -> io::Result<Vec<UwpApp>> {
+    let installed = get_installed_uwp(runner)?;
+
+    let apps = STANDARD_APPS
+        .iter()
+        .copied()
+        .chain(BLOATWARE_APPS.iter().map(|(name, _)| (*name, *name)))
+        .map(|(name, default_full_name)| {
+            let found = installed.iter().find(|(n, _)| n.eq_ignore_ascii_case(name));
+            UwpApp {
+                name: name.to_string(),
+                package_full_name: found
+                    .map(|(_, full)| full.clone())
+                    .unwrap_or_else(|| default_full_name.to_string()),
+                installed: found.is_some(),
+            }
+        })
+        .collect();
 
     Ok(apps)
 }
 
+/// Аргумент `-Name` для Get-AppxPackage. Имя попадает в скрипт PowerShell,
+/// поэтому допускаются только символы имён пакетов AppX: буквы, цифры, `.`, `_` и `-`
+fn name_arg(app_name: &str) -> io::Result<String> {
+    let valid = !app_name.is_empty() && app_name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if !valid {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("недопустимое имя UWP приложения: {:?}", app_name),
+        ));
+    }
+    Ok(format!("-Name '{}'", app_name))
+}
+
 /// Проверяет, установлено ли UWP приложение
-pub fn is_uwp_installed(app_name: &str) -> io::Result<bool> {
-    let output = Command::new("powershell")
-        .args(&[
-            "-Command",
-            &format!("Get-AppxPackage -Name {}", app_name)
-        ])
-        .output()?;
-
-    Ok(!output.stdout.is_empty())
+pub fn is_uwp_installed(runner: &dyn CommandRunner, app_name: &str) -> io::Result<bool> {
+    let output = runner.run(&CommandSpec::powershell(&format!("Get-AppxPackage {}", name_arg(app_name)?)))?;
+
+    Ok(!output.stdout.trim().is_empty())
 }
 
 /// Устанавливает UWP приложение
-pub fn install_uwp(package_name: &str) -> io::Result<()> {
-    println!("🔄 Установка UWP приложения {}...", package_name);
+pub fn install_uwp(runner: &dyn CommandRunner, package_name: &str) -> io::Result<()> {
+    let name = name_arg(package_name)?;
+    progress!("🔄 Установка UWP приложения {}...", package_name);
     
-    let status = Command::new("powershell")
-        .args(&[
-            "-Command",
-            &format!("Get-AppxPackage -AllUsers {} | Foreach {{Add-AppxPackage -DisableDevelopmentMode -Register \"$($_.InstallLocation)\\AppXManifest.xml\"}}", package_name)
-        ])
-        .status()?;
-
-    if status.success() {
-        println!("✅ UWP приложение {} успешно установлено.", package_name);
-    } else {
-        println!("❌ Не удалось установить UWP приложение {}.", package_name);
-    }
+    let spec = CommandSpec::powershell(&format!(
+        "Get-AppxPackage -AllUsers {} | Foreach {{Add-AppxPackage -DisableDevelopmentMode -Register \"$($_.InstallLocation)\\AppXManifest.xml\"}}",
+        name
+    ));
+    runner.run(&spec)?.check(&spec)?;
+    progress!("✅ UWP приложение {} успешно установлено.", package_name);
 
     Ok(())
 }
 
 /// Удаляет UWP приложение
-pub fn uninstall_uwp(package_name: &str) -> io::Result<()> {
-    println!("🔄 Удаление UWP приложения {}...", package_name);
+pub fn uninstall_uwp(runner: &dyn CommandRunner, package_name: &str) -> io::Result<()> {
+    let name = name_arg(package_name)?;
+    progress!("🔄 Удаление UWP приложения {}...", package_name);
     
-    let status = Command::new("powershell")
-        .args(&[
-            "-Command",
-            &format!("Get-AppxPackage {} | Remove-AppxPackage", package_name)
-        ])
-        .status()?;
-
-    if status.success() {
-        println!("✅ UWP приложение {} успешно удалено.", package_name);
-    } else {
-        println!("❌ Не удалось удалить UWP приложение {}.", package_name);
-    }
+    let spec = CommandSpec::powershell(&format!("Get-AppxPackage {} | Remove-AppxPackage", name));
+    runner.run(&spec)?.check(&spec)?;
+    progress!("✅ UWP приложение {} успешно удалено.", package_name);
 
     Ok(())
 }
 
+/// Устанавливает отмеченные приложения и удаляет снятые.
+/// Ошибка одного приложения не останавливает остальные.
+pub fn apply_uwp_selection(runner: &dyn CommandRunner, apps: &[UwpApp], selection: &Selection) -> ApplyReport {
+    apps.iter()
+        .filter_map(|app| match selection.get(&app.name) {
+            Some(true) if !app.installed => Some(ApplyResult::from_result(&app.name, "установка", install_uwp(runner, &app.name))),
+            Some(false) if app.installed => Some(ApplyResult::from_result(&app.name, "удаление", uninstall_uwp(runner, &app.name))),
+            _ => None,
+        })
+        .collect()
+}
+
 /// Список стандартных нежелательных UWP приложений для удаления
 pub const BLOATWARE_APPS: &[(&str, &str)] = &[
     ("Microsoft.BingWeather", "🌤️  Погода Bing"),
@@ -127,3 +159,74 @@ pub const BLOATWARE_APPS: &[(&str, &str)] = &[
     ("Microsoft.ZuneMusic", "🎵 Groove Музыка"),
     ("Microsoft.ZuneVideo", "🎬 Кино и ТВ"),
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::command::{CommandOutput, ScriptedRunner};
+
+    const APPX_LIST: &str = r#"[
+    {
+        "Name":  "Microsoft.WindowsCalculator",
+        "PackageFullName":  "Microsoft.WindowsCalculator_11.2311.0.0_x64__8wekyb3d8bbwe"
+    },
+    {
+        "Name":  "Microsoft.Paint",
+        "PackageFullName":  "Microsoft.Paint_11.2311.30.0_x64__8wekyb3d8bbwe"
+    }
+]"#;
+
+    #[test]
+    fn test_get_uwp_apps_from_transcript() {
+        let runner = ScriptedRunner::new().expect(
+            "powershell",
+            &["-Command", "Get-AppxPackage | Select-Object Name, PackageFullName | ConvertTo-Json"],
+            CommandOutput::ok(APPX_LIST),
+        );
+
+        let apps = get_uwp_apps(&runner).unwrap();
+        let calc = apps.iter().find(|a| a.name == "Microsoft.WindowsCalculator").unwrap();
+        assert!(calc.installed);
+        assert_eq!(calc.package_full_name, "Microsoft.WindowsCalculator_11.2311.0.0_x64__8wekyb3d8bbwe");
+        assert!(!apps.iter().find(|a| a.name == "Microsoft.WindowsTerminal").unwrap().installed);
+    }
+
+    #[test]
+    fn test_apply_uwp_selection() {
+        let apps = vec![
+            UwpApp { name: "Microsoft.Paint".into(), package_full_name: String::new(), installed: true },
+            UwpApp { name: "Microsoft.BingNews".into(), package_full_name: String::new(), installed: false },
+        ];
+        let selection: Selection = [("Microsoft.Paint".to_string(), false), ("Microsoft.BingNews".to_string(), false)]
+            .into_iter()
+            .collect();
+        let runner = ScriptedRunner::new().expect(
+            "powershell",
+            &["-Command", "Get-AppxPackage -Name 'Microsoft.Paint' | Remove-AppxPackage"],
+            CommandOutput::ok(""),
+        );
+
+        assert!(!apply_uwp_selection(&runner, &apps, &selection).has_failures());
+        assert!(runner.is_exhausted());
+    }
+
+    #[test]
+    fn test_unsafe_names_rejected() {
+        let runner = ScriptedRunner::new();
+        for name in ["Microsoft.Paint; Remove-Item C:\\", "a' | iex; '", "$(calc)", "Microsoft Paint", ""] {
+            assert_eq!(uninstall_uwp(&runner, name).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+            assert_eq!(install_uwp(&runner, name).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+            assert_eq!(is_uwp_installed(&runner, name).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+        // Ни одна команда не запускается
+        assert!(runner.calls().is_empty());
+    }
+
+    #[test]
+    fn test_parse_single_appx_object() {
+        let json = r#"{ "Name": "Microsoft.Paint", "PackageFullName": "Microsoft.Paint_1_x64__8wekyb3d8bbwe" }"#;
+        let apps = parse_appx_json(json).unwrap();
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].name, "Microsoft.Paint");
+    }
+}
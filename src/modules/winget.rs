@@ -0,0 +1,425 @@
+use std::fmt;
+use crate::modules::results::ApplyStatus;
+
+/// Строка таблицы winget (`list`, `search`, `upgrade`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WingetEntry {
+    pub name: String,
+    pub id: String,
+    pub version: String,
+    /// Доступная версия — есть у `list` и `upgrade`, если пакет можно обновить
+    pub available: Option<String>,
+    /// `winget`, `msstore`; пусто у программ, установленных не через winget
+    pub source: Option<String>,
+}
+
+/// Знак, которым winget обрезает слишком длинные значения
+pub const ELLIPSIS: char = '…';
+
+impl WingetEntry {
+    /// Обрезан ли Id: winget заменяет конец длинного Id на «…»
+    pub fn is_truncated(&self) -> bool {
+        self.id.ends_with(ELLIPSIS)
+    }
+
+    /// Совпадает ли Id без учёта регистра; обрезанный Id сравнивается по началу
+    pub fn id_matches(&self, id: &str) -> bool {
+        match self.id.strip_suffix(ELLIPSIS) {
+            Some(prefix) => id.to_lowercase().starts_with(&prefix.to_lowercase()),
+            None => self.id.eq_ignore_ascii_case(id),
+        }
+    }
+}
+
+/// Действие с пакетом: от него зависит, считать ли код завершения ошибкой
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Install,
+    Upgrade,
+    Uninstall,
+}
+
+/// Коды завершения winget (HRESULT `APPINSTALLER_CLI_ERROR_*`), которые различаются в отчёте
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Success,
+    NoPackagesFound,
+    NoApplicableInstaller,
+    HashMismatch,
+    UpdateNotApplicable,
+    AlreadyInstalled,
+    BlockedByPolicy,
+    PackageInUse,
+    InstallInProgress,
+    FileInUse,
+    MissingDependency,
+    DiskFull,
+    InsufficientMemory,
+    NoNetwork,
+    RebootRequired,
+    CancelledByUser,
+    Downgrade,
+    /// Код, которого нет в таблице
+    Other(i32),
+}
+
+/// Коды winget; у одного значения может быть несколько кодов (общий и код установщика)
+const EXIT_CODES: &[(u32, ExitCode)] = &[
+    (0x8A15_0010, ExitCode::NoApplicableInstaller),
+    (0x8A15_0011, ExitCode::HashMismatch),
+    (0x8A15_0014, ExitCode::NoPackagesFound),
+    (0x8A15_002B, ExitCode::UpdateNotApplicable),
+    (0x8A15_0056, ExitCode::BlockedByPolicy),
+    (0x8A15_0061, ExitCode::AlreadyInstalled),
+    (0x8A15_0101, ExitCode::PackageInUse),
+    (0x8A15_0102, ExitCode::InstallInProgress),
+    (0x8A15_0103, ExitCode::FileInUse),
+    (0x8A15_0104, ExitCode::MissingDependency),
+    (0x8A15_0105, ExitCode::DiskFull),
+    (0x8A15_0106, ExitCode::InsufficientMemory),
+    (0x8A15_0107, ExitCode::NoNetwork),
+    (0x8A15_0109, ExitCode::RebootRequired),
+    (0x8A15_010A, ExitCode::RebootRequired),
+    (0x8A15_010B, ExitCode::RebootRequired),
+    (0x8A15_010C, ExitCode::CancelledByUser),
+    (0x8A15_010D, ExitCode::AlreadyInstalled),
+    (0x8A15_010E, ExitCode::Downgrade),
+    (0x8A15_010F, ExitCode::BlockedByPolicy),
+];
+
+impl ExitCode {
+    /// Код процесса winget: на Windows HRESULT приходит отрицательным `i32`
+    pub fn from_code(code: i32) -> Self {
+        if code == 0 {
+            return ExitCode::Success;
+        }
+        EXIT_CODES
+            .iter()
+            .find(|(known, _)| *known as i32 == code)
+            .map_or(ExitCode::Other(code), |(_, exit)| *exit)
+    }
+
+    /// Числовой код; для значений с несколькими кодами — первый из таблицы
+    pub fn code(&self) -> i32 {
+        match self {
+            ExitCode::Success => 0,
+            ExitCode::Other(code) => *code,
+            known => EXIT_CODES.iter().find(|(_, exit)| exit == known).map_or(0, |(code, _)| *code as i32),
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            ExitCode::Success => "выполнено",
+            ExitCode::NoPackagesFound => "подходящий пакет не найден",
+            ExitCode::NoApplicableInstaller => "нет установщика для этой системы (архитектура, язык или область установки)",
+            ExitCode::HashMismatch => "хеш установщика не совпадает с манифестом",
+            ExitCode::UpdateNotApplicable => "обновление не требуется",
+            ExitCode::AlreadyInstalled => "пакет уже установлен",
+            ExitCode::BlockedByPolicy => "установка запрещена групповой политикой",
+            ExitCode::PackageInUse => "приложение запущено — закройте его и повторите",
+            ExitCode::InstallInProgress => "уже идёт другая установка — повторите позже",
+            ExitCode::FileInUse => "файл занят другим процессом",
+            ExitCode::MissingDependency => "не установлены зависимости пакета",
+            ExitCode::DiskFull => "недостаточно места на диске",
+            ExitCode::InsufficientMemory => "недостаточно памяти",
+            ExitCode::NoNetwork => "нет подключения к сети",
+            ExitCode::RebootRequired => "для завершения нужна перезагрузка",
+            ExitCode::CancelledByUser => "установка отменена пользователем",
+            ExitCode::Downgrade => "уже установлена более новая версия",
+            ExitCode::Other(_) => "неизвестный код winget",
+        }
+    }
+
+    /// Статус для отчёта: «уже установлено» и «обновление не требуется» — не ошибки,
+    /// «пакет не найден» — не ошибка только при удалении
+    pub fn status(&self, action: Action) -> ApplyStatus {
+        match self {
+            ExitCode::Success => ApplyStatus::Applied,
+            ExitCode::AlreadyInstalled | ExitCode::UpdateNotApplicable => ApplyStatus::Skipped,
+            ExitCode::NoPackagesFound if action == Action::Uninstall => ApplyStatus::Skipped,
+            ExitCode::RebootRequired => ApplyStatus::NeedsReboot,
+            _ => ApplyStatus::Failed,
+        }
+    }
+}
+
+impl fmt::Display for ExitCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (0x{:08X})", self.message(), self.code() as u32)
+    }
+}
+
+/// Названия необязательных столбцов в разных локализациях
+const SOURCE_HEADERS: &[&str] = &["source", "источник", "quelle", "origen", "源"];
+const MATCH_HEADERS: &[&str] = &["match", "совпадение", "übereinstimmung", "coincidencia"];
+const KNOWN_SOURCES: &[&str] = &["winget", "msstore"];
+
+/// Столбец таблицы: заголовок и начало в символах
+struct Column {
+    header: String,
+    start: usize,
+}
+
+/// Что лежит в столбце после Name, Id и Version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Extra {
+    Available,
+    Source,
+    Match,
+}
+
+/// Видимый текст строки: winget рисует индикатор прогресса через `\r` и `\b`,
+/// поэтому остаётся только то, что напечатано после последнего возврата каретки
+fn visible(line: &str) -> String {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let line = line.rsplit('\r').next().unwrap_or(line);
+    let mut text = String::new();
+    for c in line.chars() {
+        if c == '\u{8}' {
+            text.pop();
+        } else {
+            text.push(c);
+        }
+    }
+    text
+}
+
+fn is_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.len() >= 3 && line.chars().all(|c| c == '-')
+}
+
+/// Столбцы по строке заголовка: каждый начинается со слова после пробела
+fn columns(header: &str) -> Vec<Column> {
+    let chars: Vec<char> = header.chars().collect();
+    let starts: Vec<usize> = (0..chars.len())
+        .filter(|&i| !chars[i].is_whitespace() && (i == 0 || chars[i - 1].is_whitespace()))
+        .collect();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| {
+            let end = starts.get(n + 1).copied().unwrap_or(chars.len());
+            Column {
+                header: chars[start..end].iter().collect::<String>().trim().to_string(),
+                start,
+            }
+        })
+        .collect()
+}
+
+/// Значения строки по началам столбцов. `None`, если строка не ложится в таблицу:
+/// перед началом столбца стоит не пробел или Id пуст
+fn cells(line: &str, columns: &[Column]) -> Option<Vec<String>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut cells = Vec::with_capacity(columns.len());
+    for (n, column) in columns.iter().enumerate() {
+        if column.start > 0 && column.start <= chars.len() && !chars[column.start - 1].is_whitespace() {
+            return None;
+        }
+        let start = column.start.min(chars.len());
+        let end = columns.get(n + 1).map_or(chars.len(), |c| c.start).min(chars.len());
+        cells.push(chars[start..end].iter().collect::<String>().trim().to_string());
+    }
+    let id = cells.get(1)?;
+    if id.is_empty() || id.contains(char::is_whitespace) || cells.get(2).is_none_or(|v| v.is_empty()) {
+        return None;
+    }
+    Some(cells)
+}
+
+/// Назначение столбцов после Version: по известным заголовкам,
+/// а в незнакомой локализации — по значениям (источник — `winget` или `msstore`)
+fn classify(columns: &[Column], rows: &[Vec<String>]) -> Vec<Extra> {
+    let extra = columns.len().saturating_sub(3);
+    (0..extra)
+        .map(|n| {
+            let header = columns[n + 3].header.to_lowercase();
+            if SOURCE_HEADERS.contains(&header.as_str()) {
+                return Extra::Source;
+            }
+            if MATCH_HEADERS.contains(&header.as_str()) {
+                return Extra::Match;
+            }
+            let looks_like_source = rows
+                .iter()
+                .filter_map(|row| row.get(n + 3))
+                .filter(|v| !v.is_empty())
+                .all(|v| KNOWN_SOURCES.contains(&v.to_lowercase().as_str()));
+            if n + 1 == extra && looks_like_source {
+                Extra::Source
+            } else {
+                Extra::Available
+            }
+        })
+        .collect()
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Разбирает таблицы из вывода `winget list`, `winget search` или `winget upgrade`.
+/// Столбцы определяются по заголовку над строкой из дефисов, поэтому имена
+/// с пробелами и локализованные заголовки не мешают. Таблиц может быть несколько:
+/// `upgrade` отдельно печатает пакеты, требующие явного обновления.
+pub fn parse_table(output: &str) -> Vec<WingetEntry> {
+    let lines: Vec<String> = output.lines().map(visible).collect();
+    let mut entries = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if i == 0 || !is_separator(&lines[i]) {
+            i += 1;
+            continue;
+        }
+        let columns = columns(&lines[i - 1]);
+        i += 1;
+        if columns.len() < 3 {
+            continue;
+        }
+
+        let mut rows = Vec::new();
+        while let Some(row) = lines.get(i).and_then(|line| cells(line, &columns)) {
+            rows.push(row);
+            i += 1;
+        }
+
+        let extras = classify(&columns, &rows);
+        for row in rows {
+            let mut entry = WingetEntry {
+                name: row[0].clone(),
+                id: row[1].clone(),
+                version: row[2].clone(),
+                ..Default::default()
+            };
+            for (extra, value) in extras.iter().zip(&row[3..]) {
+                match extra {
+                    Extra::Available => entry.available = non_empty(value),
+                    Extra::Source => entry.source = non_empty(value),
+                    Extra::Match => {}
+                }
+            }
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+/// Есть ли пакет с таким Id среди строк таблицы
+pub fn contains_id(entries: &[WingetEntry], id: &str) -> bool {
+    entries.iter().any(|e| e.id_matches(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST_EN: &str = "   - \r   \\ \r   | \r                                        \rName                                   Id                                     Version          Available        Source\r
+-----------------------------------------------------------------------------------------------------------------------------------------\r
+7-Zip 23.01 (x64)                      7zip.7zip                              23.01                             winget\r
+Git                                    Git.Git                                2.43.0           2.44.0           winget\r
+Microsoft Visual C++ 2015-2022 Redist… Microsoft.VCRedist.2015+.x64           14.38.33135.0    14.40.33810.0    winget\r
+Microsoft Edge                         Microsoft.Edge                         123.0.2420.65                     winget\r
+Windows Terminal                       9N0DX20HK701                           1.19.10573.0                      msstore\r
+Steam                                  Valve.Steam                            2.10.91.91                        winget\r
+Some Local Tool                        ARP\\Machine\\X64\\{6A1C7D8E-3F2B-4C5A-…  1.0\r
+";
+
+    const LIST_RU: &str = "Имя                       ИД                          Версия      Доступно    Источник
+----------------------------------------------------------------------------------------------
+Mozilla Firefox (x64 ru)  Mozilla.Firefox             124.0.1     124.0.2     winget
+Notepad++ (64-bit x64)    Notepad++.Notepad++         8.6.4                   winget
+Средство удаления вредон… Microsoft.MRT               5.122
+";
+
+    const UPGRADE_RU: &str = "Имя       ИД        Версия Доступно Источник
+-----------------------------------------------
+Git       Git.Git   2.43.0 2.44.0   winget
+Python 3… Python.P… 3.12.1 3.12.2   winget
+Доступно обновлений: 2.
+Для следующих пакетов доступно обновление, но требуется явное указание для обновления:
+Имя     ИД              Версия  Доступно Источник
+--------------------------------------------------
+Discord Discord.Discord 1.0.9   1.0.9035 winget
+";
+
+    const SEARCH_EN: &str = "Name               Id                         Version Match           Source
+----------------------------------------------------------------------------
+Visual Studio Code Microsoft.VisualStudioCode 1.88.0  Moniker: vscode winget
+VSCodium           VSCodium.VSCodium          1.88.0  Tag: vscode     winget
+";
+
+    #[test]
+    fn test_parse_list_with_spinner_and_ellipsis() {
+        let entries = parse_table(LIST_EN);
+        assert_eq!(entries.len(), 7);
+        assert_eq!(entries[0].name, "7-Zip 23.01 (x64)");
+        assert_eq!(entries[0].id, "7zip.7zip");
+        assert_eq!(entries[0].available, None);
+        assert_eq!(entries[1].available.as_deref(), Some("2.44.0"));
+        assert_eq!(entries[2].name, "Microsoft Visual C++ 2015-2022 Redist…");
+        assert_eq!(entries[4].source.as_deref(), Some("msstore"));
+        assert_eq!(entries[6].source, None);
+        assert!(entries[6].is_truncated());
+        assert!(contains_id(&entries, "valve.steam"));
+        assert!(!contains_id(&entries, "Valve"));
+    }
+
+    #[test]
+    fn test_parse_russian_list() {
+        let entries = parse_table(LIST_RU);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].name, "Mozilla Firefox (x64 ru)");
+        assert_eq!(entries[0].version, "124.0.1");
+        assert_eq!(entries[0].available.as_deref(), Some("124.0.2"));
+        assert_eq!(entries[0].source.as_deref(), Some("winget"));
+        assert_eq!(entries[1].available, None);
+        assert_eq!(entries[2].name, "Средство удаления вредон…");
+        assert_eq!(entries[2].source, None);
+    }
+
+    #[test]
+    fn test_parse_russian_upgrade_with_footer() {
+        let entries = parse_table(UPGRADE_RU);
+        let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["Git.Git", "Python.P…", "Discord.Discord"]);
+        assert!(entries[1].id_matches("Python.Python.3.12"));
+        assert_eq!(entries[2].available.as_deref(), Some("1.0.9035"));
+    }
+
+    #[test]
+    fn test_parse_search_skips_match_column() {
+        let entries = parse_table(SEARCH_EN);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "Visual Studio Code");
+        assert_eq!(entries[0].id, "Microsoft.VisualStudioCode");
+        assert_eq!(entries[1].available, None);
+        assert_eq!(entries[1].source.as_deref(), Some("winget"));
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let already = ExitCode::from_code(-1978335135);
+        assert_eq!(already, ExitCode::AlreadyInstalled);
+        assert_eq!(already.status(Action::Install), ApplyStatus::Skipped);
+        assert_eq!(already.to_string(), "пакет уже установлен (0x8A150061)");
+
+        let not_found = ExitCode::from_code(0x8A15_0014_u32 as i32);
+        assert_eq!(not_found.status(Action::Install), ApplyStatus::Failed);
+        assert_eq!(not_found.status(Action::Uninstall), ApplyStatus::Skipped);
+        assert_eq!(ExitCode::from_code(0x8A15_010D_u32 as i32).code(), 0x8A15_0061_u32 as i32);
+        assert_eq!(ExitCode::from_code(0x8A15_0109_u32 as i32).status(Action::Upgrade), ApplyStatus::NeedsReboot);
+        // Перезагрузка нужна до установки
+        let reboot_first = ExitCode::from_code(0x8A15_010A_u32 as i32);
+        assert_eq!(reboot_first, ExitCode::RebootRequired);
+        assert_eq!(reboot_first.status(Action::Install), ApplyStatus::NeedsReboot);
+        assert_eq!(ExitCode::from_code(1603), ExitCode::Other(1603));
+    }
+
+    #[test]
+    fn test_parse_without_table() {
+        assert!(parse_table("No installed package found matching input criteria.").is_empty());
+        assert!(parse_table("Не найдено установленных пакетов, соответствующих условиям поиска.").is_empty());
+    }
+}
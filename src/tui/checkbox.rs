@@ -27,7 +27,7 @@ impl CheckboxItem {
         self.checked = !self.checked;
     }
     
-    pub fn to_list_item(&self, is_selected: bool) -> ListItem {
+    pub fn to_list_item(&self, is_selected: bool) -> ListItem<'_> {
         let checkbox = if self.checked { "[X]" } else { "[ ]" };
         
         let style = if is_selected {
@@ -82,7 +82,7 @@ impl CheckboxList {
         self.items.iter().filter(|item| item.checked).collect()
     }
     
-    pub fn render(&self, title: &str) -> (List, ListState) {
+    pub fn render(&self, title: &str) -> (List<'_>, ListState) {
         let header = Line::from(vec![
             Span::raw("    "),
             Span::styled("Название", Style::default().add_modifier(Modifier::BOLD)),
@@ -115,10 +115,11 @@ impl CheckboxList {
 
 /// Обрезает строку до указанной длины
 fn truncate(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
+    if s.chars().count() <= max_len {
         s.to_string()
     } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+        let head: String = s.chars().take(max_len.saturating_sub(3)).collect();
+        format!("{}...", head)
     }
 }
 
@@ -145,5 +146,6 @@ mod tests {
     fn test_truncate() {
         assert_eq!(truncate("short", 10), "short");
         assert_eq!(truncate("very long string", 10), "very lo...");
+        assert_eq!(truncate("Показывать скрытые файлы", 10), "Показыв...");
     }
 }
@@ -2,9 +2,12 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
+use crate::modules::compliance::{ComplianceReport, ComplianceStatus};
+use crate::modules::plan::Plan;
+use crate::utils::progress::BackgroundTask;
 
 /// Отрисовывает заголовок приложения
 pub fn render_header(f: &mut Frame, area: Rect, is_admin: bool) {
@@ -70,6 +73,115 @@ pub fn render_confirmation_dialog(f: &mut Frame, area: Rect, message: &str) {
     f.render_widget(paragraph, dialog_area);
 }
 
+/// Отрисовывает список запланированных изменений для подтверждения
+pub fn render_plan_review(f: &mut Frame, area: Rect, plan: &Plan, scroll: u16) {
+    let block = Block::default()
+        .title(format!("Планируемые изменения ({})", plan.steps.len()))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+    
+    let mut text: Vec<Line> = if plan.is_empty() {
+        vec![Line::from(Span::styled("Изменений нет: всё уже настроено.", Style::default().fg(Color::Green)))]
+    } else {
+        plan.steps
+            .iter()
+            .map(|step| {
+                Line::from(vec![
+                    Span::styled(format!("[{}] ", step.title), Style::default().fg(Color::Cyan)),
+                    Span::raw(step.change.to_string()),
+                ])
+            })
+            .collect()
+    };
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled(
+        "Y / Enter - выполнить | N / Esc - отмена | ↑↓ - прокрутка",
+        Style::default().fg(Color::Yellow),
+    )));
+    
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+    
+    let dialog_area = centered_rect(90, 80, area);
+    f.render_widget(Clear, dialog_area);
+    f.render_widget(paragraph, dialog_area);
+}
+
+/// Отрисовывает отчёт о соответствии профилю
+pub fn render_compliance_report(f: &mut Frame, area: Rect, report: &ComplianceReport, scroll: u16) {
+    let title_color = if report.has_drift() { Color::Red } else { Color::Green };
+    let block = Block::default()
+        .title(Span::styled(
+            format!("Проверка профиля \"{}\": {}", report.profile, report.summary()),
+            Style::default().fg(title_color),
+        ))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let mut text: Vec<Line> = report
+        .items
+        .iter()
+        .map(|item| {
+            let color = match item.status {
+                ComplianceStatus::Complies => Color::Green,
+                ComplianceStatus::Drifted => Color::Red,
+                ComplianceStatus::Unknown => Color::DarkGray,
+            };
+            Line::from(Span::styled(item.to_string(), Style::default().fg(color)))
+        })
+        .collect();
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled(
+        "Esc - назад | ↑↓ - прокрутка",
+        Style::default().fg(Color::Yellow),
+    )));
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+
+    let dialog_area = centered_rect(90, 80, area);
+    f.render_widget(Clear, dialog_area);
+    f.render_widget(paragraph, dialog_area);
+}
+
+/// Отрисовывает ход фоновой задачи: журнал вывода прокручивается за последней строкой,
+/// `scroll` — на сколько строк пользователь отмотал назад
+pub fn render_progress(f: &mut Frame, area: Rect, task: &BackgroundTask, scroll: u16) {
+    if task.log.is_empty() && task.is_running() {
+        render_loading(f, area, &task.title);
+        return;
+    }
+
+    let (status, color, hint) = match task.outcome() {
+        None if task.is_cancelled() => ("⏳ отмена...".to_string(), Color::Yellow, "↑↓ - прокрутка"),
+        None => ("⏳ выполняется".to_string(), Color::Yellow, "Esc / C - отменить | ↑↓ - прокрутка"),
+        Some(outcome) if outcome.success => (format!("✅ {}", outcome.message), Color::Green, "Enter / Esc - закрыть | ↑↓ - прокрутка"),
+        Some(outcome) => (format!("❌ {}", outcome.message), Color::Red, "Enter / Esc - закрыть | ↑↓ - прокрутка"),
+    };
+    let block = Block::default()
+        .title(Span::styled(format!("{}: {}", task.title, status), Style::default().fg(color)))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let dialog_area = centered_rect(90, 80, area);
+    // Рамка и строка подсказки
+    let height = dialog_area.height.saturating_sub(3) as usize;
+    let bottom = task.log.len().saturating_sub(scroll as usize).max(height.min(task.log.len()));
+    let mut text: Vec<Line> = task.log[bottom.saturating_sub(height)..bottom]
+        .iter()
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+    text.resize(height, Line::from(""));
+    text.push(Line::from(Span::styled(hint, Style::default().fg(Color::Yellow))));
+
+    f.render_widget(Clear, dialog_area);
+    f.render_widget(Paragraph::new(text).block(block), dialog_area);
+}
+
 /// Создаёт центрированный прямоугольник
 pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
@@ -103,6 +215,8 @@ pub fn render_loading(f: &mut Frame, area: Rect, message: &str) {
         Line::from(Span::styled("⏳ Пожалуйста, подождите...", Style::default().fg(Color::Yellow))),
         Line::from(""),
         Line::from(Span::raw(message)),
+        Line::from(""),
+        Line::from(Span::styled("Esc / C - отменить", Style::default().fg(Color::Gray))),
     ];
     
     let paragraph = Paragraph::new(text)
@@ -110,5 +224,23 @@ pub fn render_loading(f: &mut Frame, area: Rect, message: &str) {
         .alignment(Alignment::Center);
     
     let dialog_area = centered_rect(60, 20, area);
+    f.render_widget(Clear, dialog_area);
     f.render_widget(paragraph, dialog_area);
 }
+
+/// Отрисовывает строку ввода вместо панели подсказок
+pub fn render_input(f: &mut Frame, area: Rect, title: &str, value: &str) {
+    let text = Line::from(vec![
+        Span::styled(value, Style::default().fg(Color::White)),
+        Span::styled("▏", Style::default().fg(Color::Yellow)),
+    ]);
+    let input = Paragraph::new(text).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(input, area);
+}
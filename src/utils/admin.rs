@@ -1,5 +1,5 @@
+#[cfg(target_os = "windows")]
 use std::ptr;
-
 #[cfg(target_os = "windows")]
 use winapi::um::winnt::TOKEN_ELEVATION;
 #[cfg(target_os = "windows")]
@@ -0,0 +1,409 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::utils::progress::{self, Progress};
+
+/// Журнал запущенных команд в директории данных
+pub const LOG_FILE: &str = "commands.log";
+
+/// Ожидание запросов: скриптов PowerShell, списков и поиска пакетов
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Ожидание установки, удаления и обновления пакетов
+pub const INSTALL_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Описание запускаемой команды
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub timeout: Option<Duration>,
+}
+
+impl CommandSpec {
+    pub fn new(program: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            args: Vec::new(),
+            env: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    /// Команда PowerShell
+    pub fn powershell(script: &str) -> Self {
+        Self::new("powershell").args(["-Command", script]).timeout(QUERY_TIMEOUT)
+    }
+
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.args.extend(args.into_iter().map(|a| a.as_ref().to_string()));
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl fmt::Display for CommandSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            if arg.contains(' ') {
+                write!(f, " \"{}\"", arg)?;
+            } else {
+                write!(f, " {}", arg)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Результат выполнения команды
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandOutput {
+    /// Код возврата, `None` если процесс завершён сигналом
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    /// Успешный результат с указанным выводом
+    pub fn ok(stdout: &str) -> Self {
+        Self {
+            code: Some(0),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        }
+    }
+
+    /// Результат с кодом возврата и выводом
+    pub fn with_code(code: i32, stdout: &str) -> Self {
+        Self {
+            code: Some(code),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// Превращает неуспешный код возврата в ошибку
+    pub fn check(self, spec: &CommandSpec) -> io::Result<Self> {
+        if self.success() {
+            return Ok(self);
+        }
+        let details = if self.stderr.trim().is_empty() { self.stdout.trim() } else { self.stderr.trim() };
+        let code = self.code.map(|c| c.to_string()).unwrap_or_else(|| "—".to_string());
+        Err(io::Error::other(format!("{} завершилась с кодом {}: {}", spec, code, details)))
+    }
+}
+
+/// Запуск внешних программ (winget, PowerShell, sc, powercfg, taskkill)
+pub trait CommandRunner: Send + Sync {
+    /// Выполняет команду и дожидается её завершения
+    fn run(&self, spec: &CommandSpec) -> io::Result<CommandOutput>;
+
+    /// Запускает программу, не дожидаясь завершения
+    fn spawn(&self, spec: &CommandSpec) -> io::Result<()>;
+}
+
+/// Запуск настоящих процессов
+#[derive(Debug, Default)]
+pub struct SystemRunner {
+    log_file: Option<PathBuf>,
+}
+
+impl SystemRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Дописывает каждую команду и её результат в файл журнала
+    pub fn with_log_file(mut self, path: PathBuf) -> Self {
+        self.log_file = Some(path);
+        self
+    }
+
+    fn log(&self, line: &str) {
+        if let Some(path) = &self.log_file {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    fn build(spec: &CommandSpec) -> Command {
+        let mut command = Command::new(&spec.program);
+        command.args(&spec.args);
+        for (key, value) in &spec.env {
+            command.env(key, value);
+        }
+        command
+    }
+}
+
+/// Читает вывод процесса целиком; в фоновой задаче пересылает его построчно
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>, progress: Option<Progress>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(pipe) = pipe {
+            let mut reader = BufReader::new(pipe);
+            let mut line = Vec::new();
+            while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+                if let Some(progress) = &progress {
+                    let text = String::from_utf8_lossy(&line);
+                    let text = text.trim_end();
+                    if !text.is_empty() {
+                        progress.line(&format!("  {}", text));
+                    }
+                }
+                buf.append(&mut line);
+            }
+        }
+        String::from_utf8_lossy(&buf).into_owned()
+    })
+}
+
+fn cancelled(spec: &CommandSpec) -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, format!("{}: отменено", spec))
+}
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, spec: &CommandSpec) -> io::Result<CommandOutput> {
+        self.log(&format!("$ {}", spec));
+        if progress::is_cancelled() {
+            return Err(cancelled(spec));
+        }
+
+        let mut child = Self::build(spec)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", spec.program, e)))?;
+
+        let stdout = read_pipe(child.stdout.take(), progress::current());
+        let stderr = read_pipe(child.stderr.take(), progress::current());
+
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if progress::is_cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                self.log("  отменено");
+                return Err(cancelled(spec));
+            }
+            if let Some(timeout) = spec.timeout {
+                if started.elapsed() >= timeout {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    self.log("  превышено время ожидания");
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("{}: превышено время ожидания ({} с)", spec, timeout.as_secs()),
+                    ));
+                }
+            }
+            thread::sleep(Duration::from_millis(20));
+        };
+
+        let output = CommandOutput {
+            code: status.code(),
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        };
+        self.log(&format!("  код возврата: {:?}", output.code));
+
+        Ok(output)
+    }
+
+    fn spawn(&self, spec: &CommandSpec) -> io::Result<()> {
+        self.log(&format!("$ {} &", spec));
+        Self::build(spec).spawn()?;
+        Ok(())
+    }
+}
+
+/// Ожидаемая команда и результат, который на неё вернётся
+type Expectation = (String, Vec<String>, io::Result<CommandOutput>);
+
+/// Поддельный запуск команд для тестов: отвечает заранее записанными
+/// результатами в том порядке, в котором они были добавлены.
+#[derive(Debug, Default)]
+pub struct ScriptedRunner {
+    expected: Mutex<VecDeque<Expectation>>,
+    calls: Mutex<Vec<CommandSpec>>,
+}
+
+impl ScriptedRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ожидает команду с указанными аргументами и возвращает на неё `output`
+    pub fn expect(self, program: &str, args: &[&str], output: CommandOutput) -> Self {
+        self.push(program, args, Ok(output));
+        self
+    }
+
+    /// Ожидает команду, запуск которой завершится ошибкой
+    pub fn expect_error(self, program: &str, args: &[&str], kind: io::ErrorKind) -> Self {
+        self.push(program, args, Err(io::Error::new(kind, format!("{}: ошибка запуска", program))));
+        self
+    }
+
+    fn push(&self, program: &str, args: &[&str], result: io::Result<CommandOutput>) {
+        let args = args.iter().map(|a| a.to_string()).collect();
+        self.expected.lock().unwrap().push_back((program.to_string(), args, result));
+    }
+
+    /// Все выполненные команды
+    pub fn calls(&self) -> Vec<CommandSpec> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Остались ли ожидаемые, но не выполненные команды
+    pub fn is_exhausted(&self) -> bool {
+        self.expected.lock().unwrap().is_empty()
+    }
+
+    fn next(&self, spec: &CommandSpec) -> io::Result<CommandOutput> {
+        self.calls.lock().unwrap().push(spec.clone());
+
+        let (program, args, result) = self.expected.lock().unwrap().pop_front().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("неожиданная команда: {}", spec))
+        })?;
+        if program != spec.program || args != spec.args {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("ожидалась команда {} {:?}, получена {}", program, args, spec),
+            ));
+        }
+        result
+    }
+}
+
+impl CommandRunner for ScriptedRunner {
+    fn run(&self, spec: &CommandSpec) -> io::Result<CommandOutput> {
+        self.next(spec)
+    }
+
+    fn spawn(&self, spec: &CommandSpec) -> io::Result<()> {
+        self.next(spec).map(|_| ())
+    }
+}
+
+/// Запуск команд текущей системы; с `log_file` команды и коды возврата дописываются в файл
+pub fn system_runner(log_file: Option<PathBuf>) -> Box<dyn CommandRunner> {
+    match log_file {
+        Some(path) => Box::new(SystemRunner::new().with_log_file(path)),
+        None => Box::new(SystemRunner::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::temp_dir;
+
+    #[test]
+    fn test_scripted_runner_replays_in_order() {
+        let runner = ScriptedRunner::new()
+            .expect("sc", &["stop", "DiagTrack"], CommandOutput::ok("STOPPED"))
+            .expect("powercfg", &["/hibernate", "off"], CommandOutput::with_code(1, ""));
+
+        let out = runner.run(&CommandSpec::new("sc").args(["stop", "DiagTrack"])).unwrap();
+        assert_eq!(out.stdout, "STOPPED");
+
+        let spec = CommandSpec::new("powercfg").args(["/hibernate", "off"]);
+        let out = runner.run(&spec).unwrap();
+        assert!(out.check(&spec).is_err());
+
+        assert!(runner.is_exhausted());
+        assert_eq!(runner.calls().len(), 2);
+    }
+
+    #[test]
+    fn test_scripted_runner_rejects_unexpected() {
+        let runner = ScriptedRunner::new().expect("winget", &["list"], CommandOutput::ok(""));
+        let err = runner.run(&CommandSpec::new("winget").arg("search")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_command_display_quotes_spaces() {
+        let spec = CommandSpec::powershell("Get-AppxPackage -Name X");
+        assert_eq!(spec.to_string(), "powershell -Command \"Get-AppxPackage -Name X\"");
+        assert_eq!(spec.timeout, Some(QUERY_TIMEOUT));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_system_runner_timeout() {
+        let dir = temp_dir("command-log");
+        let log = dir.join("logs").join(LOG_FILE);
+        let spec = CommandSpec::new("sleep").arg("5").timeout(Duration::from_millis(100));
+        let err = SystemRunner::new().with_log_file(log.clone()).run(&spec).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(fs::read_to_string(&log).unwrap(), "$ sleep 5\n  превышено время ожидания\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_system_runner_streams_and_cancels() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let progress = Progress::new(sender, cancel.clone());
+
+        let output = progress::with_progress(progress.clone(), || {
+            SystemRunner::new().run(&CommandSpec::new("sh").args(["-c", "echo one; echo two"]))
+        })
+        .unwrap();
+        assert_eq!(output.stdout, "one\ntwo\n");
+        let mut lines = Vec::new();
+        while let Ok(progress::TaskEvent::Output(line)) = receiver.try_recv() {
+            lines.push(line);
+        }
+        assert_eq!(lines, vec!["  one", "  two"]);
+
+        cancel.store(true, Ordering::Relaxed);
+        let err = progress::with_progress(progress, || SystemRunner::new().run(&CommandSpec::new("sleep").arg("5")))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+    }
+}
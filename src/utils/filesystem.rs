@@ -1,19 +1,22 @@
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::progress;
 
 /// Получает список файлов в директории
 pub fn list_files(path: &str) -> io::Result<Vec<String>> {
     let mut files = Vec::new();
     
     if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                if let Ok(file_type) = entry.file_type() {
-                    if file_type.is_file() {
-                        if let Some(name) = entry.file_name().to_str() {
-                            files.push(name.to_string());
-                        }
+        for entry in entries.flatten() {
+            if let Ok(file_type) = entry.file_type() {
+                if file_type.is_file() {
+                    if let Some(name) = entry.file_name().to_str() {
+                        files.push(name.to_string());
                     }
                 }
             }
@@ -61,36 +64,533 @@ pub fn get_appdata_local() -> Option<PathBuf> {
     std::env::var("LOCALAPPDATA").ok().map(PathBuf::from)
 }
 
+/// Папка «Документы» пользователя; вне Windows — ~/Documents
+pub fn get_documents_dir() -> Option<PathBuf> {
+    std::env::var("USERPROFILE")
+        .or_else(|_| std::env::var("HOME"))
+        .ok()
+        .map(|home| PathBuf::from(home).join("Documents"))
+}
+
 /// Получает путь к директории ProgramFiles
 pub fn get_program_files() -> Option<PathBuf> {
     std::env::var("ProgramFiles").ok().map(PathBuf::from)
 }
 
-/// Копирует файлы конфигурации для приложения
-pub fn copy_config_files(app_name: &str, source_dir: &Path, dest_dir: &Path) -> io::Result<()> {
-    println!("📋 Копирование конфигурационных файлов для {}...", app_name);
-    
-    if !source_dir.exists() {
+/// Директория данных утилиты (журнал, профили):
+/// %LOCALAPPDATA%\\win-tool, вне Windows — ~/.local/share/win-tool
+pub fn get_tool_data_dir() -> Option<PathBuf> {
+    get_appdata_local()
+        .or_else(|| std::env::var("HOME").ok().map(|home| PathBuf::from(home).join(".local").join("share")))
+        .map(|dir| dir.join("win-tool"))
+}
+
+/// Находит директорию data/ рядом с исполняемым файлом или в текущей директории
+pub fn find_data_dir() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|p| p.join("data")));
+    let cwd_dir = std::env::current_dir().ok().map(|dir| dir.join("data"));
+
+    exe_dir.into_iter().chain(cwd_dir).find(|dir| dir.is_dir())
+}
+
+/// Как файл шаблона ложится на существующий файл
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Заменить файл целиком
+    #[default]
+    Overwrite,
+    /// Не трогать существующий файл
+    SkipIfExists,
+    /// Слить объекты JSON: ключи шаблона заменяют значения, остальные ключи файла остаются
+    JsonMerge,
+    /// Слить INI по секциям и ключам, комментарии и порядок строк файла сохраняются
+    IniMerge,
+    /// Дописать строки шаблона, которых в файле ещё нет
+    AppendLines,
+}
+
+/// Подстановки `{{ИМЯ}}` в шаблонах и путях
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Variables(Vec<(String, String)>);
+
+impl Variables {
+    /// USERNAME, USERPROFILE, APPDATA, LOCALAPPDATA и HOSTNAME текущего пользователя
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok();
+        let hostname = var("COMPUTERNAME").or_else(|| var("HOSTNAME"));
+        let user = var("USERNAME").or_else(|| var("USER"));
+        [
+            ("USERNAME", user),
+            ("USERPROFILE", var("USERPROFILE").or_else(|| var("HOME"))),
+            ("APPDATA", var("APPDATA")),
+            ("LOCALAPPDATA", var("LOCALAPPDATA")),
+            ("HOSTNAME", hostname),
+        ]
+        .into_iter()
+        .fold(Self::default(), |vars, (name, value)| match value {
+            Some(value) => vars.with(name, &value),
+            None => vars,
+        })
+    }
+
+    pub fn with(mut self, name: &str, value: &str) -> Self {
+        self.0.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Заменяет известные `{{ИМЯ}}`; неизвестные остаются как есть
+    pub fn substitute(&self, text: &str) -> String {
+        self.0.iter().fold(text.to_string(), |text, (name, value)| text.replace(&format!("{{{{{}}}}}", name), value))
+    }
+
+    /// То же для JSON: значения экранируются, чтобы `C:\Users` не ломал строки
+    pub fn substitute_json(&self, text: &str) -> String {
+        self.0.iter().fold(text.to_string(), |text, (name, value)| {
+            let escaped = serde_json::to_string(value).unwrap_or_default();
+            text.replace(&format!("{{{{{}}}}}", name), &escaped[1..escaped.len() - 1])
+        })
+    }
+}
+
+/// Параметры копирования шаблона
+#[derive(Debug, Clone, Default)]
+pub struct CopyOptions {
+    /// Стратегия для файлов, которых нет в `files`
+    pub strategy: MergeStrategy,
+    /// Стратегии отдельных файлов: путь относительно шаблона с `/`
+    pub files: BTreeMap<String, MergeStrategy>,
+    pub variables: Variables,
+}
+
+impl CopyOptions {
+    fn strategy_for(&self, relative: &Path) -> MergeStrategy {
+        let key = relative.to_string_lossy().replace('\\', "/");
+        self.files
+            .iter()
+            .find(|(path, _)| path.eq_ignore_ascii_case(&key))
+            .map_or(self.strategy, |(_, strategy)| *strategy)
+    }
+}
+
+/// Что стало с файлом после копирования
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// Путь относительно папки назначения
+    pub path: PathBuf,
+    pub status: FileStatus,
+}
+
+impl fmt::Display for FileChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mark = match self.status {
+            FileStatus::Created => '+',
+            FileStatus::Updated => '~',
+            FileStatus::Unchanged => '=',
+        };
+        write!(f, "{} {}", mark, self.path.display())
+    }
+}
+
+/// Файлы директории и её поддиректорий: пути относительно `root`, по алфавиту
+fn walk(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(root.join(relative))?.collect::<io::Result<_>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            walk(root, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn invalid_data(path: &Path, message: impl fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), message))
+}
+
+/// Рекурсивно сливает объекты; остальные значения шаблон заменяет
+fn merge_json(target: &mut Value, template: Value) {
+    match (target, template) {
+        (Value::Object(target), Value::Object(template)) => {
+            for (key, value) in template {
+                match target.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, template) => *target = template,
+    }
+}
+
+fn ini_section(line: &str) -> Option<&str> {
+    line.strip_prefix('[').and_then(|l| l.strip_suffix(']')).map(str::trim)
+}
+
+fn ini_key(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.starts_with(';') || line.starts_with('#') {
+        return None;
+    }
+    line.split_once('=').map(|(key, _)| key.trim())
+}
+
+/// Записывает `key=…` в секцию: заменяет строку с тем же ключом
+/// или добавляет после последней строки секции; секции нет — дописывает её в конец
+fn set_ini_line(lines: &mut Vec<String>, section: &str, key: &str, line: &str) {
+    let mut current = "";
+    let mut section_end = if section.is_empty() { Some(0) } else { None };
+    for (i, existing) in lines.iter().enumerate() {
+        if let Some(name) = ini_section(existing.trim()) {
+            current = name;
+            if current.eq_ignore_ascii_case(section) {
+                section_end = Some(i + 1);
+            }
+            continue;
+        }
+        if !current.eq_ignore_ascii_case(section) {
+            continue;
+        }
+        if ini_key(existing).is_some_and(|k| k.eq_ignore_ascii_case(key)) {
+            lines[i] = line.to_string();
+            return;
+        }
+        if !existing.trim().is_empty() {
+            section_end = Some(i + 1);
+        }
+    }
+    match section_end {
+        Some(end) => lines.insert(end, line.to_string()),
+        None => {
+            if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", section));
+            lines.push(line.to_string());
+        }
+    }
+}
+
+fn merge_ini(existing: &str, template: &str) -> Vec<String> {
+    let mut lines: Vec<String> = existing.lines().map(String::from).collect();
+    let mut section = String::new();
+    for line in template.lines().map(str::trim) {
+        if let Some(name) = ini_section(line) {
+            section = name.to_string();
+        } else if let Some(key) = ini_key(line) {
+            set_ini_line(&mut lines, &section, key, line);
+        }
+    }
+    lines
+}
+
+fn append_lines(existing: &str, template: &str) -> Vec<String> {
+    let mut lines: Vec<String> = existing.lines().map(String::from).collect();
+    for line in template.lines() {
+        if !line.trim().is_empty() && !lines.iter().any(|l| l.trim_end() == line.trim_end()) {
+            lines.push(line.to_string());
+        }
+    }
+    lines
+}
+
+/// Строки обратно в текст с переводами строк исходного файла
+fn join_lines(lines: &[String], existing: &str) -> Vec<u8> {
+    let newline = if existing.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut text = lines.join(newline);
+    text.push_str(newline);
+    text.into_bytes()
+}
+
+/// Новое содержимое файла по стратегии; `None` — файл остаётся как есть
+fn merged_contents(strategy: MergeStrategy, path: &Path, existing: Option<&[u8]>, template: Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+    let Some(existing) = existing else {
+        return Ok(Some(template));
+    };
+    let text = |bytes: &[u8]| -> io::Result<String> {
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid_data(path, "файл не в UTF-8"))
+    };
+    let contents = match strategy {
+        MergeStrategy::Overwrite => template,
+        MergeStrategy::SkipIfExists => return Ok(None),
+        MergeStrategy::JsonMerge => {
+            let parse = |bytes: &[u8]| serde_json::from_slice::<Value>(bytes).map_err(|e| invalid_data(path, e));
+            let current = parse(existing)?;
+            let mut merged = current.clone();
+            merge_json(&mut merged, parse(&template)?);
+            if merged == current {
+                return Ok(None);
+            }
+            let mut json = serde_json::to_string_pretty(&merged).map_err(|e| invalid_data(path, e))?;
+            json.push('\n');
+            json.into_bytes()
+        }
+        MergeStrategy::IniMerge => {
+            let existing = text(existing)?;
+            join_lines(&merge_ini(&existing, &text(&template)?), &existing)
+        }
+        MergeStrategy::AppendLines => {
+            let existing = text(existing)?;
+            join_lines(&append_lines(&existing, &text(&template)?), &existing)
+        }
+    };
+    Ok(Some(contents))
+}
+
+/// Рекурсивно копирует шаблон конфигурации приложения в `dest_dir`.
+/// В текстовых файлах подставляются переменные, существующие файлы
+/// обрабатываются по стратегии. Возвращает изменение по каждому файлу.
+pub fn copy_config_files(app_name: &str, source_dir: &Path, dest_dir: &Path, options: &CopyOptions) -> io::Result<Vec<FileChange>> {
+    progress!("📋 Копирование конфигурационных файлов для {}...", app_name);
+
+    if !source_dir.is_dir() {
         return Err(io::Error::new(
             io::ErrorKind::NotFound,
-            format!("Директория с конфигурациями не найдена: {:?}", source_dir)
+            format!("Директория с конфигурациями не найдена: {}", source_dir.display()),
         ));
     }
-    
-    create_directory_if_not_exists(dest_dir.to_str().unwrap())?;
-    
-    for entry in fs::read_dir(source_dir)? {
-        let entry = entry?;
-        let source_file = entry.path();
-        
-        if source_file.is_file() {
-            let file_name = source_file.file_name().unwrap();
-            let dest_file = dest_dir.join(file_name);
-            
-            fs::copy(&source_file, &dest_file)?;
-            println!("  ✅ Скопирован: {:?}", file_name);
+
+    let mut files = Vec::new();
+    walk(source_dir, Path::new(""), &mut files)?;
+    let mut changes = Vec::new();
+    for relative in files {
+        let strategy = options.strategy_for(&relative);
+        let mut template = fs::read(source_dir.join(&relative))?;
+        if let Ok(text) = std::str::from_utf8(&template) {
+            let is_json = strategy == MergeStrategy::JsonMerge
+                || relative.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+            template = if is_json { options.variables.substitute_json(text) } else { options.variables.substitute(text) }.into_bytes();
         }
+
+        let target = dest_dir.join(&relative);
+        let existing = match fs::read(&target) {
+            Ok(bytes) => Some(bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let status = match merged_contents(strategy, &relative, existing.as_deref(), template)? {
+            Some(contents) if existing.as_deref() != Some(contents.as_slice()) => {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, contents)?;
+                if existing.is_some() { FileStatus::Updated } else { FileStatus::Created }
+            }
+            _ => FileStatus::Unchanged,
+        };
+        let change = FileChange { path: relative, status };
+        progress!("  {}", change);
+        changes.push(change);
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::temp_dir;
+
+    fn write(dir: &Path, path: &str, text: &str) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn test_merge_ini_and_lines() {
+        let existing = "; мои настройки\n[General]\nTheme=dark\nFont=Consolas\n\n[Proxy]\nEnabled=0\n";
+        let template = "[General]\nTheme=light\nLanguage=ru\n[Updates]\nChannel=stable\n";
+        assert_eq!(
+            merge_ini(existing, template).join("\n"),
+            "; мои настройки\n[General]\nTheme=light\nFont=Consolas\nLanguage=ru\n\n[Proxy]\nEnabled=0\n\n[Updates]\nChannel=stable"
+        );
+        assert_eq!(append_lines("a\r\nb\r\n", "b\nc\n").join("|"), "a|b|c");
+    }
+
+    #[test]
+    fn test_copy_config_files() {
+        let source = temp_dir("copy-source");
+        write(&source, "settings.json", r#"{ "editor": { "fontSize": 14 }, "home": "{{APPDATA}}\\app" }"#);
+        write(&source, "conf/app.ini", "[User]\nName={{USERNAME}}\n");
+        write(&source, "conf/hosts.txt", "10.0.0.1 git.local\n");
+        write(&source, "readme.txt", "шаблон");
+
+        let dest = temp_dir("copy-dest");
+        write(&dest, "settings.json", r#"{ "editor": { "tabSize": 2 } }"#);
+        write(&dest, "conf/hosts.txt", "127.0.0.1 localhost\n");
+        write(&dest, "readme.txt", "свой");
+
+        let options = CopyOptions {
+            strategy: MergeStrategy::Overwrite,
+            files: BTreeMap::from([
+                ("settings.json".to_string(), MergeStrategy::JsonMerge),
+                ("conf/hosts.txt".to_string(), MergeStrategy::AppendLines),
+                ("readme.txt".to_string(), MergeStrategy::SkipIfExists),
+            ]),
+            variables: Variables::default().with("APPDATA", r"C:\Users\ivan\AppData\Roaming").with("USERNAME", "ivan"),
+        };
+        let changes = copy_config_files("app", &source, &dest, &options).unwrap();
+        let statuses: Vec<(String, FileStatus)> =
+            changes.iter().map(|c| (c.path.to_string_lossy().replace('\\', "/"), c.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("conf/app.ini".to_string(), FileStatus::Created),
+                ("conf/hosts.txt".to_string(), FileStatus::Updated),
+                ("readme.txt".to_string(), FileStatus::Unchanged),
+                ("settings.json".to_string(), FileStatus::Updated),
+            ]
+        );
+
+        let settings: Value = serde_json::from_str(&fs::read_to_string(dest.join("settings.json")).unwrap()).unwrap();
+        assert_eq!(settings["editor"], serde_json::json!({ "fontSize": 14, "tabSize": 2 }));
+        assert_eq!(settings["home"], r"C:\Users\ivan\AppData\Roaming\app");
+        assert_eq!(fs::read_to_string(dest.join("conf").join("app.ini")).unwrap(), "[User]\nName=ivan\n");
+        assert_eq!(fs::read_to_string(dest.join("conf").join("hosts.txt")).unwrap(), "127.0.0.1 localhost\n10.0.0.1 git.local\n");
+
+        let again = copy_config_files("app", &source, &dest, &options).unwrap();
+        assert!(again.iter().all(|c| c.status == FileStatus::Unchanged));
+    }
+
+    #[test]
+    fn test_set_ini_line_global_keys() {
+        let mut lines = Vec::new();
+        set_ini_line(&mut lines, "", "Version", "Version=2");
+        assert_eq!(lines, vec!["Version=2"]);
+
+        // Ключи до первой секции: замена без учёта регистра, новые — после последнего глобального
+        let mut lines: Vec<String> = "; шапка\nVersion=1\n\n[General]\nTheme=dark".lines().map(String::from).collect();
+        set_ini_line(&mut lines, "", "version", "Version=2");
+        set_ini_line(&mut lines, "", "Portable", "Portable=1");
+        assert_eq!(lines.join("\n"), "; шапка\nVersion=2\nPortable=1\n\n[General]\nTheme=dark");
+
+        // Одноимённый ключ в секции не глобальный
+        let mut lines = vec!["[General]".to_string(), "Theme=dark".to_string()];
+        set_ini_line(&mut lines, "", "Theme", "Theme=light");
+        assert_eq!(lines.join("\n"), "Theme=light\n[General]\nTheme=dark");
+
+        assert_eq!(
+            merge_ini("Version=1\n[General]\nTheme=dark\n", "Version=3\n[General]\nTheme=light\n").join("\n"),
+            "Version=3\n[General]\nTheme=light"
+        );
+    }
+
+    #[test]
+    fn test_ini_merge_existing_file() {
+        let source = temp_dir("copy-ini-source");
+        write(&source, "app.ini", "Version=3\n[General]\ntheme=light\n; Language=en\n[Proxy]\nHost={{HOSTNAME}}\n");
+        let dest = temp_dir("copy-ini-dest");
+        write(&dest, "app.ini", "; мои настройки\r\n[general]\r\nTheme=dark\r\n; Font=Arial\r\nFont=Consolas\r\n");
+
+        let options = CopyOptions {
+            strategy: MergeStrategy::IniMerge,
+            variables: Variables::default().with("HOSTNAME", "WS-042"),
+            ..CopyOptions::default()
+        };
+        let changes = copy_config_files("app", &source, &dest, &options).unwrap();
+        assert_eq!(changes, vec![FileChange { path: PathBuf::from("app.ini"), status: FileStatus::Updated }]);
+        // Комментарии и переводы строк файла остаются, закомментированные ключи шаблона не добавляются
+        assert_eq!(
+            fs::read_to_string(dest.join("app.ini")).unwrap(),
+            "; мои настройки\r\nVersion=3\r\n[general]\r\ntheme=light\r\n; Font=Arial\r\nFont=Consolas\r\n\r\n[Proxy]\r\nHost=WS-042\r\n"
+        );
+        let again = copy_config_files("app", &source, &dest, &options).unwrap();
+        assert_eq!(again[0].status, FileStatus::Unchanged);
+    }
+
+    #[test]
+    fn test_json_merge_edge_cases() {
+        let path = Path::new("app.json");
+        let merge = |existing: &str, template: &str| {
+            merged_contents(MergeStrategy::JsonMerge, path, Some(existing.as_bytes()), template.as_bytes().to_vec())
+                .map(|contents| contents.map(|bytes| serde_json::from_slice::<Value>(&bytes).unwrap()))
+        };
+        // Массивы и значения не-объекты шаблон заменяет целиком
+        assert_eq!(
+            merge(r#"{ "list": [1, 2], "keep": true }"#, r#"{ "list": [3] }"#).unwrap(),
+            Some(serde_json::json!({ "list": [3], "keep": true }))
+        );
+        assert_eq!(merge(r#"{ "a": { "b": 1 } }"#, r#"{ "a": 5 }"#).unwrap(), Some(serde_json::json!({ "a": 5 })));
+        assert_eq!(merge("[1, 2]", r#"{ "a": 1 }"#).unwrap(), Some(serde_json::json!({ "a": 1 })));
+        assert_eq!(merge(r#"{ "a": 1 }"#, "[]").unwrap(), Some(serde_json::json!([])));
+        // Ничего нового — файл не переписывается и сохраняет своё форматирование
+        assert_eq!(merge(r#"{"a":1,"b":2}"#, r#"{ "a": 1 }"#).unwrap(), None);
+
+        let err = merge(r#"{ "a": "#, "{}").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("app.json: "));
+        assert_eq!(merge("{}", "{ oops }").unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Битый файл назначения останавливает копирование и остаётся как был
+        let source = temp_dir("copy-json-source");
+        write(&source, "settings.json", r#"{ "a": 1 }"#);
+        let dest = temp_dir("copy-json-dest");
+        write(&dest, "settings.json", "{ // комментарий\n}");
+        let options = CopyOptions { strategy: MergeStrategy::JsonMerge, ..CopyOptions::default() };
+        let err = copy_config_files("app", &source, &dest, &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(fs::read_to_string(dest.join("settings.json")).unwrap(), "{ // комментарий\n}");
+    }
+
+    #[test]
+    fn test_non_utf8_files() {
+        let path = Path::new("app.ini");
+        let binary = b"\xff\xfeName={{USERNAME}}\n".to_vec();
+        let merge = |strategy, existing: &[u8], template: &[u8]| merged_contents(strategy, path, Some(existing), template.to_vec());
+
+        assert_eq!(merge(MergeStrategy::Overwrite, b"old", &binary).unwrap(), Some(binary.clone()));
+        assert_eq!(merge(MergeStrategy::SkipIfExists, &binary, b"new").unwrap(), None);
+        for strategy in [MergeStrategy::IniMerge, MergeStrategy::AppendLines] {
+            let err = merge(strategy, &binary, b"[User]\nName=ivan\n").unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains("UTF-8"));
+            assert_eq!(merge(strategy, b"[User]\n", &binary).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+        assert_eq!(merge(MergeStrategy::JsonMerge, &binary, b"{}").unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Двоичный шаблон копируется байт в байт, без подстановок
+        let source = temp_dir("copy-binary-source");
+        fs::write(source.join("app.ini"), &binary).unwrap();
+        let dest = temp_dir("copy-binary-dest");
+        let options = CopyOptions { variables: Variables::default().with("USERNAME", "ivan"), ..CopyOptions::default() };
+        assert_eq!(copy_config_files("app", &source, &dest, &options).unwrap()[0].status, FileStatus::Created);
+        assert_eq!(fs::read(dest.join("app.ini")).unwrap(), binary);
+    }
+
+    #[test]
+    fn test_append_lines_and_strategy_edge_cases() {
+        let path = Path::new("hosts");
+        // Последняя строка без перевода, CRLF файла сохраняется, пробелы в конце и пустые строки шаблона не считаются
+        let appended = merged_contents(MergeStrategy::AppendLines, path, Some(b"a\r\nb"), b"b \nc\n\n".to_vec()).unwrap();
+        assert_eq!(appended, Some(b"a\r\nb\r\nc\r\n".to_vec()));
+        // Файла ещё нет — любая стратегия кладёт шаблон как есть
+        for strategy in [MergeStrategy::SkipIfExists, MergeStrategy::JsonMerge, MergeStrategy::IniMerge, MergeStrategy::AppendLines] {
+            assert_eq!(merged_contents(strategy, path, None, b"x".to_vec()).unwrap(), Some(b"x".to_vec()));
+        }
+
+        let options = CopyOptions {
+            files: BTreeMap::from([("conf/app.ini".to_string(), MergeStrategy::IniMerge)]),
+            ..CopyOptions::default()
+        };
+        assert_eq!(options.strategy_for(&Path::new("CONF").join("App.INI")), MergeStrategy::IniMerge);
+        assert_eq!(options.strategy_for(Path::new("app.ini")), MergeStrategy::Overwrite);
+
+        let missing = temp_dir("copy-missing").join("nothing");
+        let err = copy_config_files("app", &missing, &missing, &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
     }
-    
-    Ok(())
 }
@@ -1,3 +1,7 @@
 pub mod admin;
+pub mod command;
 pub mod registry;
 pub mod filesystem;
+pub mod progress;
+#[cfg(test)]
+pub mod test_support;
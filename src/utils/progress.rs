@@ -0,0 +1,281 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{self, error::TryRecvError, UnboundedReceiver, UnboundedSender};
+
+/// Событие фоновой задачи
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskEvent {
+    Started(String),
+    /// Строка вывода: сообщение модуля или строка вывода внешней команды
+    Output(String),
+    Finished { success: bool, message: String },
+}
+
+/// Куда задача отправляет вывод и откуда узнаёт об отмене
+#[derive(Debug, Clone)]
+pub struct Progress {
+    sender: UnboundedSender<TaskEvent>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Progress {
+    pub fn new(sender: UnboundedSender<TaskEvent>, cancel: Arc<AtomicBool>) -> Self {
+        Self { sender, cancel }
+    }
+
+    pub fn send(&self, event: TaskEvent) {
+        // Получатель мог уже закрыть окно задачи — вывод тогда не нужен
+        let _ = self.sender.send(event);
+    }
+
+    pub fn line(&self, line: &str) {
+        self.send(TaskEvent::Output(line.to_string()));
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Progress>> = const { RefCell::new(None) };
+}
+
+/// Выполняет `f`, направляя вывод модулей в `progress` вместо stdout
+pub fn with_progress<R>(progress: Progress, f: impl FnOnce() -> R) -> R {
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            CURRENT.with(|c| c.borrow_mut().take());
+        }
+    }
+
+    CURRENT.with(|c| *c.borrow_mut() = Some(progress));
+    let _reset = Reset;
+    f()
+}
+
+/// Получатель вывода текущего потока, если он выполняет фоновую задачу
+pub fn current() -> Option<Progress> {
+    CURRENT.with(|c| c.borrow().clone())
+}
+
+/// Отменена ли задача, которую выполняет текущий поток
+pub fn is_cancelled() -> bool {
+    current().is_some_and(|p| p.is_cancelled())
+}
+
+/// Выводит строку: в журнал фоновой задачи или, вне задачи, в stderr,
+/// чтобы не смешивать ход работы с результатом команды в stdout
+pub fn emit(line: &str) {
+    match current() {
+        Some(progress) => progress.line(line),
+        None => eprintln!("{}", line),
+    }
+}
+
+/// Вывод хода работы для модулей: в фоновой задаче строка попадает в её журнал
+#[macro_export]
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        $crate::utils::progress::emit(&format!($($arg)*))
+    };
+}
+
+/// Итог фоновой задачи
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskOutcome {
+    pub success: bool,
+    pub message: String,
+}
+
+/// Значение, которое задача возвращает интерфейсу вместе с итогом
+type TaskResult = Box<dyn Any + Send>;
+
+/// Длительная операция, выполняемая в фоне.
+/// Интерфейс забирает события через `poll` и остаётся отзывчивым.
+#[derive(Debug)]
+pub struct BackgroundTask {
+    pub title: String,
+    /// Накопленный вывод задачи
+    pub log: Vec<String>,
+    outcome: Option<TaskOutcome>,
+    receiver: UnboundedReceiver<TaskEvent>,
+    cancel: Arc<AtomicBool>,
+    /// Заполняется до события `Finished`
+    result: Arc<Mutex<Option<TaskResult>>>,
+}
+
+impl BackgroundTask {
+    /// Запускает `work` в пуле блокирующих потоков tokio.
+    /// `work` возвращает итоговое сообщение об успехе.
+    pub fn spawn<F>(handle: &Handle, title: &str, work: F) -> Self
+    where
+        F: FnOnce() -> io::Result<String> + Send + 'static,
+    {
+        Self::start(handle, title, move || work().map(|message| (message, None)))
+    }
+
+    /// Запускает загрузку данных для интерфейса: значение забирается через
+    /// `take_result`, когда задача завершилась успешно
+    pub fn spawn_with_result<T, F>(handle: &Handle, title: &str, work: F) -> Self
+    where
+        T: Send + 'static,
+        F: FnOnce() -> io::Result<T> + Send + 'static,
+    {
+        Self::start(handle, title, move || work().map(|value| ("Готово".to_string(), Some(Box::new(value) as TaskResult))))
+    }
+
+    fn start<F>(handle: &Handle, title: &str, work: F) -> Self
+    where
+        F: FnOnce() -> io::Result<(String, Option<TaskResult>)> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let progress = Progress::new(sender, cancel.clone());
+        let task_title = title.to_string();
+        let result = Arc::new(Mutex::new(None));
+        let slot = Arc::clone(&result);
+
+        handle.spawn_blocking(move || {
+            progress.send(TaskEvent::Started(task_title));
+            let result = with_progress(progress.clone(), work).map(|(message, value)| {
+                *slot.lock().unwrap() = value;
+                message
+            });
+            let (success, message) = match result {
+                Ok(message) => (true, message),
+                Err(_) if progress.is_cancelled() => (false, "Отменено".to_string()),
+                Err(e) => (false, e.to_string()),
+            };
+            progress.send(TaskEvent::Finished { success, message });
+        });
+
+        Self {
+            title: title.to_string(),
+            log: Vec::new(),
+            outcome: None,
+            receiver,
+            cancel,
+            result,
+        }
+    }
+
+    /// Значение успешно завершённой задачи из `spawn_with_result`; забирается один раз
+    pub fn take_result<T: 'static>(&mut self) -> Option<T> {
+        if !self.outcome.as_ref().is_some_and(|o| o.success) {
+            return None;
+        }
+        let value = self.result.lock().unwrap().take()?;
+        value.downcast().ok().map(|value| *value)
+    }
+
+    /// Забирает накопившиеся события; возвращает true, если что-то изменилось
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(TaskEvent::Started(title)) => self.log.push(format!("▶ {}", title)),
+                Ok(TaskEvent::Output(line)) => self.log.push(line),
+                Ok(TaskEvent::Finished { success, message }) => {
+                    self.outcome = Some(TaskOutcome { success, message });
+                }
+                Err(TryRecvError::Empty) => return changed,
+                Err(TryRecvError::Disconnected) => {
+                    // Поток задачи завершился без итога — значит, упал с паникой
+                    if self.outcome.is_none() {
+                        self.outcome = Some(TaskOutcome {
+                            success: false,
+                            message: "задача завершилась аварийно".to_string(),
+                        });
+                        changed = true;
+                    }
+                    return changed;
+                }
+            }
+            changed = true;
+        }
+    }
+
+    /// Просит задачу остановиться: запущенная команда будет прервана
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.outcome.is_none()
+    }
+
+    pub fn outcome(&self) -> Option<&TaskOutcome> {
+        self.outcome.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn wait(task: &mut BackgroundTask) {
+        for _ in 0..500 {
+            task.poll();
+            if !task.is_running() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("задача не завершилась");
+    }
+
+    #[test]
+    fn test_output_goes_to_current_task() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        with_progress(Progress::new(sender, cancel.clone()), || {
+            crate::progress!("строка {}", 1);
+            assert!(!is_cancelled());
+            cancel.store(true, Ordering::Relaxed);
+            assert!(is_cancelled());
+        });
+
+        assert_eq!(receiver.try_recv().unwrap(), TaskEvent::Output("строка 1".to_string()));
+        assert!(current().is_none());
+    }
+
+    #[test]
+    fn test_background_task_reports_events() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut task = BackgroundTask::spawn(runtime.handle(), "Установка", || {
+            crate::progress!("шаг 1");
+            Ok("готово".to_string())
+        });
+        wait(&mut task);
+        assert_eq!(task.log, vec!["▶ Установка", "шаг 1"]);
+        assert_eq!(task.outcome(), Some(&TaskOutcome { success: true, message: "готово".to_string() }));
+
+        let mut task = BackgroundTask::spawn(runtime.handle(), "Долгая операция", || {
+            while !is_cancelled() {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Err(io::Error::from(io::ErrorKind::Interrupted))
+        });
+        task.cancel();
+        wait(&mut task);
+        assert_eq!(task.outcome().unwrap().message, "Отменено");
+        assert_eq!(task.take_result::<()>(), None);
+
+        let mut task = BackgroundTask::spawn_with_result(runtime.handle(), "Загрузка", || Ok(vec![1, 2, 3]));
+        wait(&mut task);
+        assert_eq!(task.take_result::<Vec<i32>>(), Some(vec![1, 2, 3]));
+        assert_eq!(task.take_result::<Vec<i32>>(), None);
+    }
+}
@@ -1,58 +1,500 @@
+use std::collections::BTreeMap;
+use std::fmt;
 use std::io;
-use winreg::enums::*;
-use winreg::{RegKey, HKEY};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
 
-/// Читает значение из реестра
-pub fn read_registry_value(hkey: HKEY, path: &str, name: &str) -> io::Result<String> {
-    let key = RegKey::predef(hkey);
-    let subkey = key.open_subkey(path)?;
-    let value: String = subkey.get_value(name)?;
-    Ok(value)
+/// Корневой раздел реестра
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum RegRoot {
+    #[serde(rename = "HKCR")]
+    ClassesRoot,
+    #[serde(rename = "HKCU")]
+    CurrentUser,
+    #[serde(rename = "HKLM")]
+    LocalMachine,
+    #[serde(rename = "HKU")]
+    Users,
 }
 
-/// Записывает строковое значение в реестр
-pub fn write_registry_string(hkey: HKEY, path: &str, name: &str, value: &str) -> io::Result<()> {
-    let key = RegKey::predef(hkey);
-    let (subkey, _) = key.create_subkey(path)?;
-    subkey.set_value(name, &value)?;
+impl RegRoot {
+    /// Короткое имя раздела (HKCU, HKLM, ...)
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            RegRoot::ClassesRoot => "HKCR",
+            RegRoot::CurrentUser => "HKCU",
+            RegRoot::LocalMachine => "HKLM",
+            RegRoot::Users => "HKU",
+        }
+    }
+
+    /// Полное имя раздела (HKEY_CURRENT_USER, ...)
+    pub fn long_name(&self) -> &'static str {
+        match self {
+            RegRoot::ClassesRoot => "HKEY_CLASSES_ROOT",
+            RegRoot::CurrentUser => "HKEY_CURRENT_USER",
+            RegRoot::LocalMachine => "HKEY_LOCAL_MACHINE",
+            RegRoot::Users => "HKEY_USERS",
+        }
+    }
+}
+
+impl fmt::Display for RegRoot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.short_name())
+    }
+}
+
+/// Значение реестра любого поддерживаемого типа.
+/// В JSON записывается как `{"type": "dword", "data": 1}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum RegValue {
+    String(String),
+    ExpandString(String),
+    MultiString(Vec<String>),
+    Dword(u32),
+    Qword(u64),
+    Binary(Vec<u8>),
+}
+
+impl fmt::Display for RegValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegValue::String(s) | RegValue::ExpandString(s) => write!(f, "\"{}\"", s),
+            RegValue::MultiString(v) => write!(f, "{:?}", v),
+            RegValue::Dword(v) => write!(f, "{} (DWORD)", v),
+            RegValue::Qword(v) => write!(f, "{} (QWORD)", v),
+            RegValue::Binary(b) => {
+                let hex: Vec<String> = b.iter().map(|byte| format!("{:02x}", byte)).collect();
+                write!(f, "hex:{}", hex.join(","))
+            }
+        }
+    }
+}
+
+/// Доступ к реестру. Все настройки работают через этот трейт,
+/// поэтому их можно проверять без настоящего реестра Windows.
+pub trait RegistryBackend: Send + Sync {
+    /// Проверяет, можно ли открыть ключ
+    fn key_exists(&self, root: RegRoot, path: &str) -> bool;
+
+    /// Создаёт ключ вместе с недостающими родителями
+    fn create_key(&self, root: RegRoot, path: &str) -> io::Result<()>;
+
+    /// Удаляет ключ со всеми подключами
+    fn delete_key(&self, root: RegRoot, path: &str) -> io::Result<()>;
+
+    /// Читает значение. Отсутствующий ключ или значение — `NotFound`
+    fn read_value(&self, root: RegRoot, path: &str, name: &str) -> io::Result<RegValue>;
+
+    /// Записывает значение, создавая ключ при необходимости
+    fn write_value(&self, root: RegRoot, path: &str, name: &str, value: &RegValue) -> io::Result<()>;
+
+    /// Удаляет значение
+    fn delete_value(&self, root: RegRoot, path: &str, name: &str) -> io::Result<()>;
+
+    /// Перечисляет значения ключа
+    fn list_values(&self, root: RegRoot, path: &str) -> io::Result<Vec<(String, RegValue)>>;
+
+    /// Перечисляет имена прямых подключей
+    fn list_subkeys(&self, root: RegRoot, path: &str) -> io::Result<Vec<String>>;
+}
+
+/// Содержимое ключа со всеми подключами, чтобы восстановить удалённый ключ
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeySnapshot {
+    pub values: Vec<(String, RegValue)>,
+    pub subkeys: Vec<(String, KeySnapshot)>,
+}
+
+/// Сохраняет содержимое ключа
+pub fn snapshot_key(reg: &dyn RegistryBackend, root: RegRoot, path: &str) -> io::Result<KeySnapshot> {
+    let mut snapshot = KeySnapshot {
+        values: reg.list_values(root, path)?,
+        subkeys: Vec::new(),
+    };
+    for name in reg.list_subkeys(root, path)? {
+        let child = snapshot_key(reg, root, &format!("{}\\{}", path, name))?;
+        snapshot.subkeys.push((name, child));
+    }
+    Ok(snapshot)
+}
+
+/// Воссоздаёт ключ из снимка
+pub fn restore_key(reg: &dyn RegistryBackend, root: RegRoot, path: &str, snapshot: &KeySnapshot) -> io::Result<()> {
+    reg.create_key(root, path)?;
+    for (name, value) in &snapshot.values {
+        reg.write_value(root, path, name, value)?;
+    }
+    for (name, child) in &snapshot.subkeys {
+        restore_key(reg, root, &format!("{}\\{}", path, name), child)?;
+    }
     Ok(())
 }
 
+/// Самый верхний ключ пути, которого ещё нет (его создаст запись значения)
+pub fn first_missing_key(reg: &dyn RegistryBackend, root: RegRoot, path: &str) -> Option<String> {
+    let mut current = String::new();
+    for part in path.split('\\') {
+        if !current.is_empty() {
+            current.push('\\');
+        }
+        current.push_str(part);
+        if !reg.key_exists(root, &current) {
+            return Some(current);
+        }
+    }
+    None
+}
+
+/// Реестр Windows через winreg
+#[cfg(windows)]
+pub struct WinRegistry;
+
+#[cfg(windows)]
+mod win {
+    use super::{RegRoot, RegValue, RegistryBackend, WinRegistry};
+    use std::io;
+    use winreg::enums::*;
+    use winreg::types::{FromRegValue, ToRegValue};
+    use winreg::{RegKey, HKEY};
+
+    fn predef(root: RegRoot) -> RegKey {
+        let hkey: HKEY = match root {
+            RegRoot::ClassesRoot => HKEY_CLASSES_ROOT,
+            RegRoot::CurrentUser => HKEY_CURRENT_USER,
+            RegRoot::LocalMachine => HKEY_LOCAL_MACHINE,
+            RegRoot::Users => HKEY_USERS,
+        };
+        RegKey::predef(hkey)
+    }
+
+    fn from_raw(raw: winreg::RegValue) -> io::Result<RegValue> {
+        let value = match raw.vtype {
+            REG_SZ => RegValue::String(String::from_reg_value(&raw)?),
+            REG_EXPAND_SZ => RegValue::ExpandString(String::from_reg_value(&raw)?),
+            REG_MULTI_SZ => RegValue::MultiString(Vec::<String>::from_reg_value(&raw)?),
+            REG_DWORD => RegValue::Dword(u32::from_reg_value(&raw)?),
+            REG_QWORD => RegValue::Qword(u64::from_reg_value(&raw)?),
+            _ => RegValue::Binary(raw.bytes),
+        };
+        Ok(value)
+    }
+
+    fn to_raw(value: &RegValue) -> winreg::RegValue {
+        match value {
+            RegValue::String(s) => s.to_reg_value(),
+            RegValue::ExpandString(s) => {
+                let mut raw = s.to_reg_value();
+                raw.vtype = REG_EXPAND_SZ;
+                raw
+            }
+            RegValue::MultiString(v) => v.to_reg_value(),
+            RegValue::Dword(v) => v.to_reg_value(),
+            RegValue::Qword(v) => v.to_reg_value(),
+            RegValue::Binary(b) => winreg::RegValue {
+                bytes: b.clone(),
+                vtype: REG_BINARY,
+            },
+        }
+    }
+
+    impl RegistryBackend for WinRegistry {
+        fn key_exists(&self, root: RegRoot, path: &str) -> bool {
+            predef(root).open_subkey(path).is_ok()
+        }
+
+        fn create_key(&self, root: RegRoot, path: &str) -> io::Result<()> {
+            predef(root).create_subkey(path)?;
+            Ok(())
+        }
+
+        fn delete_key(&self, root: RegRoot, path: &str) -> io::Result<()> {
+            predef(root).delete_subkey_all(path)
+        }
+
+        fn read_value(&self, root: RegRoot, path: &str, name: &str) -> io::Result<RegValue> {
+            let key = predef(root).open_subkey(path)?;
+            from_raw(key.get_raw_value(name)?)
+        }
+
+        fn write_value(&self, root: RegRoot, path: &str, name: &str, value: &RegValue) -> io::Result<()> {
+            let (key, _) = predef(root).create_subkey(path)?;
+            key.set_raw_value(name, &to_raw(value))
+        }
+
+        fn delete_value(&self, root: RegRoot, path: &str, name: &str) -> io::Result<()> {
+            let key = predef(root).open_subkey_with_flags(path, KEY_WRITE)?;
+            key.delete_value(name)
+        }
+
+        fn list_values(&self, root: RegRoot, path: &str) -> io::Result<Vec<(String, RegValue)>> {
+            let key = predef(root).open_subkey(path)?;
+            key.enum_values()
+                .map(|item| {
+                    let (name, raw) = item?;
+                    Ok((name, from_raw(raw)?))
+                })
+                .collect()
+        }
+
+        fn list_subkeys(&self, root: RegRoot, path: &str) -> io::Result<Vec<String>> {
+            predef(root).open_subkey(path)?.enum_keys().collect()
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MemoryKey {
+    /// Имена значений в нижнем регистре -> значение
+    values: BTreeMap<String, RegValue>,
+}
+
+/// Реестр в памяти. Имена ключей и значений, как и в Windows,
+/// не зависят от регистра.
+#[derive(Debug, Default)]
+pub struct MemoryRegistry {
+    keys: Mutex<BTreeMap<(RegRoot, String), MemoryKey>>,
+    read_only: Mutex<Vec<(RegRoot, String)>>,
+}
+
+fn normalize(path: &str) -> String {
+    path.trim_matches('\\').to_lowercase()
+}
+
+fn not_found(root: RegRoot, path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{}\\{} не найден", root, path))
+}
+
+impl MemoryRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Запрещает запись в ключ и его подключи (имитация отсутствия прав)
+    pub fn deny_writes(&self, root: RegRoot, path: &str) {
+        self.read_only.lock().unwrap().push((root, normalize(path)));
+    }
+
+    fn check_writable(&self, root: RegRoot, path: &str) -> io::Result<()> {
+        let denied = self.read_only.lock().unwrap().iter().any(|(r, p)| {
+            *r == root && (path == p || path.starts_with(&format!("{}\\", p)))
+        });
+        if denied {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{}\\{}: отказано в доступе", root, path),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl RegistryBackend for MemoryRegistry {
+    fn key_exists(&self, root: RegRoot, path: &str) -> bool {
+        self.keys.lock().unwrap().contains_key(&(root, normalize(path)))
+    }
+
+    fn create_key(&self, root: RegRoot, path: &str) -> io::Result<()> {
+        let path = normalize(path);
+        self.check_writable(root, &path)?;
+        let mut keys = self.keys.lock().unwrap();
+        let mut current = String::new();
+        for part in path.split('\\') {
+            if !current.is_empty() {
+                current.push('\\');
+            }
+            current.push_str(part);
+            keys.entry((root, current.clone())).or_default();
+        }
+        Ok(())
+    }
+
+    fn delete_key(&self, root: RegRoot, path: &str) -> io::Result<()> {
+        let path = normalize(path);
+        self.check_writable(root, &path)?;
+        let mut keys = self.keys.lock().unwrap();
+        if !keys.contains_key(&(root, path.clone())) {
+            return Err(not_found(root, &path));
+        }
+        let prefix = format!("{}\\", path);
+        keys.retain(|(r, p), _| !(*r == root && (*p == path || p.starts_with(&prefix))));
+        Ok(())
+    }
+
+    fn read_value(&self, root: RegRoot, path: &str, name: &str) -> io::Result<RegValue> {
+        let keys = self.keys.lock().unwrap();
+        keys.get(&(root, normalize(path)))
+            .and_then(|key| key.values.get(&name.to_lowercase()))
+            .cloned()
+            .ok_or_else(|| not_found(root, &format!("{}\\{}", path, name)))
+    }
+
+    fn write_value(&self, root: RegRoot, path: &str, name: &str, value: &RegValue) -> io::Result<()> {
+        self.create_key(root, path)?;
+        let mut keys = self.keys.lock().unwrap();
+        let key = keys.entry((root, normalize(path))).or_default();
+        key.values.insert(name.to_lowercase(), value.clone());
+        Ok(())
+    }
+
+    fn delete_value(&self, root: RegRoot, path: &str, name: &str) -> io::Result<()> {
+        let path = normalize(path);
+        self.check_writable(root, &path)?;
+        let mut keys = self.keys.lock().unwrap();
+        let key = keys.get_mut(&(root, path.clone())).ok_or_else(|| not_found(root, &path))?;
+        key.values
+            .remove(&name.to_lowercase())
+            .map(|_| ())
+            .ok_or_else(|| not_found(root, &format!("{}\\{}", path, name)))
+    }
+
+    fn list_values(&self, root: RegRoot, path: &str) -> io::Result<Vec<(String, RegValue)>> {
+        let path = normalize(path);
+        let keys = self.keys.lock().unwrap();
+        let key = keys.get(&(root, path.clone())).ok_or_else(|| not_found(root, &path))?;
+        Ok(key.values.iter().map(|(n, v)| (n.clone(), v.clone())).collect())
+    }
+
+    fn list_subkeys(&self, root: RegRoot, path: &str) -> io::Result<Vec<String>> {
+        let path = normalize(path);
+        let keys = self.keys.lock().unwrap();
+        if !keys.contains_key(&(root, path.clone())) {
+            return Err(not_found(root, &path));
+        }
+        let prefix = format!("{}\\", path);
+        Ok(keys
+            .keys()
+            .filter(|(r, p)| *r == root && p.starts_with(&prefix))
+            .map(|(_, p)| &p[prefix.len()..])
+            .filter(|rest| !rest.contains('\\'))
+            .map(|rest| rest.to_string())
+            .collect())
+    }
+}
+
+/// Реестр текущей системы. Вне Windows — пустой реестр в памяти,
+/// чтобы интерфейс можно было запускать при разработке.
+pub fn system_registry() -> Box<dyn RegistryBackend> {
+    #[cfg(windows)]
+    {
+        Box::new(WinRegistry)
+    }
+    #[cfg(not(windows))]
+    {
+        Box::new(MemoryRegistry::new())
+    }
+}
+
+/// Читает строковое значение из реестра
+pub fn read_registry_value(reg: &dyn RegistryBackend, root: RegRoot, path: &str, name: &str) -> io::Result<String> {
+    match reg.read_value(root, path, name)? {
+        RegValue::String(s) | RegValue::ExpandString(s) => Ok(s),
+        other => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}\\{}\\{}: ожидалась строка, получено {}", root, path, name, other),
+        )),
+    }
+}
+
+/// Читает DWORD значение из реестра
+pub fn read_registry_dword(reg: &dyn RegistryBackend, root: RegRoot, path: &str, name: &str) -> io::Result<u32> {
+    match reg.read_value(root, path, name)? {
+        RegValue::Dword(v) => Ok(v),
+        other => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}\\{}\\{}: ожидался DWORD, получено {}", root, path, name, other),
+        )),
+    }
+}
+
+/// Записывает строковое значение в реестр
+pub fn write_registry_string(reg: &dyn RegistryBackend, root: RegRoot, path: &str, name: &str, value: &str) -> io::Result<()> {
+    reg.write_value(root, path, name, &RegValue::String(value.to_string()))
+}
+
 /// Записывает DWORD значение в реестр
-pub fn write_registry_dword(hkey: HKEY, path: &str, name: &str, value: u32) -> io::Result<()> {
-    let key = RegKey::predef(hkey);
-    let (subkey, _) = key.create_subkey(path)?;
-    subkey.set_value(name, &value)?;
-    Ok(())
+pub fn write_registry_dword(reg: &dyn RegistryBackend, root: RegRoot, path: &str, name: &str, value: u32) -> io::Result<()> {
+    reg.write_value(root, path, name, &RegValue::Dword(value))
 }
 
 /// Удаляет значение из реестра
-pub fn delete_registry_value(hkey: HKEY, path: &str, name: &str) -> io::Result<()> {
-    let key = RegKey::predef(hkey);
-    let subkey = key.open_subkey_with_flags(path, KEY_WRITE)?;
-    subkey.delete_value(name)?;
-    Ok(())
+pub fn delete_registry_value(reg: &dyn RegistryBackend, root: RegRoot, path: &str, name: &str) -> io::Result<()> {
+    reg.delete_value(root, path, name)
 }
 
 /// Удаляет ключ из реестра
-pub fn delete_registry_key(hkey: HKEY, path: &str) -> io::Result<()> {
-    let key = RegKey::predef(hkey);
-    key.delete_subkey_all(path)?;
-    Ok(())
+pub fn delete_registry_key(reg: &dyn RegistryBackend, root: RegRoot, path: &str) -> io::Result<()> {
+    reg.delete_key(root, path)
 }
 
 /// Проверяет существование ключа в реестре
-pub fn registry_key_exists(hkey: HKEY, path: &str) -> bool {
-    let key = RegKey::predef(hkey);
-    key.open_subkey(path).is_ok()
+pub fn registry_key_exists(reg: &dyn RegistryBackend, root: RegRoot, path: &str) -> bool {
+    reg.key_exists(root, path)
 }
 
 /// Проверяет существование значения в реестре
-pub fn registry_value_exists(hkey: HKEY, path: &str, name: &str) -> bool {
-    let key = RegKey::predef(hkey);
-    if let Ok(subkey) = key.open_subkey(path) {
-        subkey.get_value::<String, _>(name).is_ok()
-    } else {
-        false
+pub fn registry_value_exists(reg: &dyn RegistryBackend, root: RegRoot, path: &str, name: &str) -> bool {
+    reg.read_value(root, path, name).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_registry_is_case_insensitive() {
+        let reg = MemoryRegistry::new();
+        write_registry_dword(&reg, RegRoot::CurrentUser, "Software\\Test", "Value", 7).unwrap();
+
+        assert!(registry_key_exists(&reg, RegRoot::CurrentUser, "SOFTWARE\\test"));
+        assert!(registry_key_exists(&reg, RegRoot::CurrentUser, "software"));
+        assert!(!registry_key_exists(&reg, RegRoot::LocalMachine, "Software\\Test"));
+        assert_eq!(read_registry_dword(&reg, RegRoot::CurrentUser, "software\\TEST", "value").unwrap(), 7);
+    }
+
+    #[test]
+    fn test_memory_registry_delete() {
+        let reg = MemoryRegistry::new();
+        write_registry_string(&reg, RegRoot::CurrentUser, "A\\B\\C", "", "x").unwrap();
+        write_registry_string(&reg, RegRoot::CurrentUser, "A\\BB", "n", "y").unwrap();
+
+        delete_registry_key(&reg, RegRoot::CurrentUser, "A\\B").unwrap();
+        assert!(!registry_key_exists(&reg, RegRoot::CurrentUser, "A\\B\\C"));
+        assert!(registry_key_exists(&reg, RegRoot::CurrentUser, "A\\BB"));
+
+        delete_registry_value(&reg, RegRoot::CurrentUser, "A\\BB", "n").unwrap();
+        assert!(!registry_value_exists(&reg, RegRoot::CurrentUser, "A\\BB", "n"));
+        assert_eq!(
+            delete_registry_value(&reg, RegRoot::CurrentUser, "A\\BB", "n").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn test_snapshot_and_restore_key() {
+        let reg = MemoryRegistry::new();
+        write_registry_string(&reg, RegRoot::CurrentUser, "Classes\\X", "", "root").unwrap();
+        write_registry_dword(&reg, RegRoot::CurrentUser, "Classes\\X\\Sub\\Deep", "n", 5).unwrap();
+
+        let snapshot = snapshot_key(&reg, RegRoot::CurrentUser, "Classes\\X").unwrap();
+        delete_registry_key(&reg, RegRoot::CurrentUser, "Classes\\X").unwrap();
+        assert_eq!(first_missing_key(&reg, RegRoot::CurrentUser, "Classes\\X\\Sub"), Some("Classes\\X".to_string()));
+
+        restore_key(&reg, RegRoot::CurrentUser, "Classes\\X", &snapshot).unwrap();
+        assert_eq!(read_registry_value(&reg, RegRoot::CurrentUser, "Classes\\X", "").unwrap(), "root");
+        assert_eq!(read_registry_dword(&reg, RegRoot::CurrentUser, "Classes\\X\\Sub\\Deep", "n").unwrap(), 5);
+        assert_eq!(first_missing_key(&reg, RegRoot::CurrentUser, "Classes\\X\\Sub"), None);
+    }
+
+    #[test]
+    fn test_memory_registry_denied_writes() {
+        let reg = MemoryRegistry::new();
+        reg.deny_writes(RegRoot::LocalMachine, "SOFTWARE\\Policies");
+
+        let err = write_registry_dword(&reg, RegRoot::LocalMachine, "SOFTWARE\\Policies\\X", "v", 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(write_registry_dword(&reg, RegRoot::LocalMachine, "SOFTWARE\\Other", "v", 1).is_ok());
     }
 }